@@ -0,0 +1,84 @@
+//! Parsing and decompression costs: `cargo bench`
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::io::{Read, Write};
+use warc::bench;
+
+/// A response record with `block` as its block
+fn warc_record(block: &[u8]) -> Vec<u8> {
+    let mut record = format!(
+        "WARC/1.0\r\nWARC-Type: response\r\nWARC-Date: 2025-11-06T20:10:40Z\r\n\
+         WARC-Record-ID: <urn:uuid:00000000-0000-0000-0000-000000000000>\r\nContent-Length: {}\r\n\r\n",
+        block.len()
+    )
+    .into_bytes();
+    record.extend_from_slice(block);
+    record.extend_from_slice(b"\r\n\r\n");
+    record
+}
+
+/// A response record with a `text/plain` body of `len` bytes
+fn record_with_body(len: usize) -> Vec<u8> {
+    warc_record(&[&b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\n\r\n"[..], &vec![b'x'; len]].concat())
+}
+
+fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn example_warc() -> Vec<u8> {
+    std::fs::read("test-data/example.warc").expect("run from the crate root")
+}
+
+/// A large gzip record streamed through the parser, against decompressed
+/// into a buffer first
+fn streamed_gzip(c: &mut Criterion) {
+    let gzipped = gzip(&record_with_body(16 << 20));
+    let mut group = c.benchmark_group("gzip_16MiB");
+    group.sample_size(10);
+    group.bench_function("streamed", |b| b.iter(|| bench::parse_raw(black_box(&gzipped))));
+    group.bench_function("buffered", |b| {
+        b.iter(|| bench::with_decompressed(black_box(&gzipped), bench::parse))
+    });
+    group.finish();
+}
+
+/// A row decompressed into a buffer of its own
+fn decompress(gzipped: &[u8]) -> Vec<u8> {
+    let mut decompressed = Vec::new();
+    GzDecoder::new(gzipped).read_to_end(&mut decompressed).unwrap();
+    decompressed
+}
+
+/// A buffer per row against the thread's reused buffer
+fn reused_buffer(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decompress_buffer");
+    for (name, data) in [("2KiB", example_warc()), ("1MiB", record_with_body(1 << 20))] {
+        let gzipped = gzip(&data);
+        group.bench_with_input(BenchmarkId::new("per_row", name), &gzipped, |b, gzipped| {
+            b.iter(|| bench::parse_meta(&decompress(black_box(gzipped))))
+        });
+        group.bench_with_input(BenchmarkId::new("reused", name), &gzipped, |b, gzipped| {
+            b.iter(|| bench::with_decompressed(black_box(gzipped), bench::parse_meta))
+        });
+    }
+    group.finish();
+}
+
+/// Header fields alone against a full parse, with a body large enough for
+/// any copy of it to show
+fn meta_vs_full_parse(c: &mut Criterion) {
+    let data = warc_record(&[&b"HTTP/1.1 200 OK\r\nContent-Type: text/html\r\n\r\n"[..], &[b'x'; 256 * 1024]].concat());
+    let mut group = c.benchmark_group("record_256KiB");
+    group.bench_function("parse_meta", |b| b.iter(|| bench::parse_meta(black_box(&data))));
+    group.bench_function("parse", |b| b.iter(|| bench::parse(black_box(&data))));
+    group.finish();
+}
+
+criterion_group!(benches, streamed_gzip, reused_buffer, meta_vs_full_parse);
+criterion_main!(benches);
@@ -0,0 +1,143 @@
+use crate::read_varchar_list_column;
+use duckdb::{
+    core::{DataChunkHandle, Inserter, LogicalTypeHandle, LogicalTypeId},
+    types::DuckString,
+    vscalar::{ScalarFunctionSignature, VScalar},
+    vtab::arrow::WritableVector,
+};
+use libduckdb_sys::duckdb_string_t;
+use std::error::Error;
+use url::Url;
+
+/// Query parameters dropped by default: campaign tags and ad click IDs
+///
+/// A trailing `*` matches any parameter starting with the rest.
+const TRACKING_PARAMS: &[&str] = &[
+    "utm_*", "fbclid", "gclid", "gclsrc", "dclid", "msclkid", "mc_cid", "mc_eid", "igshid", "_ga",
+];
+
+/// Whether a parameter name matches a pattern, case-insensitively
+fn param_matches(pattern: &str, name: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => name.len() >= prefix.len() && name[..prefix.len()].eq_ignore_ascii_case(prefix),
+        None => name.eq_ignore_ascii_case(pattern),
+    }
+}
+
+/// A URI reduced to a dedup key
+///
+/// Query parameters matching `strip` are removed and the others sorted by
+/// name, then value; the host is lowercased. None when the URI doesn't parse.
+pub(crate) fn canonical_uri<S: AsRef<str>>(uri: &str, strip: &[S]) -> Option<String> {
+    let mut url = Url::parse(uri.trim()).ok()?;
+    if let Some(host) = url.host_str().map(str::to_ascii_lowercase) {
+        url.set_host(Some(&host)).ok()?;
+    }
+
+    let mut params: Vec<(String, String)> = url
+        .query_pairs()
+        .into_owned()
+        .filter(|(name, _)| !strip.iter().any(|pattern| param_matches(pattern.as_ref(), name)))
+        .collect();
+    params.sort();
+    if params.is_empty() {
+        url.set_query(None);
+    } else {
+        url.query_pairs_mut().clear().extend_pairs(params);
+    }
+    Some(url.into())
+}
+
+/// DuckDB scalar function turning a URI into a dedup key without tracking parameters
+///
+/// An optional VARCHAR[] argument replaces the default list of parameters to
+/// drop. Returns NULL for a NULL or unparseable URI.
+pub(crate) struct WarcCanonicalUri;
+
+impl VScalar for WarcCanonicalUri {
+    type State = ();
+
+    unsafe fn invoke(
+        _state: &Self::State,
+        input: &mut DataChunkHandle,
+        output: &mut dyn WritableVector,
+    ) -> Result<(), Box<dyn Error>> {
+        let size = input.len();
+        let input_vector = input.flat_vector(0);
+        let uris = input_vector.as_slice_with_len::<duckdb_string_t>(size);
+        let strip_lists = (input.num_columns() > 1).then(|| read_varchar_list_column(input, 1, size));
+        let mut output_vector = output.flat_vector();
+
+        for (i, uri) in uris.iter().enumerate() {
+            if input_vector.row_is_null(i as u64) {
+                output_vector.set_null(i);
+                continue;
+            }
+
+            let mut uri = *uri;
+            let uri = DuckString::new(&mut uri).as_str().to_string();
+            let canonical = match strip_lists.as_ref().and_then(|lists| lists[i].as_deref()) {
+                Some(strip) => canonical_uri(&uri, strip),
+                None => canonical_uri(&uri, TRACKING_PARAMS),
+            };
+            match canonical {
+                Some(canonical) => output_vector.insert(i, canonical.as_str()),
+                None => output_vector.set_null(i),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn signatures() -> Vec<ScalarFunctionSignature> {
+        vec![
+            ScalarFunctionSignature::exact(
+                vec![LogicalTypeHandle::from(LogicalTypeId::Varchar)],
+                LogicalTypeHandle::from(LogicalTypeId::Varchar),
+            ),
+            ScalarFunctionSignature::exact(
+                vec![
+                    LogicalTypeHandle::from(LogicalTypeId::Varchar),
+                    LogicalTypeHandle::list(&LogicalTypeHandle::from(LogicalTypeId::Varchar)),
+                ],
+                LogicalTypeHandle::from(LogicalTypeId::Varchar),
+            ),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canonical_uri_strips_tracking() {
+        let canonical = |uri: &str| canonical_uri(uri, TRACKING_PARAMS);
+        assert_eq!(
+            canonical("https://WWW.Example.com/Shop?utm_source=news&id=7&UTM_Medium=email&fbclid=abc&color=red")
+                .as_deref(),
+            Some("https://www.example.com/Shop?color=red&id=7")
+        );
+        // Marketing variants of one page share a key
+        assert_eq!(
+            canonical("https://example.com/a?gclid=1&b=2&a=1"),
+            canonical("https://example.com/a?a=1&utm_campaign=spring&b=2")
+        );
+        assert_eq!(canonical("https://example.com/?utm_source=x").as_deref(), Some("https://example.com/"));
+        assert_eq!(canonical("not a uri"), None);
+    }
+
+    #[test]
+    fn test_canonical_uri_custom_list() {
+        let uri = "https://example.com/?sessionid=9&ref_src=tw&utm_source=x&q=1";
+        assert_eq!(
+            canonical_uri(uri, &["sessionid", "ref_*"]).as_deref(),
+            Some("https://example.com/?q=1&utm_source=x")
+        );
+        // Nothing stripped, still sorted
+        assert_eq!(
+            canonical_uri("http://example.com/?b=1&a=2", &[] as &[&str]).as_deref(),
+            Some("http://example.com/?a=2&b=1")
+        );
+    }
+}
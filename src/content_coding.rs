@@ -0,0 +1,166 @@
+use flate2::read::{DeflateDecoder, MultiGzDecoder, ZlibDecoder};
+use std::borrow::Cow;
+use std::io::Read;
+
+/// Remove the transfer and content codings of an HTTP response body
+///
+/// Transfer codings come off first (RFC 9112 section 6.1), then content
+/// codings, each list undone from the last coding applied to the first. So
+/// a `chunked` + `gzip` body is de-chunked, then gunzipped. `gzip`,
+/// `deflate`, `br` and `zstd` are decoded and `identity` is a no-op. Bytes
+/// that don't decode are passed on as they are, as for a body the crawler
+/// already de-chunked but whose header it kept. An unknown coding (e.g.
+/// `compress`) stops the pipeline with the bytes decoded so far.
+pub(crate) fn decode_http_body<'a>(
+    body: &'a [u8],
+    transfer_encoding: Option<&str>,
+    content_encoding: Option<&str>,
+) -> Cow<'a, [u8]> {
+    let codings = |value: Option<&str>| -> Vec<String> {
+        value
+            .unwrap_or_default()
+            .split(',')
+            .map(|coding| coding.trim().to_ascii_lowercase())
+            .filter(|coding| !coding.is_empty())
+            .collect()
+    };
+
+    let mut decoded = Cow::Borrowed(body);
+    let transfer = codings(transfer_encoding);
+    let content = codings(content_encoding);
+    for coding in transfer.iter().rev().chain(content.iter().rev()) {
+        match decode_coding(&decoded, coding) {
+            Some(Some(bytes)) => decoded = Cow::Owned(bytes),
+            Some(None) => {}
+            None => break,
+        }
+    }
+    decoded
+}
+
+/// Whether a body carries any transfer or content coding besides `identity`
+pub(crate) fn has_codings(transfer_encoding: Option<&str>, content_encoding: Option<&str>) -> bool {
+    [transfer_encoding, content_encoding]
+        .into_iter()
+        .flatten()
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .any(|coding| !coding.is_empty() && !coding.eq_ignore_ascii_case("identity"))
+}
+
+/// Undo one coding: the decoded bytes, Some(None) when `data` stays as it
+/// is (identity, or bytes that don't decode) and None for unknown codings
+fn decode_coding(data: &[u8], coding: &str) -> Option<Option<Vec<u8>>> {
+    match coding {
+        "identity" => Some(None),
+        "chunked" => Some(dechunk(data)),
+        "gzip" | "x-gzip" => Some(read_all(MultiGzDecoder::new(data))),
+        // Servers send both zlib-wrapped (as specified) and raw deflate
+        "deflate" => Some(read_all(ZlibDecoder::new(data)).or_else(|| read_all(DeflateDecoder::new(data)))),
+        "br" => Some(read_all(brotli::Decompressor::new(data, 4096))),
+        "zstd" => Some(zstd::stream::read::Decoder::new(data).ok().and_then(read_all)),
+        _ => None,
+    }
+}
+
+fn read_all(mut reader: impl Read) -> Option<Vec<u8>> {
+    let mut decoded = Vec::new();
+    reader.read_to_end(&mut decoded).ok()?;
+    Some(decoded)
+}
+
+/// Join the chunks of a `chunked` body, dropping chunk extensions and trailers
+///
+/// A body cut off mid-chunk keeps the data read so far. None when a chunk
+/// size line isn't hex.
+fn dechunk(mut data: &[u8]) -> Option<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(data.len());
+    loop {
+        let line_end = data.iter().position(|&b| b == b'\n').unwrap_or(data.len());
+        let line = String::from_utf8_lossy(&data[..line_end]);
+        let size = line.split(';').next().unwrap_or_default().trim();
+        let size = usize::from_str_radix(size, 16).ok()?;
+        data = data.get(line_end + 1..).unwrap_or_default();
+        if size == 0 {
+            return Some(decoded);
+        }
+
+        let chunk = &data[..size.min(data.len())];
+        decoded.extend_from_slice(chunk);
+        if chunk.len() < size {
+            return Some(decoded);
+        }
+        data = &data[size..];
+        data = data.strip_prefix(b"\r").unwrap_or(data);
+        data = data.strip_prefix(b"\n").unwrap_or(data);
+        if data.is_empty() {
+            return Some(decoded);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gzip::gzip_compress;
+
+    #[test]
+    fn test_decode_chunked_gzip_body() {
+        let html = b"<html><title>Chunked and gzipped</title></html>";
+        let gzipped = gzip_compress(html).unwrap();
+        let (first, second) = gzipped.split_at(10);
+        let chunked = [
+            format!("{:x};name=value\r\n", first.len()).as_bytes(),
+            first,
+            b"\r\n",
+            format!("{:X}\r\n", second.len()).as_bytes(),
+            second,
+            b"\r\n0\r\nX-Trailer: 1\r\n\r\n",
+        ]
+        .concat();
+
+        let decoded = decode_http_body(&chunked, Some("chunked"), Some("gzip"));
+        assert_eq!(decoded.as_ref(), html);
+        // Listing the codings in one Transfer-Encoding undoes them last to first
+        assert_eq!(decode_http_body(&chunked, Some("gzip, Chunked"), None).as_ref(), html);
+        assert_eq!(decode_http_body(&chunked, Some("identity, chunked"), Some("identity, gzip")).as_ref(), html);
+    }
+
+    #[test]
+    fn test_decode_http_body_fallbacks() {
+        // identity and no codings borrow the body unchanged
+        assert!(matches!(decode_http_body(b"abc", Some("identity"), None), Cow::Borrowed(b"abc")));
+        assert!(matches!(decode_http_body(b"abc", None, None), Cow::Borrowed(b"abc")));
+
+        // A body already de-chunked by the crawler is kept, then still gunzipped
+        let gzipped = gzip_compress(b"plain").unwrap();
+        assert_eq!(decode_http_body(&gzipped, Some("chunked"), Some("gzip")).as_ref(), b"plain");
+
+        // Unknown codings stop the pipeline; a cut-off chunk keeps its data
+        assert_eq!(decode_http_body(b"3\r\nabc\r\n0\r\n\r\n", Some("chunked"), Some("compress")).as_ref(), b"abc");
+        assert_eq!(decode_http_body(b"a\r\nabc", Some("chunked"), None).as_ref(), b"abc");
+    }
+
+    #[test]
+    fn test_decode_zstd_body() {
+        let html = "<html><title>Zstandard</title><p>Compressed with zstd</p></html>";
+        let compressed = zstd::encode_all(html.as_bytes(), 3).unwrap();
+        let decoded = decode_http_body(&compressed, None, Some("zstd"));
+        assert_eq!(std::str::from_utf8(&decoded).unwrap(), html);
+
+        // Bytes that aren't zstd are kept
+        assert_eq!(decode_http_body(b"plain", None, Some("zstd")).as_ref(), b"plain");
+    }
+
+    #[test]
+    fn test_decode_brotli_body() {
+        let html = "<html><title>Brotli</title><p>Compressed with br</p></html>";
+        let mut compressed = Vec::new();
+        brotli::BrotliCompress(&mut html.as_bytes(), &mut compressed, &Default::default()).unwrap();
+        let decoded = decode_http_body(&compressed, None, Some("br"));
+        assert_eq!(std::str::from_utf8(&decoded).unwrap(), html);
+
+        // Bytes that aren't brotli are kept
+        assert_eq!(decode_http_body(b"plain", None, Some("br")).as_ref(), b"plain");
+    }
+}
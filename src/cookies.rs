@@ -0,0 +1,215 @@
+use crate::http_date::parse_http_date;
+use crate::{for_each_blob, read_first_record_lenient, sanitize_for_ffi, split_http_message, with_decompressed};
+use duckdb::{
+    core::{DataChunkHandle, Inserter, LogicalTypeHandle, LogicalTypeId},
+    vscalar::{ScalarFunctionSignature, VScalar},
+    vtab::arrow::WritableVector,
+};
+use std::error::Error;
+use warc_rs::WarcHeader;
+
+/// A cookie set by a `Set-Cookie` response header (RFC 6265)
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct Cookie {
+    pub name: String,
+    pub value: String,
+    pub domain: Option<String>,
+    pub path: Option<String>,
+    pub expires: Option<i64>, // Microseconds since the epoch
+    pub secure: bool,
+    pub httponly: bool,
+}
+
+/// Parse a `Set-Cookie` header value; None when it has no `name=value` pair
+pub(crate) fn parse_set_cookie(header: &str) -> Option<Cookie> {
+    let mut parts = header.split(';');
+    let (name, value) = parts.next()?.split_once('=')?;
+    let name = name.trim();
+    if name.is_empty() {
+        return None;
+    }
+
+    let mut cookie = Cookie {
+        name: name.to_string(),
+        value: value.trim().to_string(),
+        ..Default::default()
+    };
+    for attribute in parts {
+        let (key, value) = match attribute.split_once('=') {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => (attribute.trim(), ""),
+        };
+        if key.eq_ignore_ascii_case("Domain") && !value.is_empty() {
+            // A leading dot is ignored (RFC 6265 section 5.2.3)
+            cookie.domain = Some(value.trim_start_matches('.').to_lowercase());
+        } else if key.eq_ignore_ascii_case("Path") && !value.is_empty() {
+            cookie.path = Some(value.to_string());
+        } else if key.eq_ignore_ascii_case("Expires") {
+            // Browsers also send "Wed, 21-Oct-2015 07:28:00 GMT"
+            cookie.expires = parse_http_date(value).or_else(|| parse_http_date(&value.replace('-', " ")));
+        } else if key.eq_ignore_ascii_case("Secure") {
+            cookie.secure = true;
+        } else if key.eq_ignore_ascii_case("HttpOnly") {
+            cookie.httponly = true;
+        }
+    }
+
+    Some(cookie)
+}
+
+/// Every cookie set by a response record, in header order
+///
+/// None when the data is not a response record with an HTTP message.
+pub(crate) fn response_cookies(data: &[u8]) -> Option<Vec<Cookie>> {
+    let record = read_first_record_lenient(data).ok()?;
+    if record.header(WarcHeader::WarcType)? != "response" {
+        return None;
+    }
+    let (header_bytes, _) = split_http_message(record.body())?;
+
+    // Read the raw lines: the JSON header map keeps only one Set-Cookie
+    let header_text = String::from_utf8_lossy(header_bytes);
+    let cookies = header_text
+        .lines()
+        .skip(1)
+        .filter_map(|line| line.split_once(':'))
+        .filter(|(key, _)| key.trim().eq_ignore_ascii_case("set-cookie"))
+        .filter_map(|(_, value)| parse_set_cookie(&sanitize_for_ffi(value)))
+        .collect();
+    Some(cookies)
+}
+
+/// DuckDB scalar function returning the cookies set by a response record
+///
+/// Returns a LIST of STRUCT(name, value, domain, path, expires, secure, httponly),
+/// or NULL for records that are not HTTP responses.
+pub(crate) struct WarcCookies;
+
+impl VScalar for WarcCookies {
+    type State = ();
+
+    unsafe fn invoke(
+        _state: &Self::State,
+        input: &mut DataChunkHandle,
+        output: &mut dyn WritableVector,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut rows = Vec::with_capacity(input.len());
+        for_each_blob(input, 0, |_, raw_data| {
+            rows.push(raw_data.and_then(|raw_data| with_decompressed(raw_data, response_cookies)));
+        });
+
+        let mut list_vector = output.list_vector();
+        let total: usize = rows.iter().flatten().map(Vec::len).sum();
+        let cookie_struct = list_vector.struct_child(total);
+        let name_vec = cookie_struct.child(0, total);
+        let value_vec = cookie_struct.child(1, total);
+        let mut domain_vec = cookie_struct.child(2, total);
+        let mut path_vec = cookie_struct.child(3, total);
+        let mut expires_vec = cookie_struct.child(4, total);
+        let mut secure_vec = cookie_struct.child(5, total);
+        let mut httponly_vec = cookie_struct.child(6, total);
+
+        let mut offset = 0;
+        for (i, cookies) in rows.iter().enumerate() {
+            let Some(cookies) = cookies else {
+                list_vector.set_null(i);
+                continue;
+            };
+            list_vector.set_entry(i, offset, cookies.len());
+            for cookie in cookies {
+                name_vec.insert(offset, cookie.name.as_str());
+                value_vec.insert(offset, cookie.value.as_str());
+                match &cookie.domain {
+                    Some(v) => domain_vec.insert(offset, v.as_str()),
+                    None => domain_vec.set_null(offset),
+                }
+                match &cookie.path {
+                    Some(v) => path_vec.insert(offset, v.as_str()),
+                    None => path_vec.set_null(offset),
+                }
+                match cookie.expires {
+                    Some(v) => expires_vec.as_mut_slice::<i64>()[offset] = v,
+                    None => expires_vec.set_null(offset),
+                }
+                secure_vec.as_mut_slice::<bool>()[offset] = cookie.secure;
+                httponly_vec.as_mut_slice::<bool>()[offset] = cookie.httponly;
+                offset += 1;
+            }
+        }
+        list_vector.set_len(total);
+
+        Ok(())
+    }
+
+    fn signatures() -> Vec<ScalarFunctionSignature> {
+        let make_return_type = || {
+            LogicalTypeHandle::list(&LogicalTypeHandle::struct_type(&[
+                ("name", LogicalTypeHandle::from(LogicalTypeId::Varchar)),
+                ("value", LogicalTypeHandle::from(LogicalTypeId::Varchar)),
+                ("domain", LogicalTypeHandle::from(LogicalTypeId::Varchar)),
+                ("path", LogicalTypeHandle::from(LogicalTypeId::Varchar)),
+                ("expires", LogicalTypeHandle::from(LogicalTypeId::Timestamp)),
+                ("secure", LogicalTypeHandle::from(LogicalTypeId::Boolean)),
+                ("httponly", LogicalTypeHandle::from(LogicalTypeId::Boolean)),
+            ]))
+        };
+
+        vec![
+            ScalarFunctionSignature::exact(vec![LogicalTypeHandle::from(LogicalTypeId::Blob)], make_return_type()),
+            ScalarFunctionSignature::exact(
+                vec![LogicalTypeHandle::from(LogicalTypeId::Varchar)],
+                make_return_type(),
+            ),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::warc_file::tests::warc_record;
+
+    #[test]
+    fn test_response_cookies_two_set_cookie_lines() {
+        let data = warc_record(
+            "response",
+            b"HTTP/1.1 200 OK\r\n\
+              Set-Cookie: session=abc123; Path=/; Secure; HttpOnly\r\n\
+              Content-Type: text/html\r\n\
+              set-cookie: theme=dark; Domain=.Example.com; Expires=Wed, 21-Oct-2015 07:28:00 GMT\r\n\
+              \r\n<html></html>",
+        );
+
+        let cookies = response_cookies(&data).unwrap();
+        assert_eq!(
+            cookies,
+            vec![
+                Cookie {
+                    name: "session".to_string(),
+                    value: "abc123".to_string(),
+                    path: Some("/".to_string()),
+                    secure: true,
+                    httponly: true,
+                    ..Default::default()
+                },
+                Cookie {
+                    name: "theme".to_string(),
+                    value: "dark".to_string(),
+                    domain: Some("example.com".to_string()),
+                    expires: Some(1_445_412_480_000_000),
+                    ..Default::default()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_set_cookie_invalid() {
+        assert_eq!(parse_set_cookie("no equals sign"), None);
+        assert_eq!(parse_set_cookie("=value"), None);
+        assert_eq!(parse_set_cookie("a=; Expires=soon").unwrap().expires, None);
+
+        let request = warc_record("request", b"GET / HTTP/1.1\r\nCookie: a=b\r\n\r\n");
+        assert_eq!(response_cookies(&request), None);
+    }
+}
@@ -0,0 +1,67 @@
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+/// RFC 4648 base32 of some bytes, uppercase and unpadded as in WARC digests
+fn to_base32(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(5) * 8);
+    let mut buffer = 0u16;
+    let mut bits = 0;
+    for &byte in bytes {
+        buffer = (buffer << 8) | byte as u16;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(ALPHABET[((buffer >> bits) & 31) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        encoded.push(ALPHABET[((buffer << (5 - bits)) & 31) as usize] as char);
+    }
+    encoded
+}
+
+/// Whether a `WARC-Block-Digest` / `WARC-Payload-Digest` value matches some bytes
+///
+/// The value is `algorithm:digest`, with `sha1` or `sha256` as the algorithm
+/// and the digest in base32 (the usual form) or hex, in any case. None when
+/// the algorithm is another one or the value isn't of that form, so nothing
+/// could be checked.
+pub(crate) fn digest_matches(declared: &str, data: &[u8]) -> Option<bool> {
+    let (algorithm, value) = declared.trim().split_once(':')?;
+    let digest = match algorithm.trim().to_ascii_lowercase().as_str() {
+        "sha1" | "sha-1" => Sha1::digest(data).to_vec(),
+        "sha256" | "sha-256" => Sha256::digest(data).to_vec(),
+        _ => return None,
+    };
+    let value = value.trim().trim_end_matches('=');
+    Some(value.eq_ignore_ascii_case(&to_base32(&digest)) || value.eq_ignore_ascii_case(&crate::to_hex(&digest)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_base32() {
+        assert_eq!(to_base32(b""), "");
+        assert_eq!(to_base32(b"f"), "MY");
+        assert_eq!(to_base32(b"foobar"), "MZXW6YTBOI");
+    }
+
+    #[test]
+    fn test_digest_matches() {
+        // The example record's block and payload digests
+        let data = std::fs::read("test-data/example.warc").unwrap();
+        let record = crate::read_first_record_lenient(&data).unwrap();
+        let block = record.body();
+        assert_eq!(digest_matches("sha1:2EXP77OU4G4NFQI7UFAB6MR65FMJLRBX", block), Some(true));
+        assert_eq!(digest_matches("SHA1:2exp77ou4g4nfqi7ufab6mr65fmjlrbx", block), Some(true));
+        assert_eq!(digest_matches("sha1:JUWMXAQNHPTRTHYQWT3EJILYCL7YC3PQ", block), Some(false));
+
+        let hex = crate::to_hex(&Sha256::digest(b"abc"));
+        assert_eq!(digest_matches(&format!("sha256:{}", hex), b"abc"), Some(true));
+        assert_eq!(digest_matches("md5:900150983cd24fb0d6963f7d28e17f72", b"abc"), None);
+        assert_eq!(digest_matches("not a digest", b"abc"), None);
+    }
+}
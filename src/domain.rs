@@ -0,0 +1,119 @@
+use duckdb::{
+    core::{DataChunkHandle, Inserter, LogicalTypeHandle, LogicalTypeId},
+    types::DuckString,
+    vscalar::{ScalarFunctionSignature, VScalar},
+    vtab::arrow::WritableVector,
+};
+use libduckdb_sys::duckdb_string_t;
+use publicsuffix::{List, Psl};
+use std::error::Error;
+use std::sync::OnceLock;
+use url::Url;
+
+/// Bundled snapshot of https://publicsuffix.org/list/public_suffix_list.dat
+static PUBLIC_SUFFIX_LIST: &str = include_str!("../data/public_suffix_list.dat");
+
+fn public_suffix_list() -> &'static List {
+    static LIST: OnceLock<List> = OnceLock::new();
+    LIST.get_or_init(|| PUBLIC_SUFFIX_LIST.parse().expect("bundled public suffix list is valid"))
+}
+
+/// Extract the lowercase host from a URI, accepting bare hostnames too
+fn uri_host(uri: &str) -> Option<String> {
+    let uri = uri.trim();
+    match Url::parse(uri) {
+        Ok(url) => match url.host() {
+            Some(url::Host::Domain(host)) => Some(host.to_lowercase()),
+            // IP addresses have no registrable domain
+            _ => None,
+        },
+        // No scheme (e.g. "www.example.com/path"), retry as http
+        Err(url::ParseError::RelativeUrlWithoutBase) => {
+            let url = Url::parse(&format!("http://{}", uri)).ok()?;
+            match url.host() {
+                Some(url::Host::Domain(host)) => Some(host.to_lowercase()),
+                _ => None,
+            }
+        }
+        Err(_) => None,
+    }
+}
+
+/// Registrable domain (public suffix plus one label) of a URI's host
+pub(crate) fn registrable_domain(uri: &str) -> Option<String> {
+    let host = uri_host(uri)?;
+    let host = host.trim_end_matches('.');
+    let domain = public_suffix_list().domain(host.as_bytes())?;
+    String::from_utf8(domain.as_bytes().to_vec()).ok()
+}
+
+/// DuckDB scalar function returning the registrable domain of a URI
+///
+/// e.g. `warc_registrable_domain('https://www.bbc.co.uk/news')` -> `bbc.co.uk`
+pub(crate) struct WarcRegistrableDomain;
+
+impl VScalar for WarcRegistrableDomain {
+    type State = ();
+
+    unsafe fn invoke(
+        _state: &Self::State,
+        input: &mut DataChunkHandle,
+        output: &mut dyn WritableVector,
+    ) -> Result<(), Box<dyn Error>> {
+        let size = input.len();
+        let input_vector = input.flat_vector(0);
+        let uris = input_vector.as_slice_with_len::<duckdb_string_t>(size);
+        let mut output_vector = output.flat_vector();
+
+        for (i, uri) in uris.iter().enumerate() {
+            if input_vector.row_is_null(i as u64) {
+                output_vector.set_null(i);
+                continue;
+            }
+
+            let mut uri = *uri;
+            let uri = DuckString::new(&mut uri).as_str().to_string();
+            match registrable_domain(&uri) {
+                Some(domain) => output_vector.insert(i, domain.as_str()),
+                None => output_vector.set_null(i),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn signatures() -> Vec<ScalarFunctionSignature> {
+        vec![ScalarFunctionSignature::exact(
+            vec![LogicalTypeHandle::from(LogicalTypeId::Varchar)],
+            LogicalTypeHandle::from(LogicalTypeId::Varchar),
+        )]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registrable_domain_example_com() {
+        assert_eq!(registrable_domain("http://www.example.com/"), Some("example.com".to_string()));
+        assert_eq!(registrable_domain("https://EXAMPLE.com"), Some("example.com".to_string()));
+    }
+
+    #[test]
+    fn test_registrable_domain_co_uk() {
+        assert_eq!(
+            registrable_domain("https://www.news.bbc.co.uk/path?q=1"),
+            Some("bbc.co.uk".to_string())
+        );
+        assert_eq!(registrable_domain("bbc.co.uk"), Some("bbc.co.uk".to_string()));
+    }
+
+    #[test]
+    fn test_registrable_domain_without_domain() {
+        // A bare public suffix or an IP address has no registrable domain
+        assert_eq!(registrable_domain("http://co.uk/"), None);
+        assert_eq!(registrable_domain("http://2.18.67.69/"), None);
+        assert_eq!(registrable_domain("not a uri"), None);
+    }
+}
@@ -0,0 +1,44 @@
+use std::fmt;
+
+/// Why a record couldn't be parsed, or was only partially parsed
+///
+/// Its message is what `parse_error` reports.
+#[derive(Debug, PartialEq)]
+pub(crate) enum WarcParseError {
+    /// A zero-length value
+    EmptyInput,
+    /// The input starts like gzip but doesn't decompress
+    DecompressFailed,
+    /// gzip input was required but the input isn't gzip
+    NotCompressed,
+    /// The input of `parse_warc_base64` isn't base64
+    InvalidBase64,
+    /// The input doesn't start with a `WARC/` version line
+    NoRecords,
+    /// The `warc` crate rejected the record (e.g. no WARC-Type)
+    WarcError(String),
+    /// A WARC version whose layout isn't known
+    UnsupportedVersion(String),
+    /// The block of a response or request record isn't an HTTP message
+    HttpParseError(&'static str),
+    /// The file ends before the end of the record
+    Truncated,
+}
+
+impl fmt::Display for WarcParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WarcParseError::EmptyInput => write!(f, "empty input"),
+            WarcParseError::DecompressFailed => write!(f, "gzip decompression failed"),
+            WarcParseError::NotCompressed => write!(f, "input is not gzip-compressed"),
+            WarcParseError::InvalidBase64 => write!(f, "invalid base64 input"),
+            WarcParseError::NoRecords => write!(f, "no WARC record found"),
+            WarcParseError::WarcError(e) => write!(f, "invalid WARC record: {}", e),
+            WarcParseError::UnsupportedVersion(version) => write!(f, "unsupported WARC version {}", version),
+            WarcParseError::HttpParseError(what) => write!(f, "invalid HTTP message: {}", what),
+            WarcParseError::Truncated => write!(f, "record truncated: the file ends before the end of the record"),
+        }
+    }
+}
+
+impl std::error::Error for WarcParseError {}
@@ -0,0 +1,453 @@
+use crate::for_each_blob;
+use duckdb::{
+    core::{DataChunkHandle, Inserter, LogicalTypeHandle, LogicalTypeId},
+    vscalar::{ScalarFunctionSignature, VScalar},
+    vtab::arrow::WritableVector,
+};
+use flate2::bufread::DeflateDecoder;
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use flate2::{Compression, Crc};
+use std::error::Error;
+use std::io::{self, BufRead, Read, Write};
+
+const FHCRC: u8 = 0x02;
+const FEXTRA: u8 = 0x04;
+const FNAME: u8 = 0x08;
+const FCOMMENT: u8 = 0x10;
+/// Flag bits RFC 1952 reserves, which must be zero
+const FRESERVED: u8 = 0xe0;
+
+/// A single decompressed gzip member
+pub(crate) struct GzipMember {
+    pub offset: u64,          // Byte offset of the member in the compressed stream
+    pub compressed_size: u64, // Member size including header and footer
+    pub data: Vec<u8>,
+    pub crc32: u32, // CRC32 declared in the member footer
+    pub isize: u32, // Uncompressed size mod 2^32 declared in the footer
+    pub crc_ok: bool,
+    pub raw: Option<Vec<u8>>, // The compressed member as read, with `GzipMembers::with_raw`
+}
+
+/// Reader wrapper tracking how many bytes have been consumed, and keeping
+/// them when `raw` is set
+struct CountingReader<R> {
+    inner: R,
+    position: u64,
+    raw: Option<Vec<u8>>,
+}
+
+impl<R: BufRead> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.position += n as u64;
+        if let Some(raw) = &mut self.raw {
+            raw.extend_from_slice(&buf[..n]);
+        }
+        Ok(n)
+    }
+}
+
+impl<R: BufRead> BufRead for CountingReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        if let Some(raw) = &mut self.raw {
+            // The bytes being consumed are still buffered, so this doesn't read
+            if let Ok(buffer) = self.inner.fill_buf() {
+                raw.extend_from_slice(&buffer[..amt.min(buffer.len())]);
+            }
+        }
+        self.position += amt as u64;
+        self.inner.consume(amt);
+    }
+}
+
+/// Iterator over the members of a (multi-member) gzip stream
+///
+/// Headers are parsed per RFC 1952 rather than by scanning for magic bytes,
+/// so FEXTRA/FNAME/FCOMMENT contents never cause a mis-split. The footer
+/// CRC32 and ISIZE are verified against the decompressed data instead of
+/// failing the read, so corrupt members are reported through `crc_ok`.
+pub(crate) struct GzipMembers<R> {
+    reader: Option<CountingReader<R>>,
+}
+
+impl<R: BufRead> GzipMembers<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader: Some(CountingReader {
+                inner: reader,
+                position: 0,
+                raw: None,
+            }),
+        }
+    }
+
+    /// Also return the compressed bytes of each member, in `GzipMember::raw`
+    pub fn with_raw(mut self) -> Self {
+        if let Some(reader) = &mut self.reader {
+            reader.raw = Some(Vec::new());
+        }
+        self
+    }
+
+    fn read_member(reader: &mut CountingReader<R>) -> io::Result<Option<GzipMember>> {
+        let offset = reader.position;
+        if reader.fill_buf()?.is_empty() {
+            return Ok(None);
+        }
+
+        if let Some(raw) = &mut reader.raw {
+            raw.clear();
+        }
+        read_member_header(reader)?;
+
+        let mut decoder = DeflateDecoder::new(&mut *reader);
+        let mut data = Vec::new();
+        decoder.read_to_end(&mut data)?;
+
+        let mut footer = [0u8; 8];
+        reader.read_exact(&mut footer)?;
+        let crc32 = u32::from_le_bytes([footer[0], footer[1], footer[2], footer[3]]);
+        let isize = u32::from_le_bytes([footer[4], footer[5], footer[6], footer[7]]);
+
+        let mut crc = Crc::new();
+        crc.update(&data);
+        let crc_ok = crc.sum() == crc32 && data.len() as u32 == isize;
+
+        Ok(Some(GzipMember {
+            offset,
+            compressed_size: reader.position - offset,
+            data,
+            crc32,
+            isize,
+            crc_ok,
+            raw: reader.raw.as_mut().map(std::mem::take),
+        }))
+    }
+}
+
+impl<R: BufRead> Iterator for GzipMembers<R> {
+    type Item = io::Result<GzipMember>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let reader = self.reader.as_mut()?;
+        match Self::read_member(reader) {
+            Ok(Some(member)) => Some(Ok(member)),
+            Ok(None) => {
+                self.reader = None;
+                None
+            }
+            Err(e) => {
+                // The next member boundary is unknown after an error
+                self.reader = None;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// Read and validate a gzip member header, skipping its optional fields
+///
+/// The fixed 10 bytes must hold the magic bytes, CM 8 (deflate) and no
+/// reserved flag; MTIME, XFL and OS may be anything, as writers such as
+/// Heritrix (Java's `GZIPOutputStream`) leave them zero or set them their own
+/// way. The optional fields are skipped as the flags announce, and a header
+/// CRC16 is checked when present.
+fn read_member_header<R: BufRead>(reader: &mut R) -> io::Result<()> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+    let mut header = vec![0u8; 10];
+    reader.read_exact(&mut header)?;
+    if header[0] != 0x1f || header[1] != 0x8b {
+        return Err(invalid("invalid gzip magic bytes"));
+    }
+    if header[2] != 8 {
+        return Err(invalid("unsupported gzip compression method"));
+    }
+    let flags = header[3];
+    if flags & FRESERVED != 0 {
+        return Err(invalid("reserved gzip header flags set"));
+    }
+
+    // Kept whole for the header CRC, which covers every byte before it
+    if flags & FEXTRA != 0 {
+        let mut xlen = [0u8; 2];
+        reader.read_exact(&mut xlen)?;
+        header.extend_from_slice(&xlen);
+        let start = header.len();
+        header.resize(start + u16::from_le_bytes(xlen) as usize, 0);
+        reader.read_exact(&mut header[start..])?;
+    }
+    for flag in [FNAME, FCOMMENT] {
+        if flags & flag != 0 {
+            reader.read_until(0, &mut header)?;
+            if header.last() != Some(&0) {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+        }
+    }
+    if flags & FHCRC != 0 {
+        let mut header_crc = [0u8; 2];
+        reader.read_exact(&mut header_crc)?;
+        let mut crc = Crc::new();
+        crc.update(&header);
+        if u16::from_le_bytes(header_crc) != crc.sum() as u16 {
+            return Err(invalid("gzip header CRC mismatch"));
+        }
+    }
+
+    Ok(())
+}
+
+/// Gzip-compress `data` as a single member
+pub(crate) fn gzip_compress(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::with_capacity(data.len() / 2), Compression::default());
+    encoder.write_all(data)?;
+    encoder.finish()
+}
+
+/// Decompress all members of a gzip stream
+pub(crate) fn gzip_decompress(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut decompressed = Vec::with_capacity(data.len() * 4);
+    MultiGzDecoder::new(data).read_to_end(&mut decompressed)?;
+    Ok(decompressed)
+}
+
+/// Apply a BLOB -> BLOB transformation to every row, NULL when it fails
+unsafe fn invoke_blob_transform(
+    input: &mut DataChunkHandle,
+    output: &mut dyn WritableVector,
+    transform: fn(&[u8]) -> io::Result<Vec<u8>>,
+) {
+    let mut output_vector = output.flat_vector();
+    let mut results = vec![None; input.len()];
+    for_each_blob(input, 0, |i, data| {
+        results[i] = data.and_then(|data| transform(data).ok());
+    });
+
+    for (i, result) in results.iter().enumerate() {
+        match result {
+            Some(v) => Inserter::<&[u8]>::insert(&output_vector, i, v.as_slice()),
+            None => output_vector.set_null(i),
+        }
+    }
+}
+
+fn blob_transform_signatures() -> Vec<ScalarFunctionSignature> {
+    vec![ScalarFunctionSignature::exact(
+        vec![LogicalTypeHandle::from(LogicalTypeId::Blob)],
+        LogicalTypeHandle::from(LogicalTypeId::Blob),
+    )]
+}
+
+/// DuckDB scalar function gzip-compressing a BLOB
+pub(crate) struct WarcGzip;
+
+impl VScalar for WarcGzip {
+    type State = ();
+
+    unsafe fn invoke(
+        _state: &Self::State,
+        input: &mut DataChunkHandle,
+        output: &mut dyn WritableVector,
+    ) -> Result<(), Box<dyn Error>> {
+        invoke_blob_transform(input, output, gzip_compress);
+        Ok(())
+    }
+
+    fn signatures() -> Vec<ScalarFunctionSignature> {
+        blob_transform_signatures()
+    }
+}
+
+/// DuckDB scalar function decompressing a (multi-member) gzip BLOB
+///
+/// Returns NULL when the input is not valid gzip.
+pub(crate) struct WarcGunzip;
+
+impl VScalar for WarcGunzip {
+    type State = ();
+
+    unsafe fn invoke(
+        _state: &Self::State,
+        input: &mut DataChunkHandle,
+        output: &mut dyn WritableVector,
+    ) -> Result<(), Box<dyn Error>> {
+        invoke_blob_transform(input, output, gzip_decompress);
+        Ok(())
+    }
+
+    fn signatures() -> Vec<ScalarFunctionSignature> {
+        blob_transform_signatures()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gzip(data: &[u8], level: Compression) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), level);
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn test_split_members() {
+        let first = gzip(b"first member", Compression::default());
+        let second = gzip(b"second member", Compression::none());
+        let stream = [first.clone(), second.clone()].concat();
+
+        let members: Vec<GzipMember> = GzipMembers::new(stream.as_slice()).map(|m| m.unwrap()).collect();
+        assert_eq!(members.len(), 2);
+
+        assert_eq!(members[0].offset, 0);
+        assert_eq!(members[0].compressed_size, first.len() as u64);
+        assert_eq!(members[0].data, b"first member");
+        assert_eq!(members[0].isize, 12);
+        assert!(members[0].crc_ok);
+
+        assert_eq!(members[1].offset, first.len() as u64);
+        assert_eq!(members[1].compressed_size, second.len() as u64);
+        assert_eq!(members[1].data, b"second member");
+        assert!(members[1].crc_ok);
+    }
+
+    #[test]
+    fn test_split_members_with_header_fields() {
+        // FEXTRA "sl" subfield whose payload looks like the start of another member
+        let extra = [&b"sl"[..], &8u16.to_le_bytes(), &[0x1f, 0x8b, 0x08, 0x00, 0x1f, 0x8b, 0x08, 0x04]].concat();
+        let mut encoder = flate2::GzBuilder::new()
+            .extra(extra)
+            .filename("record.warc")
+            .comment(&[0x1f, 0x8b][..])
+            .write(Vec::new(), Compression::default());
+        encoder.write_all(b"first member").unwrap();
+        let first = encoder.finish().unwrap();
+        assert_eq!(first[3] & (FEXTRA | FNAME | FCOMMENT), FEXTRA | FNAME | FCOMMENT);
+        let second = gzip(b"second member", Compression::default());
+        let stream = [first.clone(), second].concat();
+
+        let members: Vec<GzipMember> = GzipMembers::new(stream.as_slice()).map(|m| m.unwrap()).collect();
+        assert_eq!(members.len(), 2);
+        assert_eq!(members[0].compressed_size, first.len() as u64);
+        assert_eq!(members[0].data, b"first member");
+        assert!(members[0].crc_ok);
+        assert_eq!(members[1].offset, first.len() as u64);
+        assert_eq!(members[1].data, b"second member");
+    }
+
+    #[test]
+    fn test_members_keep_raw_bytes() {
+        let record = std::fs::read("test-data/example.warc").unwrap();
+        let mut encoder = flate2::GzBuilder::new().filename("record.warc").write(Vec::new(), Compression::best());
+        encoder.write_all(&record).unwrap();
+        let first = encoder.finish().unwrap();
+        let second = gzip(b"second member", Compression::none());
+        let stream = [first.clone(), second.clone()].concat();
+
+        // Byte for byte, so each member gunzips back to its record
+        let members: Vec<GzipMember> =
+            GzipMembers::new(stream.as_slice()).with_raw().map(|m| m.unwrap()).collect();
+        assert_eq!(members[0].raw.as_deref(), Some(first.as_slice()));
+        assert_eq!(members[1].raw.as_deref(), Some(second.as_slice()));
+        assert_eq!(gzip_decompress(members[0].raw.as_ref().unwrap()).unwrap(), record);
+
+        // Not kept by default
+        assert!(GzipMembers::new(stream.as_slice()).all(|m| m.unwrap().raw.is_none()));
+    }
+
+    #[test]
+    fn test_crc_mismatch_detected() {
+        // Stored (uncompressed) deflate blocks let us flip a payload byte
+        // without breaking the deflate stream itself
+        let mut member = gzip(b"hello world", Compression::none());
+        let payload_pos = member.windows(5).position(|w| w == b"hello").unwrap();
+        member[payload_pos] = b'j';
+
+        let members: Vec<GzipMember> = GzipMembers::new(member.as_slice()).map(|m| m.unwrap()).collect();
+        assert_eq!(members.len(), 1);
+        assert_eq!(members[0].data, b"jello world");
+        assert!(!members[0].crc_ok);
+    }
+
+    #[test]
+    fn test_gzip_round_trip() {
+        let data = std::fs::read("test-data/example.warc").unwrap();
+        let compressed = gzip_compress(&data).unwrap();
+        assert!(compressed.starts_with(&[0x1f, 0x8b]));
+        assert!(compressed.len() < data.len());
+        assert_eq!(gzip_decompress(&compressed).unwrap(), data);
+
+        assert_eq!(gzip_decompress(&gzip_compress(b"").unwrap()).unwrap(), b"");
+    }
+
+    #[test]
+    fn test_gunzip_multi_member_and_invalid() {
+        let stream = [gzip_compress(b"first ").unwrap(), gzip_compress(b"second").unwrap()].concat();
+        assert_eq!(gzip_decompress(&stream).unwrap(), b"first second");
+        assert!(gzip_decompress(b"not gzip").is_err());
+    }
+
+    #[test]
+    fn test_invalid_magic_is_an_error() {
+        let mut members = GzipMembers::new(&b"WARC/1.0\r\n"[..]);
+        assert!(members.next().unwrap().is_err());
+        assert!(members.next().is_none());
+    }
+
+    /// A member with a hand-written header, as written by Java's `GZIPOutputStream`
+    fn member_with_header(header: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut encoder = flate2::write::DeflateEncoder::new(header.to_vec(), Compression::default());
+        encoder.write_all(data).unwrap();
+        let mut member = encoder.finish().unwrap();
+        let mut crc = Crc::new();
+        crc.update(data);
+        member.extend_from_slice(&crc.sum().to_le_bytes());
+        member.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        member
+    }
+
+    #[test]
+    fn test_split_heritrix_members() {
+        let record = std::fs::read("test-data/example.warc").unwrap();
+        // Heritrix: MTIME, XFL and OS all zero (OS 0 is FAT)
+        let heritrix = member_with_header(&[0x1f, 0x8b, 0x08, 0x00, 0, 0, 0, 0, 0x00, 0x00], &record);
+        // FHCRC and FNAME, with XFL 4 (fastest) and OS 11 (NTFS)
+        let mut header = vec![0x1f, 0x8b, 0x08, FHCRC | FNAME, 0x78, 0x56, 0x34, 0x12, 0x04, 0x0b];
+        header.extend_from_slice(b"rec.warc\0");
+        let mut crc = Crc::new();
+        crc.update(&header);
+        header.extend_from_slice(&(crc.sum() as u16).to_le_bytes());
+        let checked = member_with_header(&header, b"second member");
+        let stream = [heritrix.clone(), checked.clone()].concat();
+
+        let members: Vec<GzipMember> = GzipMembers::new(stream.as_slice()).map(|m| m.unwrap()).collect();
+        assert_eq!(members.len(), 2);
+        assert_eq!(members[0].data, record);
+        assert_eq!(members[0].compressed_size, heritrix.len() as u64);
+        assert!(members[0].crc_ok);
+        assert_eq!(members[1].offset, heritrix.len() as u64);
+        assert_eq!(members[1].data, b"second member");
+        assert!(members[1].crc_ok);
+
+        // A wrong header CRC, reserved flags or another method aren't gzip headers
+        let mut bad_crc = checked.clone();
+        bad_crc[header.len() - 1] ^= 0xff;
+        let mut reserved = heritrix.clone();
+        reserved[3] = 0x20;
+        let mut method = heritrix.clone();
+        method[2] = 7;
+        for (member, message) in [
+            (bad_crc, "gzip header CRC mismatch"),
+            (reserved, "reserved gzip header flags set"),
+            (method, "unsupported gzip compression method"),
+        ] {
+            let error = GzipMembers::new(member.as_slice()).next().unwrap().err().unwrap();
+            assert_eq!(error.to_string(), message);
+        }
+    }
+}
@@ -0,0 +1,707 @@
+use crate::content_coding::decode_http_body;
+use crate::{for_each_blob, parse_http_response, read_first_record_lenient, sanitize_for_ffi, with_decompressed};
+use duckdb::{
+    core::{DataChunkHandle, Inserter, LogicalTypeHandle, LogicalTypeId},
+    vscalar::{ScalarFunctionSignature, VScalar},
+    vtab::arrow::WritableVector,
+};
+use std::error::Error;
+use url::Url;
+use warc_rs::WarcHeader;
+
+/// Text of the first `<title>` element of an HTML document
+///
+/// Tag names match case-insensitively, common character references are
+/// decoded and runs of whitespace collapse to one space. None when the
+/// document has no title element.
+pub(crate) fn html_title(html: &[u8]) -> Option<String> {
+    let open = find_tag(html, b"<title")?;
+    let content_start = open + html[open..].iter().position(|&b| b == b'>')? + 1;
+    let content_len = find_tag(&html[content_start..], b"</title").unwrap_or(html.len() - content_start);
+    let text = String::from_utf8_lossy(&html[content_start..content_start + content_len]);
+
+    let decoded = decode_entities(&text);
+    Some(sanitize_for_ffi(&decoded.split_whitespace().collect::<Vec<_>>().join(" ")))
+}
+
+/// Position of the first `tag` (e.g. `<title`) followed by `>`, `/` or whitespace
+fn find_tag(html: &[u8], tag: &[u8]) -> Option<usize> {
+    html.windows(tag.len()).enumerate().find_map(|(i, window)| {
+        let boundary = html.get(i + tag.len()).is_some_and(|&b| b == b'>' || b == b'/' || b.is_ascii_whitespace());
+        (window.eq_ignore_ascii_case(tag) && boundary).then_some(i)
+    })
+}
+
+/// Decode the named references common in titles and numeric references
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        decoded.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let entity = rest[1..].find(';').map(|end| &rest[1..end + 1]).filter(|e| e.len() <= 10);
+        let character = entity.and_then(|entity| match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some('\u{a0}'),
+            _ => {
+                let number = entity.strip_prefix('#')?;
+                let code = match number.strip_prefix(['x', 'X']) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                    None => number.parse().ok()?,
+                };
+                char::from_u32(code)
+            }
+        });
+        match (entity, character) {
+            (Some(entity), Some(character)) => {
+                decoded.push(character);
+                rest = &rest[entity.len() + 2..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// Position of the `>` closing the tag starting at `start`, skipping quoted attribute values
+fn tag_end(html: &[u8], start: usize) -> Option<usize> {
+    let mut quote = None;
+    for (i, &b) in html.iter().enumerate().skip(start) {
+        match (quote, b) {
+            (None, b'"' | b'\'') => quote = Some(b),
+            (Some(q), b) if b == q => quote = None,
+            (None, b'>') => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Attributes of a start tag's text (without `<` and `>`), names lowercased
+///
+/// Values may be double-, single- or unquoted; an attribute without a value
+/// gets an empty one.
+fn tag_attributes(tag: &str) -> Vec<(String, String)> {
+    let mut attributes = Vec::new();
+    // Skip the tag name
+    let mut rest = tag.trim_start_matches(|c: char| !c.is_whitespace() && c != '/');
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == '/');
+        let name_len = rest.find(|c: char| c.is_whitespace() || c == '=' || c == '/').unwrap_or(rest.len());
+        if name_len == 0 {
+            break;
+        }
+        let name = rest[..name_len].to_ascii_lowercase();
+        rest = rest[name_len..].trim_start();
+
+        let mut value = "";
+        if let Some(after_eq) = rest.strip_prefix('=') {
+            let after_eq = after_eq.trim_start();
+            let (parsed, remaining) = match after_eq.chars().next() {
+                Some(q @ ('"' | '\'')) => {
+                    let end = after_eq[1..].find(q).map_or(after_eq.len(), |end| end + 1);
+                    (&after_eq[1..end], after_eq.get(end + 1..).unwrap_or_default())
+                }
+                _ => {
+                    let end = after_eq.find(char::is_whitespace).unwrap_or(after_eq.len());
+                    after_eq.split_at(end)
+                }
+            };
+            value = parsed;
+            rest = remaining;
+        }
+        attributes.push((name, value.to_string()));
+    }
+    attributes
+}
+
+/// `href` of the first `<link rel="canonical">` of an HTML document, as written
+///
+/// `rel` may list several space-separated relations; they match
+/// case-insensitively. Character references in the href are decoded.
+pub(crate) fn canonical_href(html: &[u8]) -> Option<String> {
+    let mut position = 0;
+    while let Some(open) = find_tag(&html[position..], b"<link") {
+        let start = position + open;
+        let end = tag_end(html, start)?;
+        let attributes = tag_attributes(&String::from_utf8_lossy(&html[start + 1..end]));
+        let value = |name: &str| attributes.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str());
+        let is_canonical = value("rel")
+            .is_some_and(|rel| rel.split_ascii_whitespace().any(|r| r.eq_ignore_ascii_case("canonical")));
+        if let (true, Some(href)) = (is_canonical, value("href")) {
+            return Some(decode_entities(href.trim()));
+        }
+        position = end + 1;
+    }
+    None
+}
+
+/// A `<form>` of an HTML document
+#[derive(Debug, PartialEq)]
+pub(crate) struct HtmlForm {
+    pub action: String,           // As written, character references decoded; empty when missing
+    pub method: String,           // Uppercase, GET when missing
+    pub field_names: Vec<String>, // `name` of its input, select, textarea and button elements
+}
+
+/// Tags that submit a named value with their form
+const FORM_FIELD_TAGS: &[&str] = &["input", "select", "textarea", "button"];
+
+/// The `<form>` elements of an HTML document, in document order
+///
+/// A form runs to its `</form>`, the next `<form>` or the end of the
+/// document. Fields outside any form, fields without a name and tags inside
+/// comments are left out.
+pub(crate) fn html_forms(html: &[u8]) -> Vec<HtmlForm> {
+    let mut forms = Vec::new();
+    let mut in_form = false;
+    let mut position = 0;
+    while let Some(open) = html[position..].iter().position(|&b| b == b'<') {
+        let start = position + open;
+        if html[start..].starts_with(b"<!--") {
+            let Some(end) = html[start + 4..].windows(3).position(|w| w == b"-->") else {
+                break;
+            };
+            position = start + 4 + end + 3;
+            continue;
+        }
+        let Some(end) = tag_end(html, start) else {
+            break;
+        };
+        let tag = String::from_utf8_lossy(&html[start + 1..end]);
+        let name_len = tag.find(|c: char| c.is_whitespace() || c == '/' || c == '>').unwrap_or(tag.len());
+        let closing = tag.starts_with('/');
+        let name = (if closing { tag[1..].trim() } else { &tag[..name_len] }).to_ascii_lowercase();
+
+        if name == "form" && closing {
+            in_form = false;
+        } else if name == "form" {
+            let attributes = tag_attributes(&tag);
+            let value = |name: &str| attributes.iter().find(|(n, _)| n == name).map(|(_, v)| v.trim());
+            let method = value("method").filter(|m| !m.is_empty()).unwrap_or("get");
+            forms.push(HtmlForm {
+                action: decode_entities(value("action").unwrap_or_default()),
+                method: method.to_ascii_uppercase(),
+                field_names: Vec::new(),
+            });
+            in_form = true;
+        } else if in_form && FORM_FIELD_TAGS.contains(&name.as_str()) {
+            let field_name = tag_attributes(&tag).into_iter().find(|(n, _)| n == "name").map(|(_, v)| v);
+            if let (Some(form), Some(field_name)) = (forms.last_mut(), field_name.filter(|v| !v.is_empty())) {
+                form.field_names.push(decode_entities(&field_name));
+            }
+        }
+        position = end + 1;
+    }
+    forms
+}
+
+/// JSON-LD blocks of an HTML document, re-serialized compact, in document order
+///
+/// Each `<script type="application/ld+json">` element's text is parsed as
+/// JSON, with key order and numbers kept as written; blocks that aren't
+/// well-formed JSON are left out.
+pub(crate) fn jsonld_blocks(html: &[u8]) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let mut position = 0;
+    while let Some(open) = find_tag(&html[position..], b"<script") {
+        let start = position + open;
+        let Some(end) = tag_end(html, start) else {
+            break;
+        };
+        let content_start = end + 1;
+        let content_len = find_tag(&html[content_start..], b"</script").unwrap_or(html.len() - content_start);
+        position = content_start + content_len;
+
+        let attributes = tag_attributes(&String::from_utf8_lossy(&html[start + 1..end]));
+        let is_jsonld = attributes.iter().any(|(name, value)| {
+            let mime = value.split(';').next().unwrap_or_default().trim();
+            name == "type" && mime.eq_ignore_ascii_case("application/ld+json")
+        });
+        if !is_jsonld {
+            continue;
+        }
+        let text = String::from_utf8_lossy(&html[content_start..position]);
+        if let Ok(value) = serde_json::from_str::<serde_json::Value>(&text) {
+            blocks.push(sanitize_for_ffi(&value.to_string()));
+        }
+    }
+    blocks
+}
+
+/// URL of the first `<meta http-equiv="refresh">` of an HTML document, as written
+///
+/// The `content` attribute is `<delay>; url=<url>`, with `url=` optional and
+/// the URL possibly quoted. None when there is no refresh or it only reloads
+/// the page.
+pub(crate) fn meta_refresh_href(html: &[u8]) -> Option<String> {
+    let mut position = 0;
+    while let Some(open) = find_tag(&html[position..], b"<meta") {
+        let start = position + open;
+        let end = tag_end(html, start)?;
+        let attributes = tag_attributes(&String::from_utf8_lossy(&html[start + 1..end]));
+        let value = |name: &str| attributes.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str());
+        let is_refresh = value("http-equiv").is_some_and(|v| v.trim().eq_ignore_ascii_case("refresh"));
+        if let (true, Some(content)) = (is_refresh, value("content")) {
+            return refresh_content_url(&decode_entities(content));
+        }
+        position = end + 1;
+    }
+    None
+}
+
+/// URL of a refresh `content` value (`0;url='/next'`), None without one
+fn refresh_content_url(content: &str) -> Option<String> {
+    let (_, rest) = content.split_once([';', ','])?;
+    let rest = rest.trim_start();
+    let rest = match rest.get(..3) {
+        Some(name) if name.eq_ignore_ascii_case("url") => match rest[3..].trim_start().strip_prefix('=') {
+            Some(url) => url.trim_start(),
+            None => rest,
+        },
+        _ => rest,
+    };
+    let url = match rest.chars().next() {
+        Some(q @ ('"' | '\'')) => rest[1..].split(q).next().unwrap_or_default(),
+        _ => rest,
+    };
+    let url = url.trim();
+    (!url.is_empty()).then(|| url.to_string())
+}
+
+/// Resolve an href against a record's WARC-Target-URI
+///
+/// Without a target URI only absolute hrefs resolve; None for hrefs that
+/// don't resolve to a URL.
+pub(crate) fn resolve_href(target_uri: Option<&str>, href: &str) -> Option<String> {
+    let url = match target_uri.and_then(|target_uri| Url::parse(target_uri.trim()).ok()) {
+        Some(base) => base.join(href),
+        None => Url::parse(href),
+    };
+    Some(sanitize_for_ffi(url.ok()?.as_str()))
+}
+
+/// Run `f` on the WARC-Target-URI and decoded body of an HTML response record
+///
+/// None for other records and content types. The body is de-chunked and
+/// decompressed first when the response declares a `Transfer-Encoding` or
+/// `Content-Encoding`.
+fn with_html_response<T>(data: &[u8], f: impl FnOnce(Option<&str>, &[u8]) -> Option<T>) -> Option<T> {
+    let record = read_first_record_lenient(data).ok()?;
+    if record.header(WarcHeader::WarcType)? != "response" {
+        return None;
+    }
+    let response = parse_http_response(record.body());
+    let content_type = response.content_type?.to_ascii_lowercase();
+    if !content_type.contains("html") {
+        return None;
+    }
+    let body = decode_http_body(
+        response.body?,
+        response.transfer_encoding.as_deref(),
+        response.content_encoding.as_deref(),
+    );
+    f(record.header(WarcHeader::TargetURI).as_deref(), &body)
+}
+
+/// Title of an HTML response record; None for other records and content types
+pub(crate) fn response_title(data: &[u8]) -> Option<String> {
+    with_html_response(data, |_, body| html_title(body))
+}
+
+/// Canonical URL of an HTML response record, resolved against its WARC-Target-URI
+///
+/// None for other records and content types, pages without a canonical link
+/// and hrefs that don't resolve to a URL.
+pub(crate) fn response_canonical_url(data: &[u8]) -> Option<String> {
+    with_html_response(data, |target_uri, body| resolve_href(target_uri, &canonical_href(body)?))
+}
+
+/// Forms of an HTML response record, actions resolved against its WARC-Target-URI
+///
+/// A missing action submits to the page itself, so it resolves to the target
+/// URI. Empty for other records and content types; an action that doesn't
+/// resolve is NULL.
+pub(crate) fn response_forms(data: &[u8]) -> Vec<(Option<String>, HtmlForm)> {
+    with_html_response(data, |target_uri, body| {
+        let forms = html_forms(body).into_iter();
+        Some(forms.map(|form| (resolve_href(target_uri, &form.action), form)).collect())
+    })
+    .unwrap_or_default()
+}
+
+/// JSON-LD blocks of an HTML response record; empty for other records and content types
+pub(crate) fn response_jsonld(data: &[u8]) -> Vec<String> {
+    with_html_response(data, |_, body| Some(jsonld_blocks(body))).unwrap_or_default()
+}
+
+/// Write `text(record)` for every row, NULL when it returns None
+unsafe fn invoke_record_text(
+    input: &mut DataChunkHandle,
+    output: &mut dyn WritableVector,
+    text: fn(&[u8]) -> Option<String>,
+) {
+    let mut output_vector = output.flat_vector();
+    for_each_blob(input, 0, |i, raw_data| {
+        match raw_data.and_then(|raw_data| with_decompressed(raw_data, text)) {
+            Some(text) => output_vector.insert(i, text.as_str()),
+            None => output_vector.set_null(i),
+        }
+    });
+}
+
+fn record_text_signatures() -> Vec<ScalarFunctionSignature> {
+    vec![
+        ScalarFunctionSignature::exact(
+            vec![LogicalTypeHandle::from(LogicalTypeId::Blob)],
+            LogicalTypeHandle::from(LogicalTypeId::Varchar),
+        ),
+        ScalarFunctionSignature::exact(
+            vec![LogicalTypeHandle::from(LogicalTypeId::Varchar)],
+            LogicalTypeHandle::from(LogicalTypeId::Varchar),
+        ),
+    ]
+}
+
+/// DuckDB scalar function returning the `<title>` of an HTML response record
+///
+/// Returns NULL for non-response records, non-HTML responses and pages
+/// without a title.
+pub(crate) struct WarcHtmlTitle;
+
+impl VScalar for WarcHtmlTitle {
+    type State = ();
+
+    unsafe fn invoke(
+        _state: &Self::State,
+        input: &mut DataChunkHandle,
+        output: &mut dyn WritableVector,
+    ) -> Result<(), Box<dyn Error>> {
+        invoke_record_text(input, output, response_title);
+        Ok(())
+    }
+
+    fn signatures() -> Vec<ScalarFunctionSignature> {
+        record_text_signatures()
+    }
+}
+
+/// DuckDB scalar function returning the `<link rel="canonical">` URL of an HTML response record
+///
+/// Returns NULL for non-response records, non-HTML responses and pages
+/// without a canonical link.
+pub(crate) struct WarcCanonicalUrl;
+
+impl VScalar for WarcCanonicalUrl {
+    type State = ();
+
+    unsafe fn invoke(
+        _state: &Self::State,
+        input: &mut DataChunkHandle,
+        output: &mut dyn WritableVector,
+    ) -> Result<(), Box<dyn Error>> {
+        invoke_record_text(input, output, response_canonical_url);
+        Ok(())
+    }
+
+    fn signatures() -> Vec<ScalarFunctionSignature> {
+        record_text_signatures()
+    }
+}
+
+/// DuckDB scalar function returning the `<form>` elements of an HTML response record
+///
+/// Returns a LIST of STRUCT(action, method, field_names), empty for
+/// non-response records and non-HTML responses.
+pub(crate) struct WarcExtractForms;
+
+impl VScalar for WarcExtractForms {
+    type State = ();
+
+    unsafe fn invoke(
+        _state: &Self::State,
+        input: &mut DataChunkHandle,
+        output: &mut dyn WritableVector,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut rows = Vec::with_capacity(input.len());
+        for_each_blob(input, 0, |_, raw_data| {
+            rows.push(raw_data.map(|raw_data| with_decompressed(raw_data, response_forms)));
+        });
+
+        let mut list_vector = output.list_vector();
+        let total: usize = rows.iter().flatten().map(Vec::len).sum();
+        let total_names: usize = rows.iter().flatten().flatten().map(|(_, form)| form.field_names.len()).sum();
+        let form_struct = list_vector.struct_child(total);
+        let mut action_vec = form_struct.child(0, total);
+        let method_vec = form_struct.child(1, total);
+        let mut names_list = form_struct.list_vector_child(2);
+        let names_vec = names_list.child(total_names);
+
+        let mut offset = 0;
+        let mut names_offset = 0;
+        for (i, forms) in rows.iter().enumerate() {
+            let Some(forms) = forms else {
+                list_vector.set_null(i);
+                continue;
+            };
+            list_vector.set_entry(i, offset, forms.len());
+            for (action, form) in forms {
+                match action {
+                    Some(v) => action_vec.insert(offset, v.as_str()),
+                    None => action_vec.set_null(offset),
+                }
+                method_vec.insert(offset, sanitize_for_ffi(&form.method).as_str());
+                names_list.set_entry(offset, names_offset, form.field_names.len());
+                for name in &form.field_names {
+                    names_vec.insert(names_offset, sanitize_for_ffi(name).as_str());
+                    names_offset += 1;
+                }
+                offset += 1;
+            }
+        }
+        names_list.set_len(total_names);
+        list_vector.set_len(total);
+
+        Ok(())
+    }
+
+    fn signatures() -> Vec<ScalarFunctionSignature> {
+        let make_return_type = || {
+            LogicalTypeHandle::list(&LogicalTypeHandle::struct_type(&[
+                ("action", LogicalTypeHandle::from(LogicalTypeId::Varchar)),
+                ("method", LogicalTypeHandle::from(LogicalTypeId::Varchar)),
+                (
+                    "field_names",
+                    LogicalTypeHandle::list(&LogicalTypeHandle::from(LogicalTypeId::Varchar)),
+                ),
+            ]))
+        };
+        vec![
+            ScalarFunctionSignature::exact(vec![LogicalTypeHandle::from(LogicalTypeId::Blob)], make_return_type()),
+            ScalarFunctionSignature::exact(
+                vec![LogicalTypeHandle::from(LogicalTypeId::Varchar)],
+                make_return_type(),
+            ),
+        ]
+    }
+}
+
+/// DuckDB scalar function returning the JSON-LD blocks of an HTML response record
+///
+/// Returns a LIST(JSON) of each well-formed `<script type="application/ld+json">`
+/// block, empty for non-response records and non-HTML responses.
+pub(crate) struct WarcJsonld;
+
+impl VScalar for WarcJsonld {
+    type State = ();
+
+    unsafe fn invoke(
+        _state: &Self::State,
+        input: &mut DataChunkHandle,
+        output: &mut dyn WritableVector,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut rows = Vec::with_capacity(input.len());
+        for_each_blob(input, 0, |_, raw_data| {
+            rows.push(raw_data.map(|raw_data| with_decompressed(raw_data, response_jsonld)));
+        });
+
+        let mut list_vector = output.list_vector();
+        let total: usize = rows.iter().flatten().map(Vec::len).sum();
+        let block_vec = list_vector.child(total);
+
+        let mut offset = 0;
+        for (i, blocks) in rows.iter().enumerate() {
+            let Some(blocks) = blocks else {
+                list_vector.set_null(i);
+                continue;
+            };
+            list_vector.set_entry(i, offset, blocks.len());
+            for block in blocks {
+                block_vec.insert(offset, block.as_str());
+                offset += 1;
+            }
+        }
+        list_vector.set_len(total);
+
+        Ok(())
+    }
+
+    fn signatures() -> Vec<ScalarFunctionSignature> {
+        // DuckDB's JSON type is VARCHAR under the JSON alias
+        let make_return_type = || {
+            let json = LogicalTypeHandle::from(LogicalTypeId::Varchar);
+            json.set_alias("JSON");
+            LogicalTypeHandle::list(&json)
+        };
+        vec![
+            ScalarFunctionSignature::exact(vec![LogicalTypeHandle::from(LogicalTypeId::Blob)], make_return_type()),
+            ScalarFunctionSignature::exact(
+                vec![LogicalTypeHandle::from(LogicalTypeId::Varchar)],
+                make_return_type(),
+            ),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::warc_file::tests::warc_record;
+
+    #[test]
+    fn test_response_title_example() {
+        let data = std::fs::read("test-data/example.warc").unwrap();
+        assert_eq!(response_title(&data).as_deref(), Some("Example Domain"));
+
+        // Not HTML, or not a response
+        let json = warc_record("response", b"HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\r\n{\"title\": 1}");
+        assert_eq!(response_title(&json), None);
+        let request = warc_record("request", b"GET /<title>x</title> HTTP/1.1\r\n\r\n");
+        assert_eq!(response_title(&request), None);
+    }
+
+    #[test]
+    fn test_response_title_chunked_gzip() {
+        let gzipped = crate::gzip::gzip_compress(b"<title>Encoded</title>").unwrap();
+        let block = [
+            &b"HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nTransfer-Encoding: chunked\r\n\
+               Content-Encoding: gzip\r\n\r\n"[..],
+            format!("{:x}\r\n", gzipped.len()).as_bytes(),
+            &gzipped,
+            b"\r\n0\r\n\r\n",
+        ]
+        .concat();
+        assert_eq!(response_title(&warc_record("response", &block)).as_deref(), Some("Encoded"));
+    }
+
+    #[test]
+    fn test_html_title() {
+        assert_eq!(
+            html_title(b"<HTML><Title lang=en>\n  Tom &amp; Jerry &#8211;\n  &#x27;Cartoons&#39; </TITLE>").as_deref(),
+            Some("Tom & Jerry \u{2013} 'Cartoons'")
+        );
+        // Unknown references are kept, a missing close tag runs to the end
+        assert_eq!(html_title(b"<title>a &copy; &b").as_deref(), Some("a &copy; &b"));
+        // <titles> is not <title>
+        assert_eq!(html_title(b"<titles>x</titles><p>no title</p>"), None);
+        assert_eq!(html_title(b"<title></title>").as_deref(), Some(""));
+    }
+
+    #[test]
+    fn test_canonical_href() {
+        let html = b"<link rel=stylesheet href=/a.css><LINK HREF='/page?a=1&amp;b=2' Rel=\"Canonical Alternate\">";
+        assert_eq!(canonical_href(html).as_deref(), Some("/page?a=1&b=2"));
+        // A `>` inside a quoted value doesn't end the tag; <linked> is not <link>
+        assert_eq!(
+            canonical_href(b"<linked rel=canonical href=/x><link title=\"a>b\" rel=canonical href=https://e.com/>")
+                .as_deref(),
+            Some("https://e.com/")
+        );
+        assert_eq!(canonical_href(b"<link rel=canonical><p>no href</p>"), None);
+    }
+
+    #[test]
+    fn test_html_forms() {
+        let html = b"<form action=\"/search?lang=en&amp;x=1\"><input type=text name=q>\
+                     <!-- <input name=commented> --><select name='sort'><option>a</select>\
+                     <input type=submit><button name=go>Go</button></form>\
+                     <input name=outside>\
+                     <FORM METHOD=post><TEXTAREA NAME=comment></TEXTAREA>";
+        assert_eq!(
+            html_forms(html),
+            vec![
+                HtmlForm {
+                    action: "/search?lang=en&x=1".to_string(),
+                    method: "GET".to_string(),
+                    field_names: vec!["q".to_string(), "sort".to_string(), "go".to_string()],
+                },
+                HtmlForm {
+                    action: String::new(),
+                    method: "POST".to_string(),
+                    field_names: vec!["comment".to_string()],
+                },
+            ]
+        );
+        assert!(html_forms(b"<p>no forms</p>").is_empty());
+    }
+
+    #[test]
+    fn test_response_forms() {
+        let block = b"HTTP/1.1 200 OK\r\nContent-Type: text/html\r\n\r\n\
+                      <form action=find method=get><input name=q></form><form><input name=email></form>";
+        let record = String::from_utf8(warc_record("response", block))
+            .unwrap()
+            .replacen("\r\n", "\r\nWARC-Target-URI: https://example.com/help/index.html\r\n", 1);
+        let forms = response_forms(record.as_bytes());
+        let actions: Vec<Option<&str>> = forms.iter().map(|(action, _)| action.as_deref()).collect();
+        assert_eq!(actions, [Some("https://example.com/help/find"), Some("https://example.com/help/index.html")]);
+        assert_eq!(forms[0].1.field_names, ["q"]);
+
+        // Not HTML
+        let json = warc_record("response", b"HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\r\n<form>");
+        assert!(response_forms(&json).is_empty());
+    }
+
+    #[test]
+    fn test_meta_refresh_href() {
+        let html = b"<meta charset=utf-8><META HTTP-EQUIV=\"Refresh\" CONTENT=\"0; URL='/moved?a=1&amp;b=2'\">";
+        assert_eq!(meta_refresh_href(html).as_deref(), Some("/moved?a=1&b=2"));
+        assert_eq!(
+            meta_refresh_href(b"<meta http-equiv=refresh content=\"5;https://example.com/next\">").as_deref(),
+            Some("https://example.com/next")
+        );
+        // A refresh that reloads the page, or no refresh at all
+        assert_eq!(meta_refresh_href(b"<meta http-equiv=\"refresh\" content=\"30\">"), None);
+        assert_eq!(meta_refresh_href(b"<meta name=refresh content=\"0;url=/x\">"), None);
+    }
+
+    #[test]
+    fn test_response_canonical_url() {
+        let block = b"HTTP/1.1 200 OK\r\nContent-Type: text/html\r\n\r\n\
+                      <html><head><link rel=\"canonical\" href=\"../article?id=7\"></head></html>";
+        let record = String::from_utf8(warc_record("response", block))
+            .unwrap()
+            .replacen("\r\n", "\r\nWARC-Target-URI: https://example.com/news/2024/article?id=7&utm=x\r\n", 1);
+        assert_eq!(
+            response_canonical_url(record.as_bytes()).as_deref(),
+            Some("https://example.com/news/article?id=7")
+        );
+
+        // Without a target URI only absolute hrefs resolve
+        assert_eq!(response_canonical_url(&warc_record("response", block)), None);
+        let data = std::fs::read("test-data/example.warc").unwrap();
+        assert_eq!(response_canonical_url(&data), None);
+    }
+
+    #[test]
+    fn test_response_jsonld() {
+        let block = b"HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\n\r\n<html><head>\
+                      <script type=\"application/ld+json\">\n{\"@context\": \"https://schema.org\",\n \
+                      \"@type\": \"Product\", \"name\": \"Caf\xc3\xa9 <b>\", \"price\": 9.90}\n</script>\
+                      <script>var x = {};</script>\
+                      <SCRIPT TYPE='Application/LD+JSON'>{\"@type\": \"Organization\",}</SCRIPT>\
+                      <script type=application/ld+json>[{\"@type\": \"BreadcrumbList\"}]</script>\
+                      </head></html>";
+        assert_eq!(
+            response_jsonld(&warc_record("response", block)),
+            [
+                "{\"@context\":\"https://schema.org\",\"@type\":\"Product\",\"name\":\"Caf\u{e9} <b>\",\"price\":9.90}",
+                "[{\"@type\":\"BreadcrumbList\"}]",
+            ]
+        );
+
+        // Not HTML, or no JSON-LD
+        let json = warc_record("response", b"HTTP/1.1 200 OK\r\nContent-Type: application/ld+json\r\n\r\n{}");
+        assert!(response_jsonld(&json).is_empty());
+        let data = std::fs::read("test-data/example.warc").unwrap();
+        assert!(response_jsonld(&data).is_empty());
+    }
+}
@@ -0,0 +1,273 @@
+/// Parse an RFC 7231 HTTP-date into microseconds since the Unix epoch
+///
+/// Accepts the preferred IMF-fixdate (`Sun, 06 Nov 1994 08:49:37 GMT`) and the
+/// two obsolete forms recipients must still accept: RFC 850
+/// (`Sunday, 06-Nov-94 08:49:37 GMT`) and asctime (`Sun Nov  6 08:49:37 1994`).
+/// The weekday name is not checked against the date.
+pub(crate) fn parse_http_date(s: &str) -> Option<i64> {
+    let parts: Vec<&str> = s.split_whitespace().collect();
+    let (year, month, day, time) = match parts.as_slice() {
+        // IMF-fixdate
+        [weekday, day, month, year, time, "GMT"] if weekday.ends_with(',') => {
+            (year.parse::<i64>().ok()?, month_number(month)?, day.parse::<u32>().ok()?, *time)
+        }
+        // RFC 850
+        [weekday, date, time, "GMT"] if weekday.ends_with(',') => {
+            let mut fields = date.splitn(3, '-');
+            let day = fields.next()?.parse::<u32>().ok()?;
+            let month = month_number(fields.next()?)?;
+            let year = fields.next()?;
+            if year.len() != 2 {
+                return None;
+            }
+            let year = year.parse::<i64>().ok()?;
+            // Two-digit years are taken to be within 1970..=2069
+            let year = if year < 70 { 2000 + year } else { 1900 + year };
+            (year, month, day, *time)
+        }
+        // asctime
+        [_weekday, month, day, time, year] => {
+            (year.parse::<i64>().ok()?, month_number(month)?, day.parse::<u32>().ok()?, *time)
+        }
+        _ => return None,
+    };
+
+    let mut hms = time.splitn(3, ':');
+    let hour = hms.next()?.parse::<i64>().ok()?;
+    let minute = hms.next()?.parse::<i64>().ok()?;
+    let second = hms.next()?.parse::<i64>().ok()?;
+    // 60 allows for a leap second
+    if hour > 23 || minute > 59 || second > 60 || day == 0 || day > days_in_month(year, month) {
+        return None;
+    }
+
+    let days = days_from_civil(year, month, day);
+    Some((((days * 24 + hour) * 60 + minute) * 60 + second) * 1_000_000)
+}
+
+/// Parse a WARC-Date (W3C-ISO8601 in UTC) into microseconds since the Unix epoch
+///
+/// Accepts `2025-11-06T20:10:40Z`, fractional seconds as allowed by WARC 1.1
+/// (`2025-11-06T20:10:40.123456Z`), a space instead of the `T`, a missing
+/// `Z`, and a bare date (midnight), so the same parser validates user input.
+pub(crate) fn parse_warc_date(s: &str) -> Option<i64> {
+    let s = s.trim();
+    let s = s.strip_suffix('Z').unwrap_or(s);
+    let (date, time) = match s.split_once(['T', ' ']) {
+        Some((date, time)) => (date, Some(time)),
+        None => (s, None),
+    };
+
+    let mut ymd = date.splitn(3, '-');
+    let year = ymd.next().filter(|y| y.len() == 4)?.parse::<i64>().ok()?;
+    let month = ymd.next().filter(|m| m.len() == 2)?.parse::<u32>().ok()?;
+    let day = ymd.next().filter(|d| d.len() == 2)?.parse::<u32>().ok()?;
+    if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+        return None;
+    }
+
+    let mut micros_of_day = 0;
+    if let Some(time) = time {
+        let (hms, fraction) = match time.split_once('.') {
+            Some((hms, fraction)) => (hms, Some(fraction)),
+            None => (time, None),
+        };
+        let mut fields = hms.splitn(3, ':');
+        let hour = fields.next()?.parse::<i64>().ok()?;
+        let minute = fields.next()?.parse::<i64>().ok()?;
+        let second = fields.next().map_or(Some(0), |s| s.parse::<i64>().ok())?;
+        if hour > 23 || minute > 59 || second > 60 {
+            return None;
+        }
+        let fraction_micros = match fraction {
+            Some(digits) if !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()) => {
+                // Keep microsecond precision, padding or cutting the digits
+                format!("{:0<6}", &digits[..digits.len().min(6)]).parse::<i64>().ok()?
+            }
+            Some(_) => return None,
+            None => 0,
+        };
+        micros_of_day = ((hour * 60 + minute) * 60 + second) * 1_000_000 + fraction_micros;
+    }
+
+    Some(days_from_civil(year, month, day) * 86_400_000_000 + micros_of_day)
+}
+
+/// Parse the WARC-Date of a record, trying the formats seen in the wild in turn
+///
+/// The W3C-ISO8601 form of the spec comes first (`parse_warc_date`), then an
+/// RFC 3339 UTC offset (`2025-11-06T22:10:40+02:00`), the 14-digit timestamp
+/// of ARC files and CDX indexes (`20251106201040`) and an HTTP-date. None only
+/// when every format fails.
+pub(crate) fn parse_record_date(s: &str) -> Option<i64> {
+    let s = s.trim();
+    parse_warc_date(s)
+        .or_else(|| parse_offset_date(s))
+        .or_else(|| parse_compact_date(s))
+        .or_else(|| parse_http_date(s))
+}
+
+/// ISO-8601 date-time with a numeric UTC offset (`+02:00`, `-0500`)
+fn parse_offset_date(s: &str) -> Option<i64> {
+    let (date, time) = s.split_once(['T', ' '])?;
+    let sign_pos = time.rfind(['+', '-'])?;
+    let (time, offset) = time.split_at(sign_pos);
+    let sign = if offset.starts_with('-') { -1 } else { 1 };
+    let digits = offset[1..].replace(':', "");
+    if digits.len() != 4 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let hours = digits[..2].parse::<i64>().ok()?;
+    let minutes = digits[2..].parse::<i64>().ok()?;
+    if hours > 23 || minutes > 59 {
+        return None;
+    }
+    let local = parse_warc_date(&format!("{}T{}", date, time))?;
+    Some(local - sign * (hours * 60 + minutes) * 60_000_000)
+}
+
+/// `YYYYMMDDhhmmss` timestamp, as in ARC headers and CDX lines
+fn parse_compact_date(s: &str) -> Option<i64> {
+    if s.len() != 14 || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    parse_warc_date(&format!(
+        "{}-{}-{}T{}:{}:{}",
+        &s[..4],
+        &s[4..6],
+        &s[6..8],
+        &s[8..10],
+        &s[10..12],
+        &s[12..]
+    ))
+}
+
+/// Format microseconds since the Unix epoch as a WARC-Date (`2025-11-06T20:10:40Z`)
+///
+/// Sub-second precision is kept as a 6-digit fraction when there is any.
+pub(crate) fn format_warc_date(micros: i64) -> String {
+    let days = micros.div_euclid(86_400_000_000);
+    let micros_of_day = micros.rem_euclid(86_400_000_000);
+    let (year, month, day) = civil_from_days(days);
+    let seconds = micros_of_day / 1_000_000;
+    let fraction = micros_of_day % 1_000_000;
+    let mut date = format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    );
+    if fraction != 0 {
+        date.push_str(&format!(".{:06}", fraction));
+    }
+    date.push('Z');
+    date
+}
+
+fn month_number(name: &str) -> Option<u32> {
+    const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+    MONTHS.iter().position(|m| m.eq_ignore_ascii_case(name)).map(|i| i as u32 + 1)
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date (Howard Hinnant's algorithm)
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// Proleptic Gregorian date of a day count since 1970-01-01, the inverse of `days_from_civil`
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 1994-11-06 08:49:37 UTC
+    const EXAMPLE_MICROS: i64 = 784_111_777_000_000;
+
+    #[test]
+    fn test_parse_http_date_formats() {
+        assert_eq!(parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT"), Some(EXAMPLE_MICROS));
+        assert_eq!(parse_http_date("Sunday, 06-Nov-94 08:49:37 GMT"), Some(EXAMPLE_MICROS));
+        assert_eq!(parse_http_date("Sun Nov  6 08:49:37 1994"), Some(EXAMPLE_MICROS));
+        assert_eq!(parse_http_date("Thu, 01 Jan 1970 00:00:00 GMT"), Some(0));
+        assert_eq!(parse_http_date("Thu, 29 Feb 2024 12:00:00 GMT"), Some(1_709_208_000_000_000));
+    }
+
+    #[test]
+    fn test_parse_warc_date() {
+        assert_eq!(parse_warc_date("1994-11-06T08:49:37Z"), Some(EXAMPLE_MICROS));
+        assert_eq!(parse_warc_date("1994-11-06 08:49:37"), Some(EXAMPLE_MICROS));
+        assert_eq!(parse_warc_date("1994-11-06T08:49:37.25Z"), Some(EXAMPLE_MICROS + 250_000));
+        assert_eq!(parse_warc_date("1970-01-02"), Some(86_400_000_000));
+
+        assert_eq!(parse_warc_date("06/11/1994"), None);
+        assert_eq!(parse_warc_date("1994-13-01"), None);
+        assert_eq!(parse_warc_date("1994-11-06T25:00:00Z"), None);
+        assert_eq!(parse_warc_date("1994-11-06T08:49:37.Z"), None);
+        assert_eq!(parse_warc_date(""), None);
+    }
+
+    #[test]
+    fn test_parse_record_date_variants() {
+        // The standard format, then each fallback
+        assert_eq!(parse_record_date("1994-11-06T08:49:37Z"), Some(EXAMPLE_MICROS));
+        assert_eq!(parse_record_date("1994-11-06T09:49:37+01:00"), Some(EXAMPLE_MICROS));
+        assert_eq!(parse_record_date("1994-11-06T03:49:37.5-0500"), Some(EXAMPLE_MICROS + 500_000));
+        assert_eq!(parse_record_date("19941106084937"), Some(EXAMPLE_MICROS));
+        assert_eq!(parse_record_date(" Sun, 06 Nov 1994 08:49:37 GMT"), Some(EXAMPLE_MICROS));
+
+        assert_eq!(parse_record_date("1994-11-06T08:49:37+25:00"), None);
+        assert_eq!(parse_record_date("1994110608493"), None);
+        assert_eq!(parse_record_date("yesterday"), None);
+    }
+
+    #[test]
+    fn test_format_warc_date_round_trip() {
+        assert_eq!(format_warc_date(EXAMPLE_MICROS), "1994-11-06T08:49:37Z");
+        assert_eq!(format_warc_date(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_warc_date(-1), "1969-12-31T23:59:59.999999Z");
+        for date in ["2024-02-29T12:00:00Z", "2000-03-01T00:00:00.25Z", "1600-01-01T23:59:59Z"] {
+            assert_eq!(parse_warc_date(&format_warc_date(parse_warc_date(date).unwrap())), parse_warc_date(date));
+        }
+        assert_eq!(format_warc_date(parse_warc_date("2000-03-01T00:00:00.25Z").unwrap()), "2000-03-01T00:00:00.250000Z");
+    }
+
+    #[test]
+    fn test_parse_http_date_invalid() {
+        assert_eq!(parse_http_date("2024-02-29T12:00:00Z"), None);
+        assert_eq!(parse_http_date("Thu, 29 Feb 2023 12:00:00 GMT"), None);
+        assert_eq!(parse_http_date("Sun, 06 Nov 1994 08:49:37 PST"), None);
+        assert_eq!(parse_http_date("Sun, 06 Foo 1994 08:49:37 GMT"), None);
+        assert_eq!(parse_http_date("Sun, 06 Nov 1994 25:49:37 GMT"), None);
+        assert_eq!(parse_http_date(""), None);
+    }
+}
@@ -0,0 +1,155 @@
+use crate::content_coding::decode_http_body;
+use crate::{for_each_blob, parse_http_response, read_first_record_lenient, with_decompressed};
+use duckdb::{
+    core::{DataChunkHandle, Inserter, LogicalTypeHandle, LogicalTypeId},
+    ffi,
+    vscalar::{ScalarFunctionSignature, VScalar},
+    vtab::arrow::WritableVector,
+};
+use image::ImageReader;
+use std::error::Error;
+use std::io::Cursor;
+use warc_rs::WarcHeader;
+
+/// Format and pixel dimensions of an image
+#[derive(Debug, PartialEq)]
+pub(crate) struct ImageInfo {
+    pub format: &'static str, // MIME subtype: "png", "jpeg", "gif", "webp" or "bmp"
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Probe an image's format from its leading bytes and its dimensions from its
+/// header, without decoding the pixels; None for other bodies
+pub(crate) fn image_info(body: &[u8]) -> Option<ImageInfo> {
+    let reader = ImageReader::new(Cursor::new(body)).with_guessed_format().ok()?;
+    let format = reader.format()?;
+    let (width, height) = reader.into_dimensions().ok()?;
+    Some(ImageInfo {
+        format: format.to_mime_type().trim_start_matches("image/"),
+        width,
+        height,
+    })
+}
+
+/// Image info of a response record's decoded body
+///
+/// The body is recognized by its bytes, whatever its declared Content-Type.
+/// None for other records and for bodies that aren't a supported image.
+pub(crate) fn response_image_info(data: &[u8]) -> Option<ImageInfo> {
+    let record = read_first_record_lenient(data).ok()?;
+    if record.header(WarcHeader::WarcType)? != "response" {
+        return None;
+    }
+    let response = parse_http_response(record.body());
+    let body = decode_http_body(
+        response.body?,
+        response.transfer_encoding.as_deref(),
+        response.content_encoding.as_deref(),
+    );
+    image_info(&body)
+}
+
+/// DuckDB scalar function returning the format and size of an image response
+///
+/// Returns a STRUCT(format, width, height), NULL for non-response records and
+/// bodies that aren't a PNG, JPEG, GIF, WebP or BMP image.
+pub(crate) struct WarcImageInfo;
+
+impl VScalar for WarcImageInfo {
+    type State = ();
+
+    unsafe fn invoke(
+        _state: &Self::State,
+        input: &mut DataChunkHandle,
+        output: &mut dyn WritableVector,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut infos = Vec::with_capacity(input.len());
+        for_each_blob(input, 0, |_, raw_data| {
+            infos.push(raw_data.and_then(|raw_data| with_decompressed(raw_data, response_image_info)));
+        });
+
+        let mut output_struct = output.struct_vector();
+        let capacity = ffi::duckdb_vector_size() as usize;
+        let mut format_vec = output_struct.child(0, capacity);
+        let mut width_vec = output_struct.child(1, capacity);
+        let mut height_vec = output_struct.child(2, capacity);
+
+        for (i, info) in infos.iter().enumerate() {
+            match info {
+                Some(info) => {
+                    format_vec.insert(i, info.format);
+                    width_vec.as_mut_slice::<i32>()[i] = info.width.try_into().unwrap_or(i32::MAX);
+                    height_vec.as_mut_slice::<i32>()[i] = info.height.try_into().unwrap_or(i32::MAX);
+                }
+                None => {
+                    format_vec.set_null(i);
+                    width_vec.set_null(i);
+                    height_vec.set_null(i);
+                    output_struct.set_null(i);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn signatures() -> Vec<ScalarFunctionSignature> {
+        let make_return_type = || {
+            LogicalTypeHandle::struct_type(&[
+                ("format", LogicalTypeHandle::from(LogicalTypeId::Varchar)),
+                ("width", LogicalTypeHandle::from(LogicalTypeId::Integer)),
+                ("height", LogicalTypeHandle::from(LogicalTypeId::Integer)),
+            ])
+        };
+        vec![
+            ScalarFunctionSignature::exact(vec![LogicalTypeHandle::from(LogicalTypeId::Blob)], make_return_type()),
+            ScalarFunctionSignature::exact(
+                vec![LogicalTypeHandle::from(LogicalTypeId::Varchar)],
+                make_return_type(),
+            ),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::warc_file::tests::warc_record;
+    use image::{ImageFormat, RgbImage};
+
+    fn encode(width: u32, height: u32, format: ImageFormat) -> Vec<u8> {
+        let mut bytes = Cursor::new(Vec::new());
+        RgbImage::new(width, height).write_to(&mut bytes, format).unwrap();
+        bytes.into_inner()
+    }
+
+    #[test]
+    fn test_response_image_info() {
+        let png = encode(3, 2, ImageFormat::Png);
+        let response = |content_type: &str, body: &[u8]| {
+            let head = format!("HTTP/1.1 200 OK\r\nContent-Type: {}\r\n\r\n", content_type);
+            warc_record("response", &[head.as_bytes(), body].concat())
+        };
+        assert_eq!(
+            response_image_info(&response("image/png", &png)),
+            Some(ImageInfo {
+                format: "png",
+                width: 3,
+                height: 2,
+            })
+        );
+        // Recognized by its bytes, not its declared type
+        let gif = encode(640, 480, ImageFormat::Gif);
+        let info = response_image_info(&response("application/octet-stream", &gif)).unwrap();
+        assert_eq!((info.format, info.width, info.height), ("gif", 640, 480));
+
+        let jpeg = encode(17, 9, ImageFormat::Jpeg);
+        let info = image_info(&jpeg).unwrap();
+        assert_eq!((info.format, info.width, info.height), ("jpeg", 17, 9));
+
+        assert_eq!(response_image_info(&response("text/html", b"<html></html>")), None);
+        assert_eq!(response_image_info(&warc_record("resource", &png)), None);
+        assert_eq!(image_info(&png[..8]), None);
+    }
+}
@@ -0,0 +1,128 @@
+use crate::content_coding::decode_http_body;
+use crate::transcode::source_encoding;
+use crate::{for_each_blob, parse_http_response, read_first_record_lenient, with_decompressed};
+use duckdb::{
+    core::{DataChunkHandle, Inserter, LogicalTypeHandle, LogicalTypeId},
+    vscalar::{ScalarFunctionSignature, VScalar},
+    vtab::arrow::WritableVector,
+};
+use std::error::Error;
+use warc_rs::WarcHeader;
+
+/// Whether a Content-Type is JSON: `application/json`, `text/json` or a `+json` type
+fn is_json_content_type(content_type: &str) -> bool {
+    let mime = content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+    mime == "application/json" || mime == "text/json" || mime.ends_with("+json")
+}
+
+/// Body of a JSON response record, re-serialized compact or pretty-printed
+///
+/// The body is de-chunked, decompressed and decoded from its charset first.
+/// Key order and numbers are kept as written. None for other records and
+/// content types, and for bodies that aren't well-formed JSON.
+pub(crate) fn response_json(data: &[u8], pretty: bool) -> Option<String> {
+    let record = read_first_record_lenient(data).ok()?;
+    if record.header(WarcHeader::WarcType)? != "response" {
+        return None;
+    }
+    let response = parse_http_response(record.body());
+    let content_type = response.content_type.as_deref()?;
+    if !is_json_content_type(content_type) {
+        return None;
+    }
+    let body = decode_http_body(
+        response.body?,
+        response.transfer_encoding.as_deref(),
+        response.content_encoding.as_deref(),
+    );
+    let (text, _, _) = source_encoding(Some(content_type), &body).decode(&body);
+    let value: serde_json::Value = serde_json::from_str(&text).ok()?;
+    if pretty {
+        serde_json::to_string_pretty(&value).ok()
+    } else {
+        Some(value.to_string())
+    }
+}
+
+/// DuckDB scalar function returning the body of a JSON response record as JSON
+///
+/// An optional BOOLEAN argument pretty-prints it. Returns NULL for
+/// non-response records, non-JSON content types and malformed bodies.
+pub(crate) struct WarcBodyJson;
+
+impl VScalar for WarcBodyJson {
+    type State = ();
+
+    unsafe fn invoke(
+        _state: &Self::State,
+        input: &mut DataChunkHandle,
+        output: &mut dyn WritableVector,
+    ) -> Result<(), Box<dyn Error>> {
+        let size = input.len();
+        let pretty = (input.num_columns() > 1).then(|| input.flat_vector(1).as_slice_with_len::<bool>(size).to_vec());
+        let mut output_vector = output.flat_vector();
+        for_each_blob(input, 0, |i, raw_data| {
+            let pretty = pretty.as_ref().is_some_and(|pretty| pretty[i]);
+            match raw_data.and_then(|raw_data| with_decompressed(raw_data, |data| response_json(data, pretty))) {
+                Some(json) => output_vector.insert(i, json.as_str()),
+                None => output_vector.set_null(i),
+            }
+        });
+
+        Ok(())
+    }
+
+    fn signatures() -> Vec<ScalarFunctionSignature> {
+        // DuckDB's JSON type is VARCHAR under the JSON alias
+        let make_return_type = || {
+            let json = LogicalTypeHandle::from(LogicalTypeId::Varchar);
+            json.set_alias("JSON");
+            json
+        };
+        let make_input_type = |blob: bool| {
+            LogicalTypeHandle::from(if blob { LogicalTypeId::Blob } else { LogicalTypeId::Varchar })
+        };
+        [true, false]
+            .into_iter()
+            .flat_map(|blob| {
+                [
+                    ScalarFunctionSignature::exact(vec![make_input_type(blob)], make_return_type()),
+                    ScalarFunctionSignature::exact(
+                        vec![make_input_type(blob), LogicalTypeHandle::from(LogicalTypeId::Boolean)],
+                        make_return_type(),
+                    ),
+                ]
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::warc_file::tests::warc_record;
+
+    #[test]
+    fn test_response_json() {
+        let body = "{\"b\": [1, 2.50, 12345678901234567890123], \"a\": {\"name\": \"caf\u{e9}\"}}";
+        let http = format!("HTTP/1.1 200 OK\r\nContent-Type: application/json; charset=utf-8\r\n\r\n{}", body);
+        let record = warc_record("response", http.as_bytes());
+        assert_eq!(
+            response_json(&record, false).as_deref(),
+            Some("{\"b\":[1,2.50,12345678901234567890123],\"a\":{\"name\":\"caf\u{e9}\"}}")
+        );
+        let pretty = response_json(&record, true).unwrap();
+        assert!(pretty.starts_with("{\n  \"b\": [\n    1,"));
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&pretty).unwrap()["a"]["name"], "caf\u{e9}");
+
+        // Vendor types count, HTML and malformed bodies don't
+        let api = warc_record("response", b"HTTP/1.1 200 OK\r\nContent-Type: application/vnd.api+json\r\n\r\n[true]");
+        assert_eq!(response_json(&api, false).as_deref(), Some("[true]"));
+        let html = warc_record("response", b"HTTP/1.1 200 OK\r\nContent-Type: text/html\r\n\r\n{}");
+        assert_eq!(response_json(&html, false), None);
+        let truncated = warc_record("response", b"HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\r\n{\"a\":");
+        assert_eq!(response_json(&truncated, false), None);
+        let request = warc_record("request", b"POST / HTTP/1.1\r\nContent-Type: application/json\r\n\r\n{}");
+        assert_eq!(response_json(&request, false), None);
+    }
+}
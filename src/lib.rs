@@ -2,8 +2,34 @@ extern crate duckdb;
 extern crate duckdb_loadable_macros;
 extern crate libduckdb_sys;
 
+mod canonical_uri;
+mod content_coding;
+mod cookies;
+mod digest;
+mod domain;
+mod error;
+mod gzip;
+mod html;
+mod http_date;
+mod image_info;
+mod json_body;
+mod link;
+mod list_header;
+mod meta;
+mod parallel;
+mod parameters;
+mod parse_warc_rows;
+mod read_warc;
+mod read_warc_members;
+mod read_warc_types;
+mod sniff;
+mod split;
+mod transcode;
+mod warc_fields;
+mod warc_file;
+
 use duckdb::{
-    core::{DataChunkHandle, Inserter, LogicalTypeHandle, LogicalTypeId},
+    core::{DataChunkHandle, FlatVector, Inserter, ListVector, LogicalTypeHandle, LogicalTypeId},
     ffi,
     types::DuckString,
     vscalar::{ScalarFunctionSignature, VScalar},
@@ -11,411 +37,3781 @@ use duckdb::{
     Connection, Result,
 };
 use duckdb_loadable_macros::duckdb_entrypoint_c_api;
+use error::WarcParseError;
 use flate2::read::GzDecoder;
 use libduckdb_sys::duckdb_string_t;
+use std::cell::Cell;
 use std::error::Error;
-use std::io::{BufReader, Read};
-use warc::{WarcHeader, WarcReader};
+use std::io::{self, BufRead, BufReader, Read};
+use sha2::{Digest, Sha256};
+use std::time::{SystemTime, UNIX_EPOCH};
+use uuid::Uuid;
+use warc_rs::{WarcHeader, WarcReader};
+
+/// WARC versions whose record layout we know how to parse
+const SUPPORTED_WARC_VERSIONS: [&str; 2] = ["1.0", "1.1"];
 
 /// Parsed WARC record with all required fields
+#[derive(Debug, Default, PartialEq)]
 struct ParsedRecord {
-    warc_version: String,
-    warc_headers: String,   // JSON map
+    warc_version: Option<String>,
+    warc_headers: Option<String>, // JSON map
     http_version: Option<String>,
     http_status: Option<i32>,
     http_headers: Option<String>, // JSON map
     http_body: Option<Vec<u8>>,   // Binary body data
+    skipped: bool,                // Body withheld by the content-type filter
+    http_status_line: Option<String>,
+    http_protocol: Option<&'static str>, // Normalized HTTP version
+    http_last_modified: Option<i64>,     // Microseconds since the epoch
+    http_etag: Option<String>,
+    parse_error: Option<String>, // Why the record was only partially parsed
+    http_method: Option<String>, // Request method of request records
+    warc_protocol: Option<String>, // WARC-Protocol values, comma-separated
+    warc_cipher_suite: Option<String>,
+    source_compression: Option<&'static str>, // How the input was decoded: "gzip" or "none"
+    body_preview: Option<String>, // Lossy text of the first decoded bytes of the body, even when skipped
+    body_sha256: Option<String>,  // Lowercase hex; only computed by `parse_warc_hashed`
+    likely_truncated: bool,       // WARC-Truncated set, or body shorter than its HTTP Content-Length
+    warc_filename: Option<String>, // WARC-Filename of warcinfo records
+    http_content_type: Option<String>, // Content-Type of the HTTP message, not the WARC one
+    http_payload_offset: Option<i64>,  // Offset of the HTTP body in the record block
+    warc_fields: Option<String>,       // JSON map of a warcinfo/metadata `application/warc-fields` block
+    likely_soft_404: bool,             // Heuristic: a 2xx text response that reads like an error page
+    metadata_outlinks: Option<Vec<String>>, // `outlink:` URLs of metadata records
+    metadata_via: Option<String>,           // `via:` URL of metadata records
+    warc_date: Option<i64>,                 // WARC-Date in microseconds since the epoch
+    sanitized_bytes_removed: Option<i32>,   // NUL bytes dropped from WARC and HTTP headers
+    warc_concurrent_to: Vec<String>,        // Every WARC-Concurrent-To value
+    content_type_mismatch: bool,            // Declared, identified and sniffed body types disagree
+    http_retry_after: Option<i32>,          // Retry-After as seconds to wait
+    http_age: Option<i32>,                  // Age in seconds
+    warc_source_uri: Option<String>,        // WARC-Source-URI: where the archived content was obtained
+    warc_header_bytes: Option<i32>,         // Size of the WARC header block, blank line included
+    http_header_bytes: Option<i32>,         // Size of the block before the HTTP payload
+    meta_refresh_url: Option<String>,       // `<meta http-equiv="refresh">` target of HTML responses
+    identified_payload_type: Option<String>, // WARC-Identified-Payload-Type: the crawler's sniffed MIME type
+    http_connection: Option<String>,        // Connection header value
+    keep_alive: Option<bool>,               // Whether the connection stays open after this message
+    warc_version_major: Option<i16>,        // `1` of WARC/1.0
+    warc_version_minor: Option<i16>,        // `0` of WARC/1.0
+    warnings: Vec<String>,                  // Non-fatal issues found while parsing, empty when none
+    body_encoded: bool,                     // `http_body` still carries HTTP transfer or content codings
+    http_vary: Vec<String>,                 // Field names of every Vary header of a response
+}
+
+impl ParsedRecord {
+    /// A record with only `parse_error` set, for input that couldn't be parsed
+    fn from_error(error: WarcParseError) -> Self {
+        Self::default().with_error(error)
+    }
+
+    /// This record, reporting `error` in `parse_error`
+    fn with_error(self, error: WarcParseError) -> Self {
+        Self {
+            parse_error: Some(error.to_string()),
+            ..self
+        }
+    }
+}
+
+/// Default number of body bytes in `body_preview`
+const DEFAULT_PREVIEW_LENGTH: usize = 256;
+
+/// Options controlling how much of a record is materialized
+struct ParseOptions {
+    content_types: Option<ContentTypeFilter>,
+    lowercase_header_names: bool, // Lowercase the keys of `warc_headers`
+    preview_length: usize,        // Body bytes kept in `body_preview`
+    hash_body: bool,              // Compute `body_sha256`
+    body_charset: Option<&'static encoding_rs::Encoding>, // Re-encode text response bodies into this
+    header_names: Option<HeaderAllowlist>, // Keep only these keys in `warc_headers` and `http_headers`
+    require_compressed: bool,              // Reject input that isn't gzip instead of parsing it as-is
+    decode_body: bool,                     // Decode bodies for the fields derived from their content
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            content_types: None,
+            lowercase_header_names: false,
+            preview_length: DEFAULT_PREVIEW_LENGTH,
+            hash_body: false,
+            body_charset: None,
+            header_names: None,
+            require_compressed: false,
+            decode_body: true,
+        }
+    }
+}
+
+/// Header names kept in the `warc_headers` and `http_headers` JSON maps,
+/// matched case-insensitively
+struct HeaderAllowlist {
+    names: Vec<String>,
+}
+
+impl HeaderAllowlist {
+    fn new<S: AsRef<str>>(names: &[S]) -> Self {
+        let names = names.iter().map(|name| name.as_ref().trim().to_string()).collect();
+        Self { names }
+    }
+
+    fn allows(&self, name: &str) -> bool {
+        self.names.iter().any(|allowed| allowed.eq_ignore_ascii_case(name))
+    }
+}
+
+/// Whether a header goes in a JSON map: always, unless an allowlist leaves it out
+fn header_kept(names: Option<&HeaderAllowlist>, name: &str) -> bool {
+    names.is_none_or(|names| names.allows(name))
+}
+
+/// HTTP Content-Type allow/deny list (entries prefixed with `!` are denied)
+///
+/// Entries match the media type essence case-insensitively, and `type/*`
+/// matches any subtype. An empty allow list admits everything not denied.
+struct ContentTypeFilter {
+    allow: Vec<String>,
+    deny: Vec<String>,
+}
+
+impl ContentTypeFilter {
+    fn new<S: AsRef<str>>(entries: &[S]) -> Self {
+        let mut allow = Vec::new();
+        let mut deny = Vec::new();
+        for entry in entries {
+            let entry = entry.as_ref().trim().to_lowercase();
+            match entry.strip_prefix('!') {
+                Some(denied) => deny.push(denied.trim().to_string()),
+                None => allow.push(entry),
+            }
+        }
+        Self { allow, deny }
+    }
+
+    fn allows(&self, content_type: Option<&str>) -> bool {
+        let essence = content_type
+            .map(|ct| ct.split(';').next().unwrap_or("").trim().to_lowercase())
+            .unwrap_or_default();
+        let matches = |pattern: &String| match pattern.strip_suffix('*') {
+            Some(prefix) => essence.starts_with(prefix),
+            None => essence == *pattern,
+        };
+
+        if self.deny.iter().any(matches) {
+            return false;
+        }
+        self.allow.is_empty() || self.allow.iter().any(matches)
+    }
 }
 
-/// Sanitize header value for JSON output (escape quotes, remove null bytes)
-fn sanitize_header(v: &std::borrow::Cow<str>) -> String {
-    v.replace('"', "\\\"").replace('\0', "")
+/// Parsed HTTP response; the body borrows from the WARC record block
+#[derive(Default)]
+struct HttpResponse<'a> {
+    version: Option<String>,
+    status: Option<i32>,
+    status_line: Option<String>,
+    protocol: Option<&'static str>,
+    headers: Option<String>,      // JSON map
+    header_pairs: Vec<(String, String)>, // The entries of `headers`, for allowlists
+    content_type: Option<String>, // Raw Content-Type header value
+    last_modified: Option<i64>,   // Last-Modified in microseconds since the epoch
+    etag: Option<String>,
+    content_length: Option<u64>, // Declared Content-Length
+    date: Option<i64>,           // Date in microseconds since the epoch
+    retry_after: Option<String>, // Raw Retry-After value: delay-seconds or an HTTP-date
+    age: Option<i32>,
+    transfer_encoding: Option<String>,
+    content_encoding: Option<String>,
+    body: Option<&'a [u8]>, // As captured, transfer and content codings included
+    payload_offset: Option<usize>, // Where `body` starts in the record block
+}
+
+/// Sanitize header value for JSON output (escape as a JSON string, remove null bytes)
+fn sanitize_header(v: &str) -> String {
+    let mut escaped = String::with_capacity(v.len());
+    for c in v.chars() {
+        match c {
+            '\0' => {}
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
 }
 
-/// Convert WARC headers to a JSON-like map string
-fn headers_to_json(record: &warc::Record<warc::BufferedBody>) -> String {
+/// Convert WARC headers to a JSON map string
+///
+/// Every value is a JSON string, Content-Length included, so
+/// `json_extract_string` and `->>` behave the same for any key.
+fn headers_to_json(
+    record: &warc_rs::Record<warc_rs::BufferedBody>,
+    warc_protocols: &[String],
+    warc_cipher_suites: &[String],
+    provenance: &[(&str, String)],
+    lowercase_names: bool,
+    names: Option<&HeaderAllowlist>,
+) -> String {
     let mut pairs = Vec::new();
+    let mut push = |name: &str, value: &str| {
+        if header_kept(names, name) {
+            let key = if lowercase_names { name.to_ascii_lowercase() } else { name.to_string() };
+            pairs.push(format!("\"{}\": \"{}\"", key, sanitize_header(value)));
+        }
+    };
 
     // Get standard headers
     if let Some(v) = record.header(WarcHeader::WarcType) {
-        pairs.push(format!("\"WARC-Type\": \"{}\"", sanitize_header(&v)));
+        push("WARC-Type", &v);
     }
     if let Some(v) = record.header(WarcHeader::Date) {
-        pairs.push(format!("\"WARC-Date\": \"{}\"", sanitize_header(&v)));
+        push("WARC-Date", &v);
     }
     if let Some(v) = record.header(WarcHeader::RecordID) {
-        pairs.push(format!("\"WARC-Record-ID\": \"{}\"", sanitize_header(&v)));
+        push("WARC-Record-ID", &v);
     }
     if let Some(v) = record.header(WarcHeader::TargetURI) {
-        pairs.push(format!("\"WARC-Target-URI\": \"{}\"", sanitize_header(&v)));
+        push("WARC-Target-URI", &v);
     }
     if let Some(v) = record.header(WarcHeader::IPAddress) {
-        pairs.push(format!("\"WARC-IP-Address\": \"{}\"", sanitize_header(&v)));
+        push("WARC-IP-Address", &v);
     }
     if let Some(v) = record.header(WarcHeader::ContentType) {
-        pairs.push(format!("\"Content-Type\": \"{}\"", sanitize_header(&v)));
+        push("Content-Type", &v);
     }
-    pairs.push(format!("\"Content-Length\": {}", record.content_length()));
+    push("Content-Length", &record.content_length().to_string());
     if let Some(v) = record.header(WarcHeader::PayloadDigest) {
-        pairs.push(format!("\"WARC-Payload-Digest\": \"{}\"", sanitize_header(&v)));
+        push("WARC-Payload-Digest", &v);
     }
     if let Some(v) = record.header(WarcHeader::BlockDigest) {
-        pairs.push(format!("\"WARC-Block-Digest\": \"{}\"", sanitize_header(&v)));
+        push("WARC-Block-Digest", &v);
     }
     if let Some(v) = record.header(WarcHeader::IdentifiedPayloadType) {
-        pairs.push(format!("\"WARC-Identified-Payload-Type\": \"{}\"", sanitize_header(&v)));
+        push("WARC-Identified-Payload-Type", &v);
+    }
+    if let Some(v) = record.header(WarcHeader::Filename) {
+        push("WARC-Filename", &v);
+    }
+    // WARC 1.1 TLS fields; WARC-Protocol may be repeated (e.g. "h2" and "tls/1.3")
+    if let Some(v) = join_header_values(warc_protocols) {
+        push("WARC-Protocol", &v);
+    }
+    if let Some(v) = join_header_values(warc_cipher_suites) {
+        push("WARC-Cipher-Suite", &v);
+    }
+    for (name, value) in provenance {
+        push(name, value);
     }
 
     format!("{{{}}}", pairs.join(", "))
 }
 
-/// Sanitize a string for C FFI - remove null bytes and any control chars
+/// Non-standard headers naming where a record came from and what wrote it,
+/// kept in `warc_headers` although the `warc` crate doesn't know them
+const PROVENANCE_HEADERS: &[&str] = &["WARC-Source-URI", "software", "X-Harvested-By"];
+
+/// All values of a possibly repeated WARC header, read from the raw header block
+///
+/// The `warc` crate keeps a single value per header name.
+fn warc_header_values(data: &[u8], name: &str) -> Vec<String> {
+    data.split(|&b| b == b'\n')
+        .skip(1)
+        .map(|line| line.trim_ascii())
+        .take_while(|line| !line.is_empty())
+        .filter_map(|line| {
+            let colon = line.iter().position(|&b| b == b':')?;
+            line[..colon]
+                .trim_ascii()
+                .eq_ignore_ascii_case(name.as_bytes())
+                .then(|| sanitize_for_ffi(&String::from_utf8_lossy(line[colon + 1..].trim_ascii())))
+        })
+        .collect()
+}
+
+/// NUL bytes in a header block, which `sanitize_for_ffi` drops from header names and values
+fn nul_bytes(head: &[u8]) -> i32 {
+    head.iter().filter(|&&b| b == 0).count() as i32
+}
+
+/// Join repeated header values HTTP-list style, None when there are none
+fn join_header_values(values: &[String]) -> Option<String> {
+    (!values.is_empty()).then(|| values.join(", "))
+}
+
+/// Sanitize a string for C FFI - remove null bytes
+///
+/// Every other character, including multi-byte UTF-8, is kept, and text after
+/// an embedded NUL is not truncated.
 fn sanitize_for_ffi(s: &str) -> String {
     s.chars()
         .filter(|c| *c != '\0')
         .collect()
 }
 
-/// Parse HTTP response from WARC body
-/// Returns (http_version, http_status, http_headers_json, http_body_bytes)
-fn parse_http_response(body: &[u8]) -> (Option<String>, Option<i32>, Option<String>, Option<Vec<u8>>) {
+/// Parse an HTTP status code, accepting an optional leading `+`
+///
+/// Only codes in the 100-599 range are considered valid.
+fn parse_http_status(s: &str) -> Option<i32> {
+    let digits = s.strip_prefix('+').unwrap_or(s);
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    digits
+        .parse::<i32>()
+        .ok()
+        .filter(|status| (100..=599).contains(status))
+}
+
+/// Status code of an HTTP status line
+///
+/// The code is the token after the version, however malformed the version
+/// (`HTTP 200 OK`) and however many spaces separate them. The reason phrase
+/// is optional (`HTTP/1.1 200`), and whitespace or a stray CR after the code
+/// is ignored.
+fn status_line_code(status_line: &str) -> Option<i32> {
+    status_line.split_ascii_whitespace().nth(1).and_then(parse_http_status)
+}
+
+/// Normalize an HTTP version token to "HTTP/1.0", "HTTP/1.1", "HTTP/2" or "HTTP/3"
+fn normalize_http_protocol(version: &str) -> Option<&'static str> {
+    match version.trim().to_ascii_uppercase().as_str() {
+        "HTTP/1.0" | "HTTP/1" => Some("HTTP/1.0"),
+        "HTTP/1.1" => Some("HTTP/1.1"),
+        "HTTP/2" | "HTTP/2.0" | "H2" | "H2C" => Some("HTTP/2"),
+        "HTTP/3" | "HTTP/3.0" | "H3" => Some("HTTP/3"),
+        _ => None,
+    }
+}
+
+/// Value of a header among the lowercase-named pairs of an HTTP message
+fn header_value<'a>(header_pairs: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    header_pairs.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+}
+
+/// Whether a connection stays open after a message, from its protocol and
+/// Connection header (RFC 9112 section 9.3)
+///
+/// A `close` token always closes. Otherwise HTTP/1.1 keeps the connection
+/// alive by default and HTTP/1.0 only with a `keep-alive` token; HTTP/2 and
+/// HTTP/3 connections are persistent. None when the protocol is unknown.
+fn keep_alive(protocol: Option<&str>, connection: Option<&str>) -> Option<bool> {
+    let has_token = |token: &str| {
+        connection.is_some_and(|value| value.split(',').any(|t| t.trim().eq_ignore_ascii_case(token)))
+    };
+    match protocol? {
+        _ if has_token("close") => Some(false),
+        "HTTP/1.0" => Some(has_token("keep-alive")),
+        _ => Some(true),
+    }
+}
+
+/// Split an HTTP message into its header block and body
+///
+/// None when the message doesn't start with an HTTP status line. The version
+/// only has to start with `HTTP`, so a status line like `HTTP 200 OK` is read.
+fn split_http_message(body: &[u8]) -> Option<(&[u8], &[u8])> {
     // Quick check: if body doesn't start with HTTP, return None
-    if !body.starts_with(b"HTTP/") {
-        return (None, None, None, None);
+    if !body.starts_with(b"HTTP") {
+        return None;
     }
+    Some(split_http_head(body))
+}
 
+/// Split an HTTP message (request or response) at the blank line ending its head
+fn split_http_head(body: &[u8]) -> (&[u8], &[u8]) {
     // Find the header/body separator (\r\n\r\n or \n\n)
-    let separator_pos = body
-        .windows(4)
-        .position(|w| w == b"\r\n\r\n")
-        .map(|p| (p, 4))
-        .or_else(|| body.windows(2).position(|w| w == b"\n\n").map(|p| (p, 2)));
-
-    let (header_bytes, body_bytes) = match separator_pos {
-        Some((pos, sep_len)) => (&body[..pos], Some(&body[pos + sep_len..])),
+    let separators = || {
+        let crlf = body.windows(4).enumerate().filter(|(_, w)| w == b"\r\n\r\n").map(|(p, _)| (p, 4));
+        let lf = body.windows(2).enumerate().filter(|(_, w)| w == b"\n\n").map(|(p, _)| (p, 2));
+        crlf.chain(lf)
+    };
+    // Only a blank line after the start line or a header line ends the head,
+    // so a header value carrying a stray CRLF doesn't split it early
+    let separator_pos = separators()
+        .find(|&(pos, _)| ends_head_line(&body[..pos]))
+        .or_else(|| separators().next());
+
+    match separator_pos {
+        Some((pos, sep_len)) => (&body[..pos], &body[pos + sep_len..]),
         None => {
-            // No separator found
-            return (None, None, None, None);
+            // No separator: the block ends right after the headers (some capture
+            // tools drop the trailing blank line), so the body is empty
+            (body.trim_ascii_end(), &body[body.len()..])
         }
-    };
+    }
+}
 
-    // Parse headers as text (headers are always ASCII-compatible)
-    let header_text = String::from_utf8_lossy(header_bytes);
-    let mut lines = header_text.lines();
+/// Skip interim 1xx responses (`100 Continue`, `103 Early Hints`) and their
+/// headers to reach the final response
+///
+/// `101 Switching Protocols` counts as final since what follows is no longer HTTP/1.
+fn skip_informational_responses(mut message: &[u8]) -> &[u8] {
+    while let Some((head, rest)) = split_http_message(message) {
+        let status_line = head.split(|&b| b == b'\n').next().unwrap_or_default();
+        let status = status_line_code(&String::from_utf8_lossy(status_line));
+        let rest = rest.trim_ascii_start();
+        match status {
+            Some(status) if (100..200).contains(&status) && status != 101 && rest.starts_with(b"HTTP") => {
+                message = rest;
+            }
+            _ => break,
+        }
+    }
+    message
+}
 
-    // Parse HTTP status line (e.g., "HTTP/1.1 200 OK")
-    let (http_version, http_status) = if let Some(status_line) = lines.next() {
-        let parts: Vec<&str> = status_line.splitn(3, ' ').collect();
-        let version = parts.first().map(|s| sanitize_for_ffi(s));
-        let status = parts.get(1).and_then(|s| s.parse::<i32>().ok());
-        (version, status)
-    } else {
-        (None, None)
+/// Whether a message starts with a `Name: value` header line, the name being
+/// an RFC 9110 token
+fn starts_with_header_line(message: &[u8]) -> bool {
+    let Some(colon) = message.iter().position(|&b| b == b':' || b == b'\n') else {
+        return false;
     };
+    let name = &message[..colon];
+    message[colon] == b':'
+        && !name.is_empty()
+        && name.iter().all(|&b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b))
+}
+
+/// Whether the last line of `head` can precede the blank line ending an HTTP head:
+/// the start line, a header line or an obs-fold continuation of one
+fn ends_head_line(head: &[u8]) -> bool {
+    match head.iter().rposition(|&b| b == b'\n') {
+        None => true,
+        Some(newline) => {
+            let line = &head[newline + 1..];
+            line.contains(&b':') || line.starts_with(b" ") || line.starts_with(b"\t")
+        }
+    }
+}
+
+/// Header fields shared by HTTP requests and responses
+#[derive(Default)]
+struct HttpHeaders {
+    json: Option<String>, // JSON map
+    pairs: Vec<(String, String)>, // Lowercase name and joined value of each header
+    content_type: Option<String>,
+    last_modified: Option<i64>,
+    etag: Option<String>,
+    content_length: Option<u64>,
+    date: Option<i64>,
+    retry_after: Option<String>,
+    age: Option<i32>,
+    transfer_encoding: Option<String>, // Comma-separated codings of every occurrence
+    content_encoding: Option<String>,
+    has_pseudo_headers: bool,
+}
 
-    // Parse HTTP headers (sanitize and lowercase keys for consistent access)
-    let mut header_pairs = Vec::new();
+/// Parse HTTP header lines (sanitize and lowercase keys for consistent access)
+///
+/// A repeated header is stored once, its values joined with ", " (RFC 7230
+/// section 3.2.2). Set-Cookie values may contain commas, so they are joined
+/// with "\n" instead.
+fn parse_http_headers<'a>(lines: impl Iterator<Item = &'a str>) -> HttpHeaders {
+    let mut headers = HttpHeaders::default();
+    let mut header_pairs: Vec<(String, String)> = Vec::new();
     for line in lines {
+        // HTTP/2 pseudo-headers (":status", ":path") only appear in h2 captures
+        if line.starts_with(':') {
+            headers.has_pseudo_headers = true;
+            continue;
+        }
         if let Some((key, value)) = line.split_once(':') {
-            let key = sanitize_for_ffi(key.trim()).to_lowercase().replace('"', "\\\"");
-            let value = sanitize_for_ffi(value.trim()).replace('"', "\\\"");
-            header_pairs.push(format!("\"{}\": \"{}\"", key, value));
+            let key = sanitize_for_ffi(key.trim()).to_lowercase();
+            let value = sanitize_for_ffi(value.trim());
+            match key.as_str() {
+                "content-type" => headers.content_type = Some(value.clone()),
+                "last-modified" => headers.last_modified = http_date::parse_http_date(&value),
+                "etag" => headers.etag = Some(value.clone()),
+                "content-length" => headers.content_length = value.parse().ok(),
+                "date" => headers.date = http_date::parse_http_date(&value),
+                "retry-after" => headers.retry_after = Some(value.clone()),
+                "age" => headers.age = delta_seconds(&value),
+                "transfer-encoding" => append_list_value(&mut headers.transfer_encoding, &value),
+                "content-encoding" => append_list_value(&mut headers.content_encoding, &value),
+                _ => {}
+            }
+            match header_pairs.iter_mut().find(|(existing, _)| *existing == key) {
+                Some((_, joined)) => {
+                    joined.push_str(if key == "set-cookie" { "\n" } else { ", " });
+                    joined.push_str(&value);
+                }
+                None => header_pairs.push((key, value)),
+            }
         }
     }
 
-    let http_headers = if header_pairs.is_empty() {
-        None
-    } else {
-        Some(format!("{{{}}}", header_pairs.join(", ")))
-    };
+    headers.json = http_headers_json(&header_pairs, None);
+    headers.pairs = header_pairs;
+    headers
+}
 
-    // Always return body as BLOB (handles binary content like PDFs)
-    let http_body = body_bytes.map(|b| b.to_vec());
+/// JSON map of HTTP headers, None when the message has none
+fn http_headers_json(header_pairs: &[(String, String)], names: Option<&HeaderAllowlist>) -> Option<String> {
+    if header_pairs.is_empty() {
+        return None;
+    }
+    let pairs: Vec<String> = header_pairs
+        .iter()
+        .filter(|(key, _)| header_kept(names, key))
+        .map(|(key, value)| format!("\"{}\": \"{}\"", sanitize_header(key), sanitize_header(value)))
+        .collect();
+    Some(format!("{{{}}}", pairs.join(", ")))
+}
 
-    (http_version, http_status, http_headers, http_body)
+/// A delta-seconds header value (`Age`, `Retry-After`), capped at the
+/// INTEGER maximum as RFC 9111 section 1.2.2 allows
+fn delta_seconds(value: &str) -> Option<i32> {
+    let value = value.trim();
+    if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some(value.parse::<i32>().unwrap_or(i32::MAX))
 }
 
-/// Parse a WARC record from decompressed bytes using the warc library
-fn parse_warc_record(data: &[u8]) -> Option<ParsedRecord> {
-    let reader = BufReader::new(data);
-    let warc_reader = WarcReader::new(reader);
-
-    // Get the first record
-    let record = match warc_reader.iter_records().next() {
-        Some(Ok(r)) => r,
-        Some(Err(_)) => return None,
-        None => return None,
-    };
+/// Seconds a Retry-After value asks to wait
+///
+/// Either delay-seconds, or an HTTP-date counted from the response's `Date`
+/// (`sent`), as RFC 9110 section 10.2.3 specifies; a date already past is 0.
+fn retry_after_seconds(value: &str, sent: Option<i64>) -> Option<i32> {
+    if let Some(seconds) = delta_seconds(value) {
+        return Some(seconds);
+    }
+    let retry_at = http_date::parse_http_date(value)?;
+    let seconds = (retry_at - sent?).div_euclid(1_000_000).max(0);
+    Some(seconds.min(i32::MAX as i64) as i32)
+}
 
-    // Get WARC version from the record (sanitize for C FFI)
-    let warc_version = sanitize_for_ffi(&record.warc_version().to_string());
+/// Add a value to a comma-separated header list
+fn append_list_value(list: &mut Option<String>, value: &str) {
+    match list {
+        Some(list) => {
+            list.push_str(", ");
+            list.push_str(value);
+        }
+        None => *list = Some(value.to_string()),
+    }
+}
 
-    // Convert headers to JSON (sanitize for C FFI)
-    let warc_headers = sanitize_for_ffi(&headers_to_json(&record));
+/// Parse HTTP response from WARC body
+///
+/// Blank lines before the status line, which some proxies prepend, are skipped.
+/// A message with header lines but no status line (an HTTP/1.0 capture that
+/// lost it, a raw header dump) keeps its headers and body, with no version,
+/// status or status line.
+fn parse_http_response(body: &[u8]) -> HttpResponse<'_> {
+    let start = body.iter().position(|&b| b != b'\r' && b != b'\n').unwrap_or(body.len());
+    let message = skip_informational_responses(&body[start..]);
+    let (header_bytes, body_bytes, has_status_line) = match split_http_message(message) {
+        Some((header_bytes, body_bytes)) => (header_bytes, body_bytes, true),
+        None if starts_with_header_line(message) => {
+            let (header_bytes, body_bytes) = split_http_head(message);
+            (header_bytes, body_bytes, false)
+        }
+        None => return HttpResponse::default(),
+    };
 
-    // Check if this is a response record
-    let warc_type = record.header(WarcHeader::WarcType)?;
+    // Parse headers as text (headers are always ASCII-compatible)
+    let header_text = String::from_utf8_lossy(header_bytes);
+    let mut lines = header_text.lines();
 
-    if warc_type == "response" {
-        let body = record.body();
-        let (http_version, http_status, http_headers, http_body) = parse_http_response(body);
-
-        Some(ParsedRecord {
-            warc_version,
-            warc_headers,
-            http_version,
-            http_status,
-            http_headers,
-            http_body,
-        })
+    // Parse HTTP status line (e.g., "HTTP/1.1 200 OK")
+    let status_line = if has_status_line { lines.next() } else { None };
+    let (version, status, status_line) = if let Some(status_line) = status_line {
+        // The version as written, even when nonstandard (`HTTP`, `HTTP/1.1.`)
+        let version = status_line.split_ascii_whitespace().next().map(sanitize_for_ffi);
+        let status = status_line_code(status_line);
+        (version, status, Some(sanitize_for_ffi(status_line)))
     } else {
-        // Non-response records don't have HTTP fields
-        Some(ParsedRecord {
-            warc_version,
-            warc_headers,
-            http_version: None,
-            http_status: None,
-            http_headers: None,
-            http_body: None,
-        })
+        (None, None, None)
+    };
+
+    let headers = parse_http_headers(lines);
+    let protocol = version
+        .as_deref()
+        .and_then(normalize_http_protocol)
+        .or(if headers.has_pseudo_headers { Some("HTTP/2") } else { None });
+
+    // Body is kept as raw bytes (handles binary content like PDFs)
+    HttpResponse {
+        version,
+        status,
+        status_line,
+        protocol,
+        headers: headers.json,
+        header_pairs: headers.pairs,
+        content_type: headers.content_type,
+        last_modified: headers.last_modified,
+        etag: headers.etag,
+        content_length: headers.content_length,
+        date: headers.date,
+        retry_after: headers.retry_after,
+        age: headers.age,
+        transfer_encoding: headers.transfer_encoding,
+        content_encoding: headers.content_encoding,
+        body: Some(body_bytes),
+        // The body is always a suffix of the block, past any interim responses
+        payload_offset: Some(body.len() - body_bytes.len()),
     }
 }
 
-/// DuckDB scalar function to parse WARC records from gzip-compressed data
-///
-/// Returns a struct with:
-/// - warc_version: VARCHAR
-/// - warc_headers: VARCHAR (JSON map)
-/// - http_version: VARCHAR
-/// - http_status: INTEGER
-/// - http_headers: VARCHAR (JSON map)
-/// - http_body: VARCHAR
-struct ParseWarc;
-
-impl VScalar for ParseWarc {
-    type State = ();
+/// Parsed HTTP request; the body borrows from the WARC record block
+struct HttpRequest<'a> {
+    method: String,
+    version: String,
+    protocol: Option<&'static str>,
+    headers: Option<String>, // JSON map
+    header_pairs: Vec<(String, String)>,
+    content_type: Option<String>,
+    content_length: Option<u64>,
+    body: &'a [u8], // POST/PUT payload, empty for most GETs
+}
 
-    unsafe fn invoke(
-        _state: &Self::State,
-        input: &mut DataChunkHandle,
-        output: &mut dyn WritableVector,
-    ) -> std::result::Result<(), Box<dyn Error>> {
-        let size = input.len();
-        let _input_vector = input.flat_vector(0);
+/// Parse an HTTP request (e.g. "POST /form HTTP/1.1") from a request record block
+fn parse_http_request(body: &[u8]) -> Option<HttpRequest<'_>> {
+    let (header_bytes, body_bytes) = split_http_head(body);
+    let header_text = String::from_utf8_lossy(header_bytes);
+    let mut lines = header_text.lines();
 
-        let output_struct = output.struct_vector();
-        let mut warc_version_vec = output_struct.child(0, size);
-        let mut warc_headers_vec = output_struct.child(1, size);
-        let mut http_version_vec = output_struct.child(2, size);
-        let mut http_status_vec = output_struct.child(3, size);
-        let mut http_headers_vec = output_struct.child(4, size);
-        let mut http_body_vec = output_struct.child(5, size);
+    let mut request_line = lines.next()?.split(' ');
+    let method = request_line.next()?;
+    let _target = request_line.next()?;
+    let version = request_line.next()?;
+    if method.is_empty() || !method.bytes().all(|b| b.is_ascii_uppercase()) || !version.starts_with("HTTP/") {
+        return None;
+    }
 
-        let input_vector = _input_vector;
+    let headers = parse_http_headers(lines);
+    Some(HttpRequest {
+        method: method.to_string(),
+        version: sanitize_for_ffi(version),
+        protocol: normalize_http_protocol(version),
+        headers: headers.json,
+        header_pairs: headers.pairs,
+        content_type: headers.content_type,
+        content_length: headers.content_length,
+        body: body_bytes,
+    })
+}
 
-        // Get input as blob slice
-        let blob_slice = input_vector.as_slice_with_len::<duckdb_string_t>(size);
+thread_local! {
+    /// Decompression buffer reused by every row decoded on a thread
+    static DECOMPRESS_BUFFER: Cell<Vec<u8>> = const { Cell::new(Vec::new()) };
+}
 
-        for i in 0..size {
-            if input_vector.row_is_null(i as u64) {
-                warc_version_vec.set_null(i);
-                warc_headers_vec.set_null(i);
-                http_version_vec.set_null(i);
-                http_status_vec.set_null(i);
-                http_headers_vec.set_null(i);
-                http_body_vec.set_null(i);
-                continue;
-            }
+/// Larger buffers are freed after use instead of being kept for the next row
+const MAX_RETAINED_BUFFER: usize = 16 * 1024 * 1024;
 
-            // Get data as blob
-            let mut blob_data = blob_slice[i];
-            let mut blob = DuckString::new(&mut blob_data);
-            let raw_data = blob.as_bytes();
-
-            // Try to decompress gzip data, fall back to raw data if it fails
-            let data_to_parse = {
-                let mut decoder = GzDecoder::new(raw_data);
-                let mut decompressed = Vec::new();
-                if decoder.read_to_end(&mut decompressed).is_ok() && !decompressed.is_empty() {
-                    decompressed
-                } else {
-                    // Not gzip compressed, use raw data
-                    raw_data.to_vec()
-                }
-            };
-
-            // Parse the WARC record
-            match parse_warc_record(&data_to_parse) {
-                Some(record) => {
-                    warc_version_vec.insert(i, record.warc_version.as_str());
-                    warc_headers_vec.insert(i, record.warc_headers.as_str());
-
-                    match &record.http_version {
-                        Some(v) => http_version_vec.insert(i, v.as_str()),
-                        None => http_version_vec.set_null(i),
-                    }
-
-                    match record.http_status {
-                        Some(v) => {
-                            let slice = http_status_vec.as_mut_slice::<i32>();
-                            slice[i] = v;
-                        }
-                        None => http_status_vec.set_null(i),
-                    }
-
-                    match &record.http_headers {
-                        Some(v) => http_headers_vec.insert(i, v.as_str()),
-                        None => http_headers_vec.set_null(i),
-                    }
-
-                    match &record.http_body {
-                        Some(v) => {
-                            // Use explicit &[u8] type to ensure BLOB insertion (not string)
-                            Inserter::<&[u8]>::insert(&http_body_vec, i, v.as_slice());
-                        }
-                        None => http_body_vec.set_null(i),
-                    }
-                }
-                None => {
-                    warc_version_vec.set_null(i);
-                    warc_headers_vec.set_null(i);
-                    http_version_vec.set_null(i);
-                    http_status_vec.set_null(i);
-                    http_headers_vec.set_null(i);
-                    http_body_vec.set_null(i);
-                }
-            }
-        }
+/// Call `f` with the decompressed bytes of a row
+///
+/// Input without the gzip magic bytes is passed as-is, skipping the decoder,
+/// as is input that doesn't decode. gzip input is decoded into a buffer kept
+/// per thread, so a scan reuses one allocation instead of making one per
+/// row. A nested call gets a buffer of its own.
+fn with_decompressed<T>(raw_data: &[u8], f: impl FnOnce(&[u8]) -> T) -> T {
+    if !raw_data.starts_with(&[0x1f, 0x8b]) {
+        return f(raw_data);
+    }
 
-        Ok(())
+    let mut buffer = DECOMPRESS_BUFFER.take();
+    buffer.clear();
+    let decoded = GzDecoder::new(raw_data).read_to_end(&mut buffer).is_ok() && !buffer.is_empty();
+    // Not valid gzip after all, use raw data
+    let result = f(if decoded { &buffer } else { raw_data });
+    if buffer.capacity() <= MAX_RETAINED_BUFFER {
+        DECOMPRESS_BUFFER.set(buffer);
     }
+    result
+}
 
-    fn signatures() -> Vec<ScalarFunctionSignature> {
-        // Helper to create struct return type (needed twice since LogicalTypeHandle doesn't impl Clone)
-        let make_return_type = || {
-            LogicalTypeHandle::struct_type(&[
-                ("warc_version", LogicalTypeHandle::from(LogicalTypeId::Varchar)),
-                ("warc_headers", LogicalTypeHandle::from(LogicalTypeId::Varchar)),
-                ("http_version", LogicalTypeHandle::from(LogicalTypeId::Varchar)),
-                ("http_status", LogicalTypeHandle::from(LogicalTypeId::Integer)),
-                ("http_headers", LogicalTypeHandle::from(LogicalTypeId::Varchar)),
-                ("http_body", LogicalTypeHandle::from(LogicalTypeId::Blob)),
-            ])
-        };
+/// The first `length` bytes of a body as text, with invalid UTF-8 replaced
+fn body_preview(body: &[u8], length: usize) -> String {
+    body_text(&body[..length.min(body.len())])
+}
 
-        // Support both BLOB and VARCHAR inputs
-        vec![
-            ScalarFunctionSignature::exact(
-                vec![LogicalTypeHandle::from(LogicalTypeId::Blob)],
-                make_return_type(),
-            ),
-            ScalarFunctionSignature::exact(
-                vec![LogicalTypeHandle::from(LogicalTypeId::Varchar)],
-                make_return_type(),
-            ),
-        ]
-    }
+/// A body as text, with invalid UTF-8 replaced
+fn body_text(body: &[u8]) -> String {
+    sanitize_for_ffi(&String::from_utf8_lossy(body))
 }
 
-#[duckdb_entrypoint_c_api()]
-pub unsafe fn extension_entrypoint(con: Connection) -> Result<(), Box<dyn Error>> {
-    con.register_scalar_function::<ParseWarc>("parse_warc")?;
-    Ok(())
+/// Whether an HTTP body holds fewer bytes than its Content-Length declares
+fn shorter_than_declared(body: Option<&[u8]>, content_length: Option<u64>) -> bool {
+    matches!((body, content_length), (Some(body), Some(length)) if (body.len() as u64) < length)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::fs;
+/// Non-fatal issues with a record's WARC headers
+///
+/// A WARC-Truncated header, and a WARC-Block-Digest that doesn't match the
+/// block. The digest is only checked when `check_digest` is set, as hashing
+/// every block isn't free.
+fn warc_warnings(record: &warc_rs::Record<warc_rs::BufferedBody>, check_digest: bool) -> Vec<String> {
+    let mut warnings = Vec::new();
+    if let Some(reason) = record.header(WarcHeader::Truncated) {
+        warnings.push(format!("truncated by the crawler (WARC-Truncated: {})", sanitize_for_ffi(&reason)));
+    }
+    if let Some(declared) = record.header(WarcHeader::BlockDigest).filter(|_| check_digest) {
+        if digest::digest_matches(&declared, record.body()) == Some(false) {
+            warnings.push(format!("WARC-Block-Digest mismatch ({})", sanitize_for_ffi(&declared)));
+        }
+    }
+    warnings
+}
 
-    fn load_example_warc() -> Vec<u8> {
-        fs::read("test-data/example.warc").expect("Failed to read test-data/example.warc")
+/// Non-fatal issues with an HTTP message: a body whose size differs from its
+/// Content-Length, and a Content-Type charset no encoding goes by
+///
+/// A chunked body is longer than its Content-Length would say, so only a
+/// short one is reported then.
+fn http_warnings(
+    body: &[u8],
+    content_length: Option<u64>,
+    transfer_encoding: Option<&str>,
+    content_type: Option<&str>,
+) -> Vec<String> {
+    let mut warnings = Vec::new();
+    match content_length {
+        Some(length) if (body.len() as u64) < length => {
+            warnings.push(format!("HTTP body shorter than its Content-Length ({} of {} bytes)", body.len(), length));
+        }
+        Some(length) if (body.len() as u64) > length && transfer_encoding.is_none() => {
+            warnings.push(format!("HTTP body longer than its Content-Length ({} of {} bytes)", body.len(), length));
+        }
+        _ => {}
+    }
+    if let Some(label) = content_type.and_then(warc_fields::charset) {
+        if encoding_rs::Encoding::for_label(label.as_bytes()).is_none() {
+            warnings.push(format!("unknown charset \"{}\"", sanitize_for_ffi(&label)));
+        }
     }
+    warnings
+}
 
-    #[test]
-    fn test_parse_warc_record_basic() {
-        let data = load_example_warc();
-        let result = parse_warc_record(&data);
-        assert!(result.is_some());
+/// Bodies at most this long may be flagged as soft 404s by their text alone
+const SOFT_404_MAX_BODY: usize = 4096;
 
-        let record = result.unwrap();
-        assert_eq!(record.warc_version, "1.0");
-        assert_eq!(record.http_status, Some(200));
-        assert_eq!(record.http_version, Some("HTTP/1.1".to_string()));
-        assert!(record.http_body.is_some());
-        let body = String::from_utf8_lossy(record.http_body.as_ref().unwrap());
-        assert!(body.contains("Example Domain"));
+/// Heuristic: whether a 2xx text response is really a "not found" page
+///
+/// The decoded `body` is flagged when its HTML title mentions "not found" or
+/// "404", or when it is short and its text does. Non-2xx responses and
+/// non-text content types are never flagged.
+fn likely_soft_404(response: &HttpResponse, body: &[u8]) -> bool {
+    let (Some(status), Some(content_type)) = (response.status, &response.content_type) else {
+        return false;
+    };
+    if !(200..300).contains(&status) || !transcode::is_text_content_type(content_type) {
+        return false;
     }
 
-    #[test]
-    fn test_parse_warc_headers_json() {
-        let data = load_example_warc();
-        let result = parse_warc_record(&data).unwrap();
+    let mentions_not_found = |text: &str| {
+        let text = text.to_lowercase();
+        text.contains("not found") || text.contains("404")
+    };
+    html::html_title(body).is_some_and(|title| mentions_not_found(&title))
+        || (body.len() <= SOFT_404_MAX_BODY && mentions_not_found(&String::from_utf8_lossy(body)))
+}
 
-        // Check WARC headers contain expected fields
-        assert!(result.warc_headers.contains("\"WARC-Type\": \"response\""));
-        assert!(result.warc_headers.contains("\"WARC-Target-URI\": \"http://www.example.com/\""));
-        assert!(result.warc_headers.contains("\"WARC-IP-Address\": \"2.18.67.69\""));
+/// URL an HTML response redirects to with `<meta http-equiv="refresh">` in
+/// its decoded `body`, resolved against the record's WARC-Target-URI
+fn meta_refresh_url(response: &HttpResponse, body: &[u8], target_uri: Option<&str>) -> Option<String> {
+    if !response.content_type.as_deref()?.to_ascii_lowercase().contains("html") {
+        return None;
     }
+    html::resolve_href(target_uri, &html::meta_refresh_href(body)?)
+}
 
-    #[test]
-    fn test_parse_http_headers_lowercase() {
-        let data = load_example_warc();
-        let result = parse_warc_record(&data).unwrap();
-        let http_headers = result.http_headers.unwrap();
+/// Whether a response's Content-Type, its WARC-Identified-Payload-Type and
+/// the type sniffed from its decoded `body` disagree (see
+/// [`sniff::content_type_mismatch`])
+///
+/// Without a body, only the declared and identified types are compared.
+fn response_type_mismatch(response: &HttpResponse, identified: Option<&str>, body: Option<&[u8]>) -> bool {
+    let sniffed = body.and_then(sniff::sniff_content_type);
+    sniff::content_type_mismatch(response.content_type.as_deref(), identified, sniffed)
+}
 
-        // HTTP header keys should be lowercase
-        assert!(http_headers.contains("\"content-type\": \"text/html\""));
-        assert!(http_headers.contains("\"content-length\": \"513\""));
+/// Lowercase hex of some bytes
+fn to_hex(bytes: &[u8]) -> String {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    let mut hex = String::with_capacity(bytes.len() * 2);
+    for &b in bytes {
+        hex.push(DIGITS[(b >> 4) as usize] as char);
+        hex.push(DIGITS[(b & 0xf) as usize] as char);
     }
+    hex
+}
 
-    #[test]
-    fn test_parse_http_response_basic() {
-        let http_data = b"HTTP/1.1 404 Not Found\r\nContent-Type: text/plain\r\n\r\nNot found";
-        let (version, status, headers, body) = parse_http_response(http_data);
+/// Lowercase hex SHA-256 of a body, whatever digest the record declares
+fn body_sha256(body: &[u8]) -> String {
+    to_hex(&Sha256::digest(body))
+}
 
-        assert_eq!(version, Some("HTTP/1.1".to_string()));
-        assert_eq!(status, Some(404));
-        assert!(headers.unwrap().contains("\"content-type\": \"text/plain\""));
-        assert_eq!(body, Some(b"Not found".to_vec()));
+/// Decompress and parse a record, noting which decoder read it
+///
+/// gzip is the only compression recognized; anything else is parsed as-is
+/// and reported as "none", or rejected with `require_compressed`. gzip
+/// input is decompressed as the record is read rather than into a buffer of
+/// its own, so a large record is held in memory once.
+fn parse_raw_warc_record(raw_data: &[u8], options: &ParseOptions) -> Result<ParsedRecord, WarcParseError> {
+    if raw_data.is_empty() {
+        return Err(WarcParseError::EmptyInput);
+    }
+    if !raw_data.starts_with(&[0x1f, 0x8b]) {
+        if options.require_compressed {
+            return Err(WarcParseError::NotCompressed);
+        }
+        let mut record = parse_warc_record(raw_data, options)?;
+        record.source_compression = Some("none");
+        return Ok(record);
     }
 
-    #[test]
-    fn test_parse_http_response_binary() {
-        // Binary content (PNG header) should be preserved in BLOB
-        let http_data = b"HTTP/1.1 200 OK\r\nContent-Type: image/png\r\n\r\n\x89PNG\r\n\x1a\n";
-        let (version, status, headers, body) = parse_http_response(http_data);
-
-        assert_eq!(version, Some("HTTP/1.1".to_string()));
-        assert_eq!(status, Some(200));
-        assert!(headers.is_some());
-        // Binary body is now preserved (not skipped)
-        assert_eq!(body, Some(b"\x89PNG\r\n\x1a\n".to_vec()));
+    let mut decoder = BufReader::new(CountingReader::new(GzDecoder::new(raw_data)));
+    let record = parse_warc_stream(&mut decoder, options);
+    // Read to the end of the member so its checksum is verified, as when it
+    // was decompressed whole
+    let drained = io::copy(&mut decoder, &mut io::sink());
+    let decoder = decoder.into_inner();
+    if drained.is_err() || decoder.failed || decoder.bytes == 0 {
+        return Err(WarcParseError::DecompressFailed);
     }
+    let mut record = record?;
+    record.source_compression = Some("gzip");
+    Ok(record)
+}
 
-    #[test]
-    fn test_parse_http_response_pdf() {
-        // PDF content should be preserved in BLOB
-        let http_data = b"HTTP/1.1 200 OK\r\nContent-Type: application/pdf\r\n\r\n%PDF-1.4\n%\xe2\xe3\xcf\xd3";
-        let (version, status, headers, body) = parse_http_response(http_data);
+/// Reader noting how many bytes it returned and whether a read failed
+struct CountingReader<R> {
+    inner: R,
+    bytes: u64,
+    failed: bool,
+}
 
-        assert_eq!(version, Some("HTTP/1.1".to_string()));
-        assert_eq!(status, Some(200));
-        assert!(headers.unwrap().contains("\"content-type\": \"application/pdf\""));
-        // PDF body preserved with binary data
-        assert!(body.is_some());
-        assert!(body.unwrap().starts_with(b"%PDF-1.4"));
+impl<R> CountingReader<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            bytes: 0,
+            failed: false,
+        }
     }
+}
 
-    #[test]
-    fn test_parse_http_response_not_http() {
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf);
+        match &read {
+            Ok(n) => self.bytes += *n as u64,
+            Err(_) => self.failed = true,
+        }
+        read
+    }
+}
+
+/// Decode base64 text (standard alphabet), ignoring line breaks and other
+/// whitespace; the `=` padding is optional
+fn decode_base64(text: &[u8]) -> Result<Vec<u8>, WarcParseError> {
+    use base64::engine::{general_purpose, DecodePaddingMode, Engine, GeneralPurpose};
+    const ENGINE: GeneralPurpose = GeneralPurpose::new(
+        &base64::alphabet::STANDARD,
+        general_purpose::PAD.with_decode_padding_mode(DecodePaddingMode::Indifferent),
+    );
+
+    let text: Vec<u8> = text.iter().copied().filter(|b| !b.is_ascii_whitespace()).collect();
+    ENGINE.decode(text).map_err(|_| WarcParseError::InvalidBase64)
+}
+
+/// Check that a chunk of `size` rows fits a struct output vector
+///
+/// Struct children are written through slices of `capacity` rows with no
+/// bounds checks on the DuckDB side, so a chunk larger than the vector or a
+/// struct without the expected number of fields is refused rather than
+/// written out of bounds.
+fn check_struct_output(size: usize, capacity: usize, children: usize, fields: usize) -> Result<(), String> {
+    if size > capacity {
+        return Err(format!("chunk of {} rows exceeds the output vector capacity of {}", size, capacity));
+    }
+    if children != fields {
+        return Err(format!("output struct has {} fields, expected {}", children, fields));
+    }
+    Ok(())
+}
+
+/// Raw bytes of each row of a BLOB/VARCHAR column (None for NULL rows)
+///
+/// The slices borrow the vector's own memory, so they stay valid for the
+/// duration of the invocation and can be handed to other threads.
+///
+/// Rows are read densely by index. This is correct after a filter or join:
+/// DuckDB's C API flattens the input chunk before calling a scalar function,
+/// so any selection or dictionary vector has already been applied.
+unsafe fn blob_rows(input: &DataChunkHandle, column: usize) -> Vec<Option<&[u8]>> {
+    let input_vector = input.flat_vector(column);
+    let strings = input_vector.as_mut_ptr::<duckdb_string_t>();
+
+    (0..input.len())
+        .map(|i| {
+            if input_vector.row_is_null(i as u64) {
+                return None;
+            }
+            let string = strings.add(i);
+            let len = ffi::duckdb_string_t_length(*string) as usize;
+            let data = ffi::duckdb_string_t_data(string) as *const u8;
+            Some(std::slice::from_raw_parts(data, len))
+        })
+        .collect()
+}
+
+/// Call `f` with the row index and raw bytes of each row of a BLOB/VARCHAR column
+/// (None for NULL rows)
+unsafe fn for_each_blob(input: &DataChunkHandle, column: usize, mut f: impl FnMut(usize, Option<&[u8]>)) {
+    for (i, raw_data) in blob_rows(input, column).into_iter().enumerate() {
+        f(i, raw_data);
+    }
+}
+
+/// Repair line endings in the WARC header block written by buggy encoders
+///
+/// Windows text-mode writers turn CRLF into CR CR LF, others mix in bare LFs
+/// or write LF only. Every header line (and the terminating blank line) is
+/// rewritten to end in CRLF; the record body is left untouched. Returns None
+/// when the block is already well-formed or has no terminating blank line.
+fn repair_warc_header_block(data: &[u8]) -> Option<Vec<u8>> {
+    if !data.starts_with(b"WARC/") {
+        return None;
+    }
+
+    // Common case: every header line already ends in a single CRLF
+    let mut pos = 0;
+    loop {
+        let newline = pos + data[pos..].iter().position(|&b| b == b'\n')?;
+        let line = &data[pos..newline];
+        if !line.ends_with(b"\r") || line.ends_with(b"\r\r") {
+            break;
+        }
+        if line.len() == 1 {
+            return None;
+        }
+        pos = newline + 1;
+    }
+
+    let mut repaired = Vec::with_capacity(data.len() + 64);
+    let mut pos = 0;
+    loop {
+        let newline = pos + data[pos..].iter().position(|&b| b == b'\n')?;
+        let mut line = &data[pos..newline];
+        while let [rest @ .., b'\r'] = line {
+            line = rest;
+        }
+        repaired.extend_from_slice(line);
+        repaired.extend_from_slice(b"\r\n");
+        pos = newline + 1;
+        if line.is_empty() {
+            break;
+        }
+    }
+    repaired.extend_from_slice(&data[pos..]);
+
+    (repaired != data).then_some(repaired)
+}
+
+/// Read the first WARC record, with a repaired header block (line endings,
+/// WARC-Date format) if needed
+fn read_first_record_lenient(data: &[u8]) -> Result<warc_rs::Record<warc_rs::BufferedBody>, WarcParseError> {
+    read_first_record_stream(data).map(|(_, record)| record)
+}
+
+/// Read the first WARC record from a stream of decompressed bytes, along with
+/// its header block as read
+///
+/// Only the header block is buffered ahead, to be repaired (line endings,
+/// WARC-Date format) when needed; the block then streams from `reader` into
+/// the record's body.
+fn read_first_record_stream(
+    mut reader: impl BufRead,
+) -> Result<(Vec<u8>, warc_rs::Record<warc_rs::BufferedBody>), WarcParseError> {
+    let io_error = |e: io::Error| WarcParseError::WarcError(e.to_string());
+    let mut head = Vec::new();
+    reader.read_until(b'\n', &mut head).map_err(io_error)?;
+    if !head.starts_with(b"WARC/") {
+        return Err(WarcParseError::NoRecords);
+    }
+    // Up to and including the blank line, however its line ending is mangled
+    loop {
+        let start = head.len();
+        if reader.read_until(b'\n', &mut head).map_err(io_error)? == 0 {
+            break;
+        }
+        if head[start..].iter().all(|&b| b == b'\r' || b == b'\n') {
+            break;
+        }
+    }
+
+    let repaired = repair_warc_header_block(&head);
+    let normalized = normalize_warc_date(repaired.as_deref().unwrap_or(&head));
+    let fixed = normalized.or(repaired);
+    let warc_reader = WarcReader::new(fixed.as_deref().unwrap_or(&head).chain(reader));
+    match warc_reader.iter_records().next() {
+        Some(Ok(record)) => Ok((head, record)),
+        Some(Err(e)) => Err(WarcParseError::WarcError(e.to_string())),
+        None => Err(WarcParseError::NoRecords),
+    }
+}
+
+/// Rewrite a WARC-Date the `warc` crate rejects (it only reads RFC 3339) into
+/// the standard form, when `http_date::parse_record_date` understands it
+///
+/// Returns None when there is no WARC-Date to rewrite.
+fn normalize_warc_date(data: &[u8]) -> Option<Vec<u8>> {
+    let mut pos = 0;
+    loop {
+        let newline = pos + data[pos..].iter().position(|&b| b == b'\n')?;
+        let line = &data[pos..newline + 1];
+        if line.trim_ascii().is_empty() {
+            return None;
+        }
+        if let Some(colon) = line.iter().position(|&b| b == b':') {
+            if line[..colon].trim_ascii().eq_ignore_ascii_case(b"WARC-Date") {
+                let value = String::from_utf8_lossy(line[colon + 1..].trim_ascii());
+                let normalized = http_date::format_warc_date(http_date::parse_record_date(&value)?);
+                if normalized == value {
+                    return None;
+                }
+                let line_end = &line[line.trim_ascii_end().len()..];
+                let rest = &data[newline + 1..];
+                return Some([&data[..pos], b"WARC-Date: ", normalized.as_bytes(), line_end, rest].concat());
+            }
+        }
+        pos = newline + 1;
+    }
+}
+
+/// Extract the UUID from a `<urn:uuid:...>` WARC-Record-ID
+fn record_id_uuid(record_id: &str) -> Option<u128> {
+    let id = record_id.trim().trim_start_matches('<').trim_end_matches('>');
+    let prefix = id.get(..9)?;
+    if !prefix.eq_ignore_ascii_case("urn:uuid:") {
+        return None;
+    }
+    Uuid::try_parse(&id[9..]).ok().map(|uuid| uuid.as_u128())
+}
+
+/// Major and minor numbers of a WARC version such as "1.0"; either is None
+/// when missing or not a number
+fn warc_version_numbers(version: &str) -> (Option<i16>, Option<i16>) {
+    let (major, minor) = version.split_once('.').unwrap_or((version, ""));
+    (major.trim().parse().ok(), minor.trim().parse().ok())
+}
+
+/// Parse a WARC record from decompressed bytes using the warc library
+///
+/// A record that is only partially parsed (unknown WARC version, block that
+/// isn't HTTP) is returned with its error in `parse_error`.
+fn parse_warc_record(data: &[u8], options: &ParseOptions) -> Result<ParsedRecord, WarcParseError> {
+    parse_warc_stream(data, options)
+}
+
+/// Parse the first WARC record of a stream of decompressed bytes
+///
+/// Fields read from the raw header lines use the header block alone, so the
+/// record's block is only ever held once, in the record.
+fn parse_warc_stream(reader: impl BufRead, options: &ParseOptions) -> Result<ParsedRecord, WarcParseError> {
+    let (data, record) = read_first_record_stream(reader)?;
+    let data = data.as_slice();
+
+    // Fields every record type shares (sanitized for C FFI)
+    let warc_protocols = warc_header_values(data, "WARC-Protocol");
+    let warc_cipher_suites = warc_header_values(data, "WARC-Cipher-Suite");
+    // May be repeated, one record ID each
+    let warc_concurrent_to = warc_header_values(data, "WARC-Concurrent-To");
+    let provenance: Vec<(&str, String)> = PROVENANCE_HEADERS
+        .iter()
+        .filter_map(|&name| join_header_values(&warc_header_values(data, name)).map(|value| (name, value)))
+        .collect();
+    let (warc_version_major, warc_version_minor) = warc_version_numbers(record.warc_version());
+    let base = ParsedRecord {
+        warc_version: Some(sanitize_for_ffi(record.warc_version())),
+        warc_version_major,
+        warc_version_minor,
+        warc_headers: Some(sanitize_for_ffi(&headers_to_json(
+            &record,
+            &warc_protocols,
+            &warc_cipher_suites,
+            &provenance,
+            options.lowercase_header_names,
+            options.header_names.as_ref(),
+        ))),
+        warc_protocol: join_header_values(&warc_protocols),
+        warc_cipher_suite: join_header_values(&warc_cipher_suites),
+        likely_truncated: record.header(WarcHeader::Truncated).is_some(),
+        warc_filename: record.header(WarcHeader::Filename).map(|v| sanitize_for_ffi(&v)),
+        warc_date: record.header(WarcHeader::Date).and_then(|v| http_date::parse_record_date(&v)),
+        sanitized_bytes_removed: Some(nul_bytes(split_http_head(data).0)),
+        warc_concurrent_to,
+        warc_source_uri: warc_header_values(data, "WARC-Source-URI").into_iter().next(),
+        warc_header_bytes: Some(data.len().try_into().unwrap_or(i32::MAX)),
+        identified_payload_type: record.header(WarcHeader::IdentifiedPayloadType).map(|v| sanitize_for_ffi(&v)),
+        warnings: warc_warnings(&record, options.hash_body),
+        ..Default::default()
+    };
+
+    // A future version may lay out the block differently; report it instead
+    // of mis-parsing the body
+    if !SUPPORTED_WARC_VERSIONS.contains(&record.warc_version()) {
+        return Ok(base.with_error(WarcParseError::UnsupportedVersion(record.warc_version().to_string())));
+    }
+
+    // Check if this is a response record
+    let Some(warc_type) = record.header(WarcHeader::WarcType) else {
+        return Err(WarcParseError::WarcError("missing WARC-Type".to_string()));
+    };
+
+    if warc_type == "response" {
+        let response = parse_http_response(record.body());
+        if response.body.is_none() {
+            return Ok(base.with_error(WarcParseError::HttpParseError("no HTTP status line")));
+        }
+
+        // Only copy the body when its content type passes the filter
+        let skipped = options
+            .content_types
+            .as_ref()
+            .is_some_and(|filter| !filter.allows(response.content_type.as_deref()));
+        // The body without its HTTP codings, decoded once for every field
+        // read from its content; never for a skipped body
+        let coded =
+            content_coding::has_codings(response.transfer_encoding.as_deref(), response.content_encoding.as_deref());
+        let decoded = response.body.filter(|_| options.decode_body && !skipped).map(|body| {
+            content_coding::decode_http_body(
+                body,
+                response.transfer_encoding.as_deref(),
+                response.content_encoding.as_deref(),
+            )
+        });
+        let transcoded = options.body_charset.is_some()
+            && decoded.is_some()
+            && response.content_type.as_deref().is_some_and(transcode::is_text_content_type);
+        let http_body = if skipped {
+            None
+        } else {
+            response.body.map(|b| match (options.body_charset, &decoded) {
+                // Transcoding needs the text, so HTTP codings come off first
+                (Some(target), Some(decoded)) if transcoded => {
+                    transcode::transcode(decoded, response.content_type.as_deref(), target)
+                }
+                _ => b.to_vec(),
+            })
+        };
+        let status_line_missing = response.status_line.is_none();
+        let body_encoded = http_body.is_some() && !transcoded && coded;
+        // A body left coded has no text to preview; one without codings reads as is
+        let preview = decoded
+            .as_deref()
+            .or(response.body.filter(|_| !coded))
+            .map(|body| body_preview(body, options.preview_length));
+        // Fields read from the body's content need it decoded
+        let soft_404 = decoded.as_deref().is_some_and(|body| likely_soft_404(&response, body));
+        let mismatch = response_type_mismatch(&response, base.identified_payload_type.as_deref(), decoded.as_deref());
+        let refresh_url = decoded
+            .as_deref()
+            .and_then(|body| meta_refresh_url(&response, body, record.header(WarcHeader::TargetURI).as_deref()));
+        let connection = header_value(&response.header_pairs, "connection").map(str::to_string);
+        let vary = response
+            .header_pairs
+            .iter()
+            .filter(|(name, _)| name == "vary")
+            .flat_map(|(_, value)| list_header::split_list_header(value))
+            .collect();
+        let http_warnings = response.body.map_or_else(Vec::new, |body| {
+            http_warnings(
+                body,
+                response.content_length,
+                response.transfer_encoding.as_deref(),
+                response.content_type.as_deref(),
+            )
+        });
+
+        let parsed = ParsedRecord {
+            http_version: response.version,
+            http_status: response.status,
+            http_headers: match &options.header_names {
+                Some(names) => http_headers_json(&response.header_pairs, Some(names)),
+                None => response.headers,
+            },
+            http_body,
+            skipped,
+            body_encoded,
+            http_vary: vary,
+            http_status_line: response.status_line,
+            http_protocol: response.protocol,
+            http_last_modified: response.last_modified,
+            http_etag: response.etag,
+            body_preview: preview,
+            body_sha256: response.body.filter(|_| options.hash_body).map(body_sha256),
+            likely_truncated: base.likely_truncated || shorter_than_declared(response.body, response.content_length),
+            http_content_type: response.content_type,
+            http_payload_offset: response.payload_offset.map(|offset| offset as i64),
+            http_header_bytes: response.payload_offset.map(|offset| offset.try_into().unwrap_or(i32::MAX)),
+            meta_refresh_url: refresh_url,
+            likely_soft_404: soft_404,
+            content_type_mismatch: mismatch,
+            // Without a Date header, a Retry-After date counts from the capture
+            http_retry_after: response
+                .retry_after
+                .as_deref()
+                .and_then(|value| retry_after_seconds(value, response.date.or(base.warc_date))),
+            http_age: response.age,
+            keep_alive: keep_alive(response.protocol, connection.as_deref()),
+            http_connection: connection,
+            sanitized_bytes_removed: base.sanitized_bytes_removed.map(|n| {
+                n + response.payload_offset.map_or(0, |offset| nul_bytes(&record.body()[..offset]))
+            }),
+            warnings: [base.warnings.as_slice(), &http_warnings].concat(),
+            ..base
+        };
+        // Headers without a status line are kept, but the message is incomplete
+        if status_line_missing {
+            return Ok(parsed.with_error(WarcParseError::HttpParseError("no HTTP status line")));
+        }
+        Ok(parsed)
+    } else if warc_type == "request" {
+        let Some(request) = parse_http_request(record.body()) else {
+            return Ok(base.with_error(WarcParseError::HttpParseError("no HTTP request line")));
+        };
+
+        // Form data / JSON payloads go through the same Content-Type filter
+        let skipped = options
+            .content_types
+            .as_ref()
+            .is_some_and(|filter| !filter.allows(request.content_type.as_deref()));
+        let http_body = if skipped { None } else { Some(request.body.to_vec()) };
+        let connection = header_value(&request.header_pairs, "connection").map(str::to_string);
+        let http_warnings = http_warnings(request.body, request.content_length, None, request.content_type.as_deref());
+
+        Ok(ParsedRecord {
+            http_version: Some(request.version),
+            http_headers: match &options.header_names {
+                Some(names) => http_headers_json(&request.header_pairs, Some(names)),
+                None => request.headers,
+            },
+            http_body,
+            skipped,
+            http_protocol: request.protocol,
+            http_method: Some(request.method),
+            body_preview: Some(body_preview(request.body, options.preview_length)),
+            body_sha256: options.hash_body.then(|| body_sha256(request.body)),
+            likely_truncated: base.likely_truncated || shorter_than_declared(Some(request.body), request.content_length),
+            http_content_type: request.content_type,
+            http_header_bytes: Some((record.body().len() - request.body.len()).try_into().unwrap_or(i32::MAX)),
+            keep_alive: keep_alive(request.protocol, connection.as_deref()),
+            http_connection: connection,
+            sanitized_bytes_removed: base
+                .sanitized_bytes_removed
+                .map(|n| n + nul_bytes(split_http_head(record.body()).0)),
+            warnings: [base.warnings.as_slice(), &http_warnings].concat(),
+            ..base
+        })
+    } else if warc_type == "resource" {
+        // Resource records (DNS responses, FTP files, ...) store the payload
+        // directly, typed by the WARC Content-Type rather than an HTTP envelope
+        let content_type = record.header(WarcHeader::ContentType);
+        let skipped = options
+            .content_types
+            .as_ref()
+            .is_some_and(|filter| !filter.allows(content_type.as_deref()));
+        let http_body = if skipped { None } else { Some(record.body().to_vec()) };
+
+        Ok(ParsedRecord {
+            http_body,
+            skipped,
+            body_preview: Some(body_preview(record.body(), options.preview_length)),
+            body_sha256: options.hash_body.then(|| body_sha256(record.body())),
+            ..base
+        })
+    } else if warc_type == "warcinfo" || warc_type == "metadata" {
+        // Field blocks are text in the charset of their Content-Type
+        let content_type = record.header(WarcHeader::ContentType);
+        let links = (warc_type == "metadata")
+            .then(|| warc_fields::metadata_links(record.body(), content_type.as_deref()))
+            .flatten();
+        Ok(ParsedRecord {
+            warc_fields: warc_fields::warc_fields_to_json(record.body(), content_type.as_deref())
+                .map(|json| sanitize_for_ffi(&json)),
+            metadata_via: links.as_ref().and_then(|links| links.via.clone()),
+            metadata_outlinks: links.map(|links| links.outlinks),
+            ..base
+        })
+    } else {
+        // Other non-response records don't have HTTP fields
+        Ok(base)
+    }
+}
+
+/// Names and types of the `ParsedRecord` fields, in output order
+fn record_fields() -> Vec<(&'static str, LogicalTypeHandle)> {
+    vec![
+        ("warc_version", LogicalTypeHandle::from(LogicalTypeId::Varchar)),
+        ("warc_headers", LogicalTypeHandle::from(LogicalTypeId::Varchar)),
+        ("http_version", LogicalTypeHandle::from(LogicalTypeId::Varchar)),
+        ("http_status", LogicalTypeHandle::from(LogicalTypeId::Integer)),
+        ("http_headers", LogicalTypeHandle::from(LogicalTypeId::Varchar)),
+        ("http_body", LogicalTypeHandle::from(LogicalTypeId::Blob)),
+        ("skipped", LogicalTypeHandle::from(LogicalTypeId::Boolean)),
+        ("http_status_line", LogicalTypeHandle::from(LogicalTypeId::Varchar)),
+        ("http_protocol", LogicalTypeHandle::from(LogicalTypeId::Varchar)),
+        ("http_last_modified", LogicalTypeHandle::from(LogicalTypeId::Timestamp)),
+        ("http_etag", LogicalTypeHandle::from(LogicalTypeId::Varchar)),
+        ("parse_error", LogicalTypeHandle::from(LogicalTypeId::Varchar)),
+        ("http_method", LogicalTypeHandle::from(LogicalTypeId::Varchar)),
+        ("warc_protocol", LogicalTypeHandle::from(LogicalTypeId::Varchar)),
+        ("warc_cipher_suite", LogicalTypeHandle::from(LogicalTypeId::Varchar)),
+        ("source_compression", LogicalTypeHandle::from(LogicalTypeId::Varchar)),
+        ("body_preview", LogicalTypeHandle::from(LogicalTypeId::Varchar)),
+        ("body_sha256", LogicalTypeHandle::from(LogicalTypeId::Varchar)),
+        ("likely_truncated", LogicalTypeHandle::from(LogicalTypeId::Boolean)),
+        ("warc_filename", LogicalTypeHandle::from(LogicalTypeId::Varchar)),
+        ("http_content_type", LogicalTypeHandle::from(LogicalTypeId::Varchar)),
+        ("http_payload_offset", LogicalTypeHandle::from(LogicalTypeId::Bigint)),
+        ("warc_fields", LogicalTypeHandle::from(LogicalTypeId::Varchar)),
+        ("likely_soft_404", LogicalTypeHandle::from(LogicalTypeId::Boolean)),
+        ("metadata_outlinks", LogicalTypeHandle::list(&LogicalTypeHandle::from(LogicalTypeId::Varchar))),
+        ("metadata_via", LogicalTypeHandle::from(LogicalTypeId::Varchar)),
+        ("warc_date", LogicalTypeHandle::from(LogicalTypeId::Timestamp)),
+        ("sanitized_bytes_removed", LogicalTypeHandle::from(LogicalTypeId::Integer)),
+        ("warc_concurrent_to", LogicalTypeHandle::list(&LogicalTypeHandle::from(LogicalTypeId::Varchar))),
+        ("content_type_mismatch", LogicalTypeHandle::from(LogicalTypeId::Boolean)),
+        ("http_retry_after", LogicalTypeHandle::from(LogicalTypeId::Integer)),
+        ("http_age", LogicalTypeHandle::from(LogicalTypeId::Integer)),
+        ("warc_source_uri", LogicalTypeHandle::from(LogicalTypeId::Varchar)),
+        ("warc_header_bytes", LogicalTypeHandle::from(LogicalTypeId::Integer)),
+        ("http_header_bytes", LogicalTypeHandle::from(LogicalTypeId::Integer)),
+        ("meta_refresh_url", LogicalTypeHandle::from(LogicalTypeId::Varchar)),
+        ("identified_payload_type", LogicalTypeHandle::from(LogicalTypeId::Varchar)),
+        ("http_connection", LogicalTypeHandle::from(LogicalTypeId::Varchar)),
+        ("keep_alive", LogicalTypeHandle::from(LogicalTypeId::Boolean)),
+        ("warc_version_major", LogicalTypeHandle::from(LogicalTypeId::Smallint)),
+        ("warc_version_minor", LogicalTypeHandle::from(LogicalTypeId::Smallint)),
+        ("warnings", LogicalTypeHandle::list(&LogicalTypeHandle::from(LogicalTypeId::Varchar))),
+        ("body_encoded", LogicalTypeHandle::from(LogicalTypeId::Boolean)),
+        ("http_vary", LogicalTypeHandle::list(&LogicalTypeHandle::from(LogicalTypeId::Varchar))),
+    ]
+}
+
+/// `record_fields` with `http_body` as a VARCHAR, for `parse_warc_hex` and `parse_warc_text`
+fn record_fields_varchar_body() -> Vec<(&'static str, LogicalTypeHandle)> {
+    record_fields()
+        .into_iter()
+        .map(|(name, logical_type)| match name {
+            "http_body" => (name, LogicalTypeHandle::from(LogicalTypeId::Varchar)),
+            _ => (name, logical_type),
+        })
+        .collect()
+}
+
+/// Output vectors for the fields listed by `record_fields`
+///
+/// Shared by `parse_warc` (struct children) and `read_warc` (table columns).
+struct RecordVectors {
+    warc_version: FlatVector,
+    warc_headers: FlatVector,
+    http_version: FlatVector,
+    http_status: FlatVector,
+    http_headers: FlatVector,
+    http_body: FlatVector,
+    skipped: FlatVector,
+    http_status_line: FlatVector,
+    http_protocol: FlatVector,
+    http_last_modified: FlatVector,
+    http_etag: FlatVector,
+    parse_error: FlatVector,
+    http_method: FlatVector,
+    warc_protocol: FlatVector,
+    warc_cipher_suite: FlatVector,
+    source_compression: FlatVector,
+    body_preview: FlatVector,
+    body_sha256: FlatVector,
+    likely_truncated: FlatVector,
+    warc_filename: FlatVector,
+    http_content_type: FlatVector,
+    http_payload_offset: FlatVector,
+    warc_fields: FlatVector,
+    likely_soft_404: FlatVector,
+    metadata_outlinks: ListVector,
+    metadata_outlinks_len: usize, // Entries written to the `metadata_outlinks` child so far
+    metadata_via: FlatVector,
+    warc_date: FlatVector,
+    sanitized_bytes_removed: FlatVector,
+    warc_concurrent_to: ListVector,
+    warc_concurrent_to_len: usize,
+    content_type_mismatch: FlatVector,
+    http_retry_after: FlatVector,
+    http_age: FlatVector,
+    warc_source_uri: FlatVector,
+    warc_header_bytes: FlatVector,
+    http_header_bytes: FlatVector,
+    meta_refresh_url: FlatVector,
+    identified_payload_type: FlatVector,
+    http_connection: FlatVector,
+    keep_alive: FlatVector,
+    warc_version_major: FlatVector,
+    warc_version_minor: FlatVector,
+    warnings: ListVector,
+    warnings_len: usize,
+    body_encoded: FlatVector,
+    http_vary: ListVector,
+    http_vary_len: usize,
+    hex_body: bool,  // `http_body` is a VARCHAR of hex (`record_fields_varchar_body`)
+    text_body: bool, // `http_body` is a VARCHAR of the body as text
+}
+
+impl RecordVectors {
+    /// Build from functions returning the vector of the n-th field, for flat
+    /// and LIST fields
+    fn new(mut vector: impl FnMut(usize) -> FlatVector, mut list: impl FnMut(usize) -> ListVector) -> Self {
+        Self {
+            warc_version: vector(0),
+            warc_headers: vector(1),
+            http_version: vector(2),
+            http_status: vector(3),
+            http_headers: vector(4),
+            http_body: vector(5),
+            skipped: vector(6),
+            http_status_line: vector(7),
+            http_protocol: vector(8),
+            http_last_modified: vector(9),
+            http_etag: vector(10),
+            parse_error: vector(11),
+            http_method: vector(12),
+            warc_protocol: vector(13),
+            warc_cipher_suite: vector(14),
+            source_compression: vector(15),
+            body_preview: vector(16),
+            body_sha256: vector(17),
+            likely_truncated: vector(18),
+            warc_filename: vector(19),
+            http_content_type: vector(20),
+            http_payload_offset: vector(21),
+            warc_fields: vector(22),
+            likely_soft_404: vector(23),
+            metadata_outlinks: list(24),
+            metadata_outlinks_len: 0,
+            metadata_via: vector(25),
+            warc_date: vector(26),
+            sanitized_bytes_removed: vector(27),
+            warc_concurrent_to: list(28),
+            warc_concurrent_to_len: 0,
+            content_type_mismatch: vector(29),
+            http_retry_after: vector(30),
+            http_age: vector(31),
+            warc_source_uri: vector(32),
+            warc_header_bytes: vector(33),
+            http_header_bytes: vector(34),
+            meta_refresh_url: vector(35),
+            identified_payload_type: vector(36),
+            http_connection: vector(37),
+            keep_alive: vector(38),
+            warc_version_major: vector(39),
+            warc_version_minor: vector(40),
+            warnings: list(41),
+            warnings_len: 0,
+            body_encoded: vector(42),
+            http_vary: list(43),
+            http_vary_len: 0,
+            hex_body: false,
+            text_body: false,
+        }
+    }
+
+    /// Write a record to row `i`, or NULL in every field for None
+    fn write(&mut self, i: usize, record: Option<&ParsedRecord>) {
+        let Some(record) = record else {
+            for vector in [
+                &mut self.warc_version,
+                &mut self.warc_headers,
+                &mut self.http_version,
+                &mut self.http_status,
+                &mut self.http_headers,
+                &mut self.http_body,
+                &mut self.skipped,
+                &mut self.http_status_line,
+                &mut self.http_protocol,
+                &mut self.http_last_modified,
+                &mut self.http_etag,
+                &mut self.parse_error,
+                &mut self.http_method,
+                &mut self.warc_protocol,
+                &mut self.warc_cipher_suite,
+                &mut self.source_compression,
+                &mut self.body_preview,
+                &mut self.body_sha256,
+                &mut self.likely_truncated,
+                &mut self.warc_filename,
+                &mut self.http_content_type,
+                &mut self.http_payload_offset,
+                &mut self.warc_fields,
+                &mut self.likely_soft_404,
+                &mut self.metadata_via,
+                &mut self.warc_date,
+                &mut self.sanitized_bytes_removed,
+                &mut self.content_type_mismatch,
+                &mut self.http_retry_after,
+                &mut self.http_age,
+                &mut self.warc_source_uri,
+                &mut self.warc_header_bytes,
+                &mut self.http_header_bytes,
+                &mut self.meta_refresh_url,
+                &mut self.identified_payload_type,
+                &mut self.http_connection,
+                &mut self.keep_alive,
+                &mut self.warc_version_major,
+                &mut self.warc_version_minor,
+                &mut self.body_encoded,
+            ] {
+                vector.set_null(i);
+            }
+            self.metadata_outlinks.set_null(i);
+            self.warc_concurrent_to.set_null(i);
+            self.warnings.set_null(i);
+            self.http_vary.set_null(i);
+            return;
+        };
+
+        match &record.warc_version {
+            Some(v) => self.warc_version.insert(i, v.as_str()),
+            None => self.warc_version.set_null(i),
+        }
+
+        match &record.warc_headers {
+            Some(v) => self.warc_headers.insert(i, v.as_str()),
+            None => self.warc_headers.set_null(i),
+        }
+
+        match &record.http_version {
+            Some(v) => self.http_version.insert(i, v.as_str()),
+            None => self.http_version.set_null(i),
+        }
+
+        match record.http_status {
+            Some(v) => self.http_status.as_mut_slice::<i32>()[i] = v,
+            None => self.http_status.set_null(i),
+        }
+
+        match &record.http_headers {
+            Some(v) => self.http_headers.insert(i, v.as_str()),
+            None => self.http_headers.set_null(i),
+        }
+
+        match &record.http_body {
+            Some(v) if self.hex_body => self.http_body.insert(i, to_hex(v).as_str()),
+            Some(v) if self.text_body => self.http_body.insert(i, body_text(v).as_str()),
+            // Use explicit &[u8] type to ensure BLOB insertion (not string)
+            Some(v) => Inserter::<&[u8]>::insert(&self.http_body, i, v.as_slice()),
+            None => self.http_body.set_null(i),
+        }
+
+        self.skipped.as_mut_slice::<bool>()[i] = record.skipped;
+
+        match &record.http_status_line {
+            Some(v) => self.http_status_line.insert(i, v.as_str()),
+            None => self.http_status_line.set_null(i),
+        }
+
+        match record.http_protocol {
+            Some(v) => self.http_protocol.insert(i, v),
+            None => self.http_protocol.set_null(i),
+        }
+
+        match record.http_last_modified {
+            Some(v) => self.http_last_modified.as_mut_slice::<i64>()[i] = v,
+            None => self.http_last_modified.set_null(i),
+        }
+
+        match &record.http_etag {
+            Some(v) => self.http_etag.insert(i, v.as_str()),
+            None => self.http_etag.set_null(i),
+        }
+
+        match &record.parse_error {
+            Some(v) => self.parse_error.insert(i, v.as_str()),
+            None => self.parse_error.set_null(i),
+        }
+
+        match &record.http_method {
+            Some(v) => self.http_method.insert(i, v.as_str()),
+            None => self.http_method.set_null(i),
+        }
+
+        match &record.warc_protocol {
+            Some(v) => self.warc_protocol.insert(i, v.as_str()),
+            None => self.warc_protocol.set_null(i),
+        }
+
+        match &record.warc_cipher_suite {
+            Some(v) => self.warc_cipher_suite.insert(i, v.as_str()),
+            None => self.warc_cipher_suite.set_null(i),
+        }
+
+        match record.source_compression {
+            Some(v) => self.source_compression.insert(i, v),
+            None => self.source_compression.set_null(i),
+        }
+
+        match &record.body_preview {
+            Some(v) => self.body_preview.insert(i, v.as_str()),
+            None => self.body_preview.set_null(i),
+        }
+
+        match &record.body_sha256 {
+            Some(v) => self.body_sha256.insert(i, v.as_str()),
+            None => self.body_sha256.set_null(i),
+        }
+
+        self.likely_truncated.as_mut_slice::<bool>()[i] = record.likely_truncated;
+
+        match &record.warc_filename {
+            Some(v) => self.warc_filename.insert(i, v.as_str()),
+            None => self.warc_filename.set_null(i),
+        }
+
+        match &record.http_content_type {
+            Some(v) => self.http_content_type.insert(i, v.as_str()),
+            None => self.http_content_type.set_null(i),
+        }
+
+        match record.http_payload_offset {
+            Some(v) => self.http_payload_offset.as_mut_slice::<i64>()[i] = v,
+            None => self.http_payload_offset.set_null(i),
+        }
+
+        match &record.warc_fields {
+            Some(v) => self.warc_fields.insert(i, v.as_str()),
+            None => self.warc_fields.set_null(i),
+        }
+
+        self.likely_soft_404.as_mut_slice::<bool>()[i] = record.likely_soft_404;
+
+        match &record.metadata_outlinks {
+            Some(outlinks) => write_list(&mut self.metadata_outlinks, &mut self.metadata_outlinks_len, i, outlinks),
+            None => self.metadata_outlinks.set_null(i),
+        }
+
+        match &record.metadata_via {
+            Some(v) => self.metadata_via.insert(i, v.as_str()),
+            None => self.metadata_via.set_null(i),
+        }
+
+        match record.warc_date {
+            Some(v) => self.warc_date.as_mut_slice::<i64>()[i] = v,
+            None => self.warc_date.set_null(i),
+        }
+
+        match record.sanitized_bytes_removed {
+            Some(v) => self.sanitized_bytes_removed.as_mut_slice::<i32>()[i] = v,
+            None => self.sanitized_bytes_removed.set_null(i),
+        }
+
+        write_list(&mut self.warc_concurrent_to, &mut self.warc_concurrent_to_len, i, &record.warc_concurrent_to);
+
+        self.content_type_mismatch.as_mut_slice::<bool>()[i] = record.content_type_mismatch;
+
+        match record.http_retry_after {
+            Some(v) => self.http_retry_after.as_mut_slice::<i32>()[i] = v,
+            None => self.http_retry_after.set_null(i),
+        }
+
+        match record.http_age {
+            Some(v) => self.http_age.as_mut_slice::<i32>()[i] = v,
+            None => self.http_age.set_null(i),
+        }
+
+        match &record.warc_source_uri {
+            Some(v) => self.warc_source_uri.insert(i, v.as_str()),
+            None => self.warc_source_uri.set_null(i),
+        }
+
+        match record.warc_header_bytes {
+            Some(v) => self.warc_header_bytes.as_mut_slice::<i32>()[i] = v,
+            None => self.warc_header_bytes.set_null(i),
+        }
+
+        match record.http_header_bytes {
+            Some(v) => self.http_header_bytes.as_mut_slice::<i32>()[i] = v,
+            None => self.http_header_bytes.set_null(i),
+        }
+
+        match &record.meta_refresh_url {
+            Some(v) => self.meta_refresh_url.insert(i, v.as_str()),
+            None => self.meta_refresh_url.set_null(i),
+        }
+
+        match &record.identified_payload_type {
+            Some(v) => self.identified_payload_type.insert(i, v.as_str()),
+            None => self.identified_payload_type.set_null(i),
+        }
+
+        match &record.http_connection {
+            Some(v) => self.http_connection.insert(i, v.as_str()),
+            None => self.http_connection.set_null(i),
+        }
+
+        match record.keep_alive {
+            Some(v) => self.keep_alive.as_mut_slice::<bool>()[i] = v,
+            None => self.keep_alive.set_null(i),
+        }
+
+        match record.warc_version_major {
+            Some(v) => self.warc_version_major.as_mut_slice::<i16>()[i] = v,
+            None => self.warc_version_major.set_null(i),
+        }
+
+        match record.warc_version_minor {
+            Some(v) => self.warc_version_minor.as_mut_slice::<i16>()[i] = v,
+            None => self.warc_version_minor.set_null(i),
+        }
+        write_list(&mut self.warnings, &mut self.warnings_len, i, &record.warnings);
+        self.body_encoded.as_mut_slice::<bool>()[i] = record.body_encoded;
+        write_list(&mut self.http_vary, &mut self.http_vary_len, i, &record.http_vary);
+    }
+}
+
+/// Write `values` as the LIST of row `i`, after the `len` entries already in its child
+fn write_list(list: &mut ListVector, len: &mut usize, i: usize, values: &[String]) {
+    let offset = *len;
+    *len += values.len();
+    let child = list.child(*len);
+    for (j, value) in values.iter().enumerate() {
+        child.insert(offset + j, value.as_str());
+    }
+    list.set_entry(i, offset, values.len());
+    list.set_len(*len);
+}
+
+/// DuckDB scalar function to parse WARC records from gzip-compressed data
+///
+/// Returns a struct with:
+/// - warc_version: VARCHAR
+/// - warc_headers: VARCHAR (JSON map)
+/// - http_version: VARCHAR
+/// - http_status: INTEGER
+/// - http_headers: VARCHAR (JSON map)
+/// - http_body: VARCHAR
+/// - skipped: BOOLEAN
+/// - http_status_line: VARCHAR (raw status line)
+/// - http_protocol: VARCHAR (normalized, e.g. "HTTP/2")
+/// - http_last_modified: TIMESTAMP (parsed Last-Modified HTTP-date)
+/// - http_etag: VARCHAR (verbatim ETag)
+/// - parse_error: VARCHAR (why the record was only partially parsed)
+/// - http_method: VARCHAR (request records only)
+/// - warc_protocol: VARCHAR (WARC-Protocol values, e.g. "h2, tls/1.3")
+/// - warc_cipher_suite: VARCHAR
+/// - source_compression: VARCHAR ("gzip" or "none")
+/// - body_preview: VARCHAR (first bytes of the body as text)
+/// - body_sha256: VARCHAR (hex SHA-256 of the body; `parse_warc_hashed` only)
+/// - likely_truncated: BOOLEAN (WARC-Truncated, or body shorter than its Content-Length)
+/// - warc_filename: VARCHAR (WARC-Filename of warcinfo records)
+/// - http_content_type: VARCHAR (Content-Type of the HTTP message)
+/// - http_payload_offset: BIGINT (offset of the HTTP body in the record block)
+/// - warc_fields: VARCHAR (JSON map of warcinfo/metadata fields)
+/// - likely_soft_404: BOOLEAN (heuristic: 2xx text response reading like a "not found" page)
+/// - metadata_outlinks: VARCHAR[] (`outlink:` URLs of metadata records)
+/// - metadata_via: VARCHAR (`via:` URL of metadata records)
+/// - warc_date: TIMESTAMP (parsed WARC-Date)
+/// - sanitized_bytes_removed: INTEGER (NUL bytes dropped from WARC and HTTP headers)
+/// - warc_concurrent_to: VARCHAR[] (every WARC-Concurrent-To record ID)
+/// - content_type_mismatch: BOOLEAN (declared, identified and sniffed body types disagree)
+/// - http_retry_after: INTEGER (Retry-After in seconds, from delay-seconds or an HTTP-date)
+/// - http_age: INTEGER (Age in seconds)
+/// - warc_source_uri: VARCHAR (WARC-Source-URI)
+/// - warc_header_bytes: INTEGER (size of the WARC header block)
+/// - http_header_bytes: INTEGER (size of the HTTP headers before the payload)
+/// - meta_refresh_url: VARCHAR (`<meta http-equiv="refresh">` URL of HTML responses)
+/// - identified_payload_type: VARCHAR (WARC-Identified-Payload-Type)
+/// - http_connection: VARCHAR (Connection header)
+/// - keep_alive: BOOLEAN (connection persists, per the HTTP version's default and Connection)
+/// - warc_version_major: SMALLINT (`1` of WARC/1.0)
+/// - warc_version_minor: SMALLINT (`0` of WARC/1.0)
+/// - warnings: VARCHAR[] (non-fatal issues: truncation, digest or Content-Length mismatch, unknown charset)
+/// - body_encoded: BOOLEAN (`http_body` still carries HTTP transfer or content codings)
+/// - http_vary: VARCHAR[] (field names of the Vary header, split on commas)
+///
+/// An optional VARCHAR[] argument is a Content-Type allow/deny list, and an
+/// optional trailing INTEGER sets the `body_preview` length (default 256).
+struct ParseWarc;
+
+/// Read the INTEGER preview length argument; NULL means the default and
+/// negative lengths are treated as 0
+unsafe fn read_preview_length_column(input: &DataChunkHandle, column: usize, size: usize) -> Vec<usize> {
+    let vector = input.flat_vector(column);
+    vector
+        .as_slice_with_len::<i32>(size)
+        .iter()
+        .enumerate()
+        .map(|(row, &length)| {
+            if vector.row_is_null(row as u64) {
+                DEFAULT_PREVIEW_LENGTH
+            } else {
+                length.max(0) as usize
+            }
+        })
+        .collect()
+}
+
+/// Read a LIST(VARCHAR) argument column into one owned list per row
+unsafe fn read_varchar_list_column(input: &DataChunkHandle, column: usize, size: usize) -> Vec<Option<Vec<String>>> {
+    let vector = ffi::duckdb_data_chunk_get_vector(input.get_ptr(), column as u64);
+    let entries = FlatVector::from(vector);
+    let child = FlatVector::from(ffi::duckdb_list_vector_get_child(vector));
+    let child_len = ffi::duckdb_list_vector_get_size(vector) as usize;
+    let child_strings = child.as_slice_with_len::<duckdb_string_t>(child_len);
+
+    entries
+        .as_slice_with_len::<ffi::duckdb_list_entry>(size)
+        .iter()
+        .enumerate()
+        .map(|(row, entry)| {
+            if entries.row_is_null(row as u64) {
+                return None;
+            }
+            let start = entry.offset as usize;
+            let end = start + entry.length as usize;
+            let values = (start..end)
+                .filter(|&j| !child.row_is_null(j as u64))
+                .map(|j| {
+                    let mut value = child_strings[j];
+                    DuckString::new(&mut value).as_str().to_string()
+                })
+                .collect();
+            Some(values)
+        })
+        .collect()
+}
+
+/// Per-function settings distinguishing `parse_warc` from its variants
+#[derive(Clone, Default)]
+struct ParseWarcState {
+    hash_body: bool,
+    hex_body: bool,
+    text_body: bool,    // `http_body` as UTF-8 text, text bodies decoded from their charset
+    base64_input: bool, // Input is base64 text of the record bytes
+    require_compressed: bool, // Input must be gzip
+}
+
+impl VScalar for ParseWarc {
+    type State = ParseWarcState;
+
+    unsafe fn invoke(
+        state: &Self::State,
+        input: &mut DataChunkHandle,
+        output: &mut dyn WritableVector,
+    ) -> std::result::Result<(), Box<dyn Error>> {
+        let size = input.len();
+        if size == 0 {
+            return Ok(());
+        }
+
+        // Optional Content-Type filter and preview length arguments
+        let column_type = |column: usize| input.flat_vector(column).logical_type().id();
+        let content_type_lists = if input.num_columns() > 1 && column_type(1) == LogicalTypeId::List {
+            Some(read_varchar_list_column(input, 1, size))
+        } else {
+            None
+        };
+        let last = input.num_columns() - 1;
+        let preview_lengths = if last > 0 && column_type(last) == LogicalTypeId::Integer {
+            Some(read_preview_length_column(input, last, size))
+        } else {
+            None
+        };
+
+        let rows: Vec<(Option<&[u8]>, ParseOptions)> = blob_rows(input, 0)
+            .into_iter()
+            .enumerate()
+            .map(|(i, raw_data)| {
+                let options = ParseOptions {
+                    content_types: content_type_lists
+                        .as_ref()
+                        .and_then(|lists| lists[i].as_deref())
+                        .map(ContentTypeFilter::new),
+                    // Spec casing; the table functions take `lowercase_headers`
+                    lowercase_header_names: false,
+                    preview_length: preview_lengths.as_ref().map_or(DEFAULT_PREVIEW_LENGTH, |lengths| lengths[i]),
+                    hash_body: state.hash_body,
+                    body_charset: state.text_body.then_some(encoding_rs::UTF_8),
+                    header_names: None,
+                    require_compressed: state.require_compressed,
+                    decode_body: true,
+                };
+                (raw_data, options)
+            })
+            .collect();
+
+        // Decompress and parse rows on the extension's pool; only writing
+        // the output vectors has to happen on DuckDB's thread
+        let base64_input = state.base64_input;
+        let records = parallel::par_map(&rows, |(raw_data, options)| {
+            // Unparseable input is reported in `parse_error`; only NULL stays NULL
+            raw_data.map(|raw_data| {
+                let record = if base64_input {
+                    decode_base64(raw_data).and_then(|data| parse_raw_warc_record(&data, options))
+                } else {
+                    parse_raw_warc_record(raw_data, options)
+                };
+                record.unwrap_or_else(ParsedRecord::from_error)
+            })
+        });
+
+        let output_struct = output.struct_vector();
+        let capacity = ffi::duckdb_vector_size() as usize;
+        check_struct_output(size, capacity, output_struct.num_children(), record_fields().len())?;
+        let mut vectors = RecordVectors::new(
+            |field| output_struct.child(field, capacity),
+            |field| output_struct.list_vector_child(field),
+        );
+        vectors.hex_body = state.hex_body;
+        vectors.text_body = state.text_body;
+        for (i, record) in records.iter().enumerate() {
+            vectors.write(i, record.as_ref());
+        }
+
+        Ok(())
+    }
+
+    fn signatures() -> Vec<ScalarFunctionSignature> {
+        parse_warc_signatures(record_fields)
+    }
+}
+
+/// `parse_warc` with `http_body` as a VARCHAR instead of a BLOB: lowercase hex
+/// for exports (e.g. CSV) that can't carry binary data (`parse_warc_hex`), or
+/// the body as text (`parse_warc_text`)
+struct ParseWarcVarcharBody;
+
+impl VScalar for ParseWarcVarcharBody {
+    type State = ParseWarcState;
+
+    unsafe fn invoke(
+        state: &Self::State,
+        input: &mut DataChunkHandle,
+        output: &mut dyn WritableVector,
+    ) -> std::result::Result<(), Box<dyn Error>> {
+        ParseWarc::invoke(state, input, output)
+    }
+
+    fn signatures() -> Vec<ScalarFunctionSignature> {
+        parse_warc_signatures(record_fields_varchar_body)
+    }
+}
+
+/// Signatures of `parse_warc` and its variants, returning a struct of `fields`
+fn parse_warc_signatures(fields: fn() -> Vec<(&'static str, LogicalTypeHandle)>) -> Vec<ScalarFunctionSignature> {
+    // Helper to create struct return type (needed twice since LogicalTypeHandle doesn't impl Clone)
+    let make_return_type = || LogicalTypeHandle::struct_type(&fields());
+    let content_types = || LogicalTypeHandle::list(&LogicalTypeHandle::from(LogicalTypeId::Varchar));
+
+    let preview_length = || LogicalTypeHandle::from(LogicalTypeId::Integer);
+
+    // Support both BLOB and VARCHAR inputs, optionally with a Content-Type
+    // filter and a preview length
+    vec![
+        ScalarFunctionSignature::exact(
+            vec![LogicalTypeHandle::from(LogicalTypeId::Blob)],
+            make_return_type(),
+        ),
+        ScalarFunctionSignature::exact(
+            vec![LogicalTypeHandle::from(LogicalTypeId::Varchar)],
+            make_return_type(),
+        ),
+        ScalarFunctionSignature::exact(
+            vec![LogicalTypeHandle::from(LogicalTypeId::Blob), content_types()],
+            make_return_type(),
+        ),
+        ScalarFunctionSignature::exact(
+            vec![LogicalTypeHandle::from(LogicalTypeId::Varchar), content_types()],
+            make_return_type(),
+        ),
+        ScalarFunctionSignature::exact(
+            vec![LogicalTypeHandle::from(LogicalTypeId::Blob), preview_length()],
+            make_return_type(),
+        ),
+        ScalarFunctionSignature::exact(
+            vec![LogicalTypeHandle::from(LogicalTypeId::Varchar), preview_length()],
+            make_return_type(),
+        ),
+        ScalarFunctionSignature::exact(
+            vec![LogicalTypeHandle::from(LogicalTypeId::Blob), content_types(), preview_length()],
+            make_return_type(),
+        ),
+        ScalarFunctionSignature::exact(
+            vec![LogicalTypeHandle::from(LogicalTypeId::Varchar), content_types(), preview_length()],
+            make_return_type(),
+        ),
+    ]
+}
+
+/// DuckDB scalar function returning the UUID of a record's WARC-Record-ID
+///
+/// NULL when the ID is not a `urn:uuid:` URN.
+struct WarcRecordUuid;
+
+impl VScalar for WarcRecordUuid {
+    type State = ();
+
+    unsafe fn invoke(
+        _state: &Self::State,
+        input: &mut DataChunkHandle,
+        output: &mut dyn WritableVector,
+    ) -> std::result::Result<(), Box<dyn Error>> {
+        let mut output_vector = output.flat_vector();
+        let mut uuids = vec![None; input.len()];
+
+        for_each_blob(input, 0, |i, raw_data| {
+            uuids[i] = raw_data.and_then(|raw_data| {
+                let record = with_decompressed(raw_data, |data| read_first_record_lenient(data).ok())?;
+                record_id_uuid(&record.header(WarcHeader::RecordID)?)
+            });
+        });
+
+        for (i, uuid) in uuids.into_iter().enumerate() {
+            match uuid {
+                Some(uuid) => {
+                    // DuckDB stores UUIDs as a HUGEINT with the top bit flipped
+                    output_vector.as_mut_slice::<ffi::duckdb_hugeint>()[i] = ffi::duckdb_hugeint {
+                        lower: uuid as u64,
+                        upper: ((uuid >> 64) as u64 ^ (1 << 63)) as i64,
+                    };
+                }
+                None => output_vector.set_null(i),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn signatures() -> Vec<ScalarFunctionSignature> {
+        vec![
+            ScalarFunctionSignature::exact(
+                vec![LogicalTypeHandle::from(LogicalTypeId::Blob)],
+                LogicalTypeHandle::from(LogicalTypeId::Uuid),
+            ),
+            ScalarFunctionSignature::exact(
+                vec![LogicalTypeHandle::from(LogicalTypeId::Varchar)],
+                LogicalTypeHandle::from(LogicalTypeId::Uuid),
+            ),
+        ]
+    }
+}
+
+/// Microseconds a day lasts, for WARC-Date arithmetic
+const MICROS_PER_DAY: f64 = 86_400_000_000.0;
+
+/// Days between a record's WARC-Date and `now`, in microseconds since the epoch
+///
+/// Both are UTC, so no time zone enters the difference. Negative for a date
+/// after `now`; None when the date is missing or unparseable.
+fn record_age_days(data: &[u8], now: i64) -> Option<f64> {
+    let record = read_first_record_lenient(data).ok()?;
+    let warc_date = http_date::parse_record_date(&record.header(WarcHeader::Date)?)?;
+    Some((now - warc_date) as f64 / MICROS_PER_DAY)
+}
+
+/// The current time in microseconds since the epoch
+fn now_micros() -> i64 {
+    let elapsed = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    elapsed.as_micros().try_into().unwrap_or(i64::MAX)
+}
+
+/// DuckDB scalar function returning the days since a record's WARC-Date
+///
+/// Returns a DOUBLE counted from the current time, NULL when the date is
+/// missing or unparseable.
+struct WarcAgeDays;
+
+impl VScalar for WarcAgeDays {
+    type State = ();
+
+    unsafe fn invoke(
+        _state: &Self::State,
+        input: &mut DataChunkHandle,
+        output: &mut dyn WritableVector,
+    ) -> std::result::Result<(), Box<dyn Error>> {
+        let mut output_vector = output.flat_vector();
+        // One clock reading per chunk, so rows of a chunk agree on "now"
+        let now = now_micros();
+
+        for_each_blob(input, 0, |i, raw_data| {
+            match raw_data.and_then(|raw_data| with_decompressed(raw_data, |data| record_age_days(data, now))) {
+                Some(days) => output_vector.as_mut_slice::<f64>()[i] = days,
+                None => output_vector.set_null(i),
+            }
+        });
+
+        Ok(())
+    }
+
+    fn signatures() -> Vec<ScalarFunctionSignature> {
+        vec![
+            ScalarFunctionSignature::exact(
+                vec![LogicalTypeHandle::from(LogicalTypeId::Blob)],
+                LogicalTypeHandle::from(LogicalTypeId::Double),
+            ),
+            ScalarFunctionSignature::exact(
+                vec![LogicalTypeHandle::from(LogicalTypeId::Varchar)],
+                LogicalTypeHandle::from(LogicalTypeId::Double),
+            ),
+        ]
+    }
+}
+
+/// The HTTP message of a response record exactly as captured
+///
+/// Status line, headers and body are returned byte-for-byte, without the
+/// header lowercasing and sanitizing applied by `parse_warc`.
+fn http_raw(data: &[u8]) -> Option<Vec<u8>> {
+    let record = read_first_record_lenient(data).ok()?;
+    if record.header(WarcHeader::WarcType)? != "response" {
+        return None;
+    }
+    Some(record.body().to_vec())
+}
+
+/// DuckDB scalar function returning the raw HTTP message of a response record
+///
+/// NULL for other record types and unparseable input.
+struct WarcHttpRaw;
+
+impl VScalar for WarcHttpRaw {
+    type State = ();
+
+    unsafe fn invoke(
+        _state: &Self::State,
+        input: &mut DataChunkHandle,
+        output: &mut dyn WritableVector,
+    ) -> std::result::Result<(), Box<dyn Error>> {
+        let mut output_vector = output.flat_vector();
+        let mut messages = vec![None; input.len()];
+
+        for_each_blob(input, 0, |i, raw_data| {
+            messages[i] = raw_data.and_then(|raw_data| with_decompressed(raw_data, http_raw));
+        });
+
+        for (i, message) in messages.iter().enumerate() {
+            match message {
+                Some(v) => Inserter::<&[u8]>::insert(&output_vector, i, v.as_slice()),
+                None => output_vector.set_null(i),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn signatures() -> Vec<ScalarFunctionSignature> {
+        vec![
+            ScalarFunctionSignature::exact(
+                vec![LogicalTypeHandle::from(LogicalTypeId::Blob)],
+                LogicalTypeHandle::from(LogicalTypeId::Blob),
+            ),
+            ScalarFunctionSignature::exact(
+                vec![LogicalTypeHandle::from(LogicalTypeId::Varchar)],
+                LogicalTypeHandle::from(LogicalTypeId::Blob),
+            ),
+        ]
+    }
+}
+
+/// The record with its block dropped: every WARC header kept as written
+/// (WARC-Payload-Digest included) and Content-Length set to 0
+///
+/// None when the input isn't a WARC record.
+fn strip_body(data: &[u8]) -> Option<Vec<u8>> {
+    read_first_record_lenient(data).ok()?;
+    let (header_block, _) = warc_file::read_header_block(&mut &data[..]).ok()??;
+
+    let mut stripped = Vec::with_capacity(header_block.len() + 4);
+    for line in header_block.split_inclusive(|&b| b == b'\n') {
+        let name = line.split(|&b| b == b':').next().unwrap_or_default();
+        if line.contains(&b':') && name.trim_ascii().eq_ignore_ascii_case(b"Content-Length") {
+            stripped.extend_from_slice(b"Content-Length: 0");
+            stripped.extend_from_slice(&line[line.trim_ascii_end().len()..]);
+        } else {
+            stripped.extend_from_slice(line);
+        }
+    }
+    // Empty block, then the record's closing CRLF CRLF
+    stripped.extend_from_slice(b"\r\n\r\n");
+    Some(stripped)
+}
+
+/// `strip_body` of a possibly gzipped record, gzipped again when the input was
+fn strip_body_raw(raw_data: &[u8]) -> Option<Vec<u8>> {
+    with_decompressed(raw_data, |data| {
+        let stripped = strip_body(data)?;
+        // Anything else than `raw_data` itself was decompressed from it
+        if std::ptr::eq(data, raw_data) {
+            Some(stripped)
+        } else {
+            gzip::gzip_compress(&stripped).ok()
+        }
+    })
+}
+
+/// DuckDB scalar function re-emitting a record without its block
+///
+/// NULL for input that isn't a WARC record.
+struct WarcStripBody;
+
+impl VScalar for WarcStripBody {
+    type State = ();
+
+    unsafe fn invoke(
+        _state: &Self::State,
+        input: &mut DataChunkHandle,
+        output: &mut dyn WritableVector,
+    ) -> std::result::Result<(), Box<dyn Error>> {
+        let mut output_vector = output.flat_vector();
+        let mut records = vec![None; input.len()];
+
+        for_each_blob(input, 0, |i, raw_data| {
+            records[i] = raw_data.and_then(strip_body_raw);
+        });
+
+        for (i, record) in records.iter().enumerate() {
+            match record {
+                Some(v) => Inserter::<&[u8]>::insert(&output_vector, i, v.as_slice()),
+                None => output_vector.set_null(i),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn signatures() -> Vec<ScalarFunctionSignature> {
+        vec![
+            ScalarFunctionSignature::exact(
+                vec![LogicalTypeHandle::from(LogicalTypeId::Blob)],
+                LogicalTypeHandle::from(LogicalTypeId::Blob),
+            ),
+            ScalarFunctionSignature::exact(
+                vec![LogicalTypeHandle::from(LogicalTypeId::Varchar)],
+                LogicalTypeHandle::from(LogicalTypeId::Blob),
+            ),
+        ]
+    }
+}
+
+/// The decompressed record as text, exactly as handed to `WarcReader`
+///
+/// Invalid UTF-8 (binary bodies) is lossily decoded and NUL bytes are dropped.
+fn raw_record_text(raw_data: &[u8]) -> String {
+    with_decompressed(raw_data, |data| sanitize_for_ffi(&String::from_utf8_lossy(data)))
+}
+
+/// DuckDB scalar function returning the decompressed WARC record as text
+struct WarcRawRecord;
+
+impl VScalar for WarcRawRecord {
+    type State = ();
+
+    unsafe fn invoke(
+        _state: &Self::State,
+        input: &mut DataChunkHandle,
+        output: &mut dyn WritableVector,
+    ) -> std::result::Result<(), Box<dyn Error>> {
+        let mut output_vector = output.flat_vector();
+        let mut texts = vec![None; input.len()];
+
+        for_each_blob(input, 0, |i, raw_data| {
+            texts[i] = raw_data.map(raw_record_text);
+        });
+
+        for (i, text) in texts.iter().enumerate() {
+            match text {
+                Some(v) => output_vector.insert(i, v.as_str()),
+                None => output_vector.set_null(i),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn signatures() -> Vec<ScalarFunctionSignature> {
+        vec![
+            ScalarFunctionSignature::exact(
+                vec![LogicalTypeHandle::from(LogicalTypeId::Blob)],
+                LogicalTypeHandle::from(LogicalTypeId::Varchar),
+            ),
+            ScalarFunctionSignature::exact(
+                vec![LogicalTypeHandle::from(LogicalTypeId::Varchar)],
+                LogicalTypeHandle::from(LogicalTypeId::Varchar),
+            ),
+        ]
+    }
+}
+
+/// Extension entrypoint, registering all functions on the connection
+///
+/// # Safety
+///
+/// Called by DuckDB when the extension is loaded; `con` must be a valid connection.
+#[duckdb_entrypoint_c_api()]
+pub unsafe fn extension_entrypoint(con: Connection) -> Result<(), Box<dyn Error>> {
+    // Size the parsing pool from DuckDB's `threads` setting
+    let threads: i64 = con.query_row("SELECT current_setting('threads')", [], |row| row.get(0))?;
+    parallel::init(threads)?;
+
+    con.register_scalar_function::<ParseWarc>("parse_warc")?;
+    con.register_scalar_function_with_state::<ParseWarc>(
+        "parse_warc_hashed",
+        &ParseWarcState {
+            hash_body: true,
+            ..Default::default()
+        },
+    )?;
+    con.register_scalar_function_with_state::<ParseWarcVarcharBody>(
+        "parse_warc_hex",
+        &ParseWarcState {
+            hex_body: true,
+            ..Default::default()
+        },
+    )?;
+    con.register_scalar_function_with_state::<ParseWarcVarcharBody>(
+        "parse_warc_text",
+        &ParseWarcState {
+            text_body: true,
+            ..Default::default()
+        },
+    )?;
+    con.register_scalar_function_with_state::<ParseWarc>(
+        "parse_warc_base64",
+        &ParseWarcState {
+            base64_input: true,
+            ..Default::default()
+        },
+    )?;
+    con.register_scalar_function_with_state::<ParseWarc>(
+        "parse_warc_gzip",
+        &ParseWarcState {
+            require_compressed: true,
+            ..Default::default()
+        },
+    )?;
+    con.register_scalar_function::<WarcRecordUuid>("warc_record_uuid")?;
+    con.register_scalar_function::<WarcAgeDays>("warc_age_days")?;
+    con.register_scalar_function::<WarcHttpRaw>("warc_http_raw")?;
+    con.register_scalar_function::<WarcRawRecord>("warc_raw_record")?;
+    con.register_scalar_function::<WarcStripBody>("warc_strip_body")?;
+    con.register_scalar_function::<meta::ParseWarcMeta>("parse_warc_meta")?;
+    con.register_scalar_function::<meta::WarcHeaderPresent>("warc_header_present")?;
+    con.register_scalar_function::<cookies::WarcCookies>("warc_cookies")?;
+    con.register_scalar_function::<read_warc_types::WarcTypeCounts>("warc_type_counts")?;
+    con.register_scalar_function::<split::WarcSplit>("warc_split")?;
+    con.register_scalar_function::<link::WarcParseLinkHeader>("warc_parse_link_header")?;
+    con.register_scalar_function::<list_header::WarcSplitListHeader>("warc_split_list_header")?;
+    con.register_scalar_function::<html::WarcHtmlTitle>("warc_html_title")?;
+    con.register_scalar_function::<html::WarcCanonicalUrl>("warc_canonical_url")?;
+    con.register_scalar_function::<html::WarcExtractForms>("warc_extract_forms")?;
+    con.register_scalar_function::<html::WarcJsonld>("warc_jsonld")?;
+    con.register_scalar_function::<json_body::WarcBodyJson>("warc_body_json")?;
+    con.register_scalar_function::<image_info::WarcImageInfo>("warc_image_info")?;
+    con.register_scalar_function::<domain::WarcRegistrableDomain>("warc_registrable_domain")?;
+    con.register_scalar_function::<canonical_uri::WarcCanonicalUri>("warc_canonical_uri")?;
+    con.register_scalar_function::<gzip::WarcGzip>("warc_gzip")?;
+    con.register_scalar_function::<gzip::WarcGunzip>("warc_gunzip")?;
+    con.register_table_function::<read_warc::ReadWarc>("read_warc")?;
+    con.register_table_function::<read_warc_members::ReadWarcMembers>("read_warc_members")?;
+    con.register_table_function::<read_warc_types::ReadWarcTypes>("read_warc_types")?;
+    con.register_table_function::<parse_warc_rows::ParseWarcRows>("parse_warc_rows")?;
+    Ok(())
+}
+
+/// Entry points for the benchmarks in `benches/`, which link the rlib
+///
+/// Not part of the extension's interface; results are opaque so that only
+/// their cost can be observed.
+#[doc(hidden)]
+pub mod bench {
+    use super::*;
+
+    /// `parse_warc` of one row, gzip decompressed as the record is read
+    pub fn parse_raw(raw_data: &[u8]) -> impl Sized {
+        parse_raw_warc_record(raw_data, &ParseOptions::default())
+    }
+
+    /// `parse_warc` of one row already decompressed
+    pub fn parse(data: &[u8]) -> impl Sized {
+        parse_warc_record(data, &ParseOptions::default())
+    }
+
+    /// The header fields read by the metadata-only scalars
+    pub fn parse_meta(data: &[u8]) -> impl Sized {
+        meta::parse_record_meta(data)
+    }
+
+    /// `f` on a row decompressed into the thread's reused buffer
+    pub fn with_decompressed<T>(raw_data: &[u8], f: impl FnOnce(&[u8]) -> T) -> T {
+        super::with_decompressed(raw_data, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn load_example_warc() -> Vec<u8> {
+        fs::read("test-data/example.warc").expect("Failed to read test-data/example.warc")
+    }
+
+    /// Read the first WARC record as the `warc` crate does, without repairs
+    fn read_first_record(data: &[u8]) -> Result<warc_rs::Record<warc_rs::BufferedBody>, WarcParseError> {
+        match WarcReader::new(data).iter_records().next() {
+            Some(Ok(r)) => Ok(r),
+            Some(Err(e)) => Err(WarcParseError::WarcError(e.to_string())),
+            None => Err(WarcParseError::NoRecords),
+        }
+    }
+
+    #[test]
+    fn test_parse_warc_record_basic() {
+        let data = load_example_warc();
+        let result = parse_warc_record(&data, &ParseOptions::default());
+        assert!(result.is_ok());
+
+        let record = result.unwrap();
+        assert_eq!(record.warc_version.as_deref(), Some("1.0"));
+        assert_eq!(record.http_status, Some(200));
+        assert_eq!(record.http_version, Some("HTTP/1.1".to_string()));
+        assert!(record.http_body.is_some());
+        let body = String::from_utf8_lossy(record.http_body.as_ref().unwrap());
+        assert!(body.contains("Example Domain"));
+    }
+
+    #[test]
+    fn test_parse_warc_headers_json() {
+        let data = load_example_warc();
+        let result = parse_warc_record(&data, &ParseOptions::default()).unwrap();
+
+        // Check WARC headers contain expected fields
+        assert!(result.warc_headers.as_deref().unwrap().contains("\"WARC-Type\": \"response\""));
+        assert!(result.warc_headers.as_deref().unwrap().contains("\"WARC-Target-URI\": \"http://www.example.com/\""));
+        assert!(result.warc_headers.as_deref().unwrap().contains("\"WARC-IP-Address\": \"2.18.67.69\""));
+
+        // All values are strings, Content-Length included
+        let headers: serde_json::Value = serde_json::from_str(result.warc_headers.as_deref().unwrap()).unwrap();
+        let headers = headers.as_object().unwrap();
+        assert!(headers.values().all(serde_json::Value::is_string));
+        assert_eq!(headers["Content-Length"], "885");
+    }
+
+    #[test]
+    fn test_header_allowlist() {
+        let options = ParseOptions {
+            header_names: Some(HeaderAllowlist::new(&["WARC-Type", "warc-target-uri", "Content-Type"])),
+            ..Default::default()
+        };
+        let record = parse_warc_record(&load_example_warc(), &options).unwrap();
+        assert_eq!(
+            record.warc_headers.as_deref(),
+            Some(
+                "{\"WARC-Type\": \"response\", \"WARC-Target-URI\": \"http://www.example.com/\", \
+                 \"Content-Type\": \"application/http; msgtype=response\"}"
+            )
+        );
+        // The same list applies to HTTP headers, which keep their lowercase keys
+        assert_eq!(record.http_headers.as_deref(), Some("{\"content-type\": \"text/html\"}"));
+        // Fields parsed from the headers are unaffected
+        assert_eq!(record.http_etag.as_deref(), Some("\"bc2473a18e003bdb249eba5ce893033f:1760028122.592274\""));
+
+        let options = ParseOptions {
+            header_names: Some(HeaderAllowlist::new::<&str>(&[])),
+            ..Default::default()
+        };
+        let record = parse_warc_record(&load_example_warc(), &options).unwrap();
+        assert_eq!(record.warc_headers.as_deref(), Some("{}"));
+        assert_eq!(record.http_headers.as_deref(), Some("{}"));
+    }
+
+    #[test]
+    fn test_parse_warc_protocol_and_cipher_suite() {
+        let data = load_example_warc();
+        let record = parse_warc_record(&data, &ParseOptions::default()).unwrap();
+        assert_eq!(record.warc_protocol, Some("http/1.1".to_string()));
+        assert_eq!(record.warc_cipher_suite, None);
+
+        // WARC 1.1 records repeat WARC-Protocol for each layer
+        let tls = String::from_utf8(data)
+            .unwrap()
+            .replacen(
+                "WARC-Protocol: http/1.1\r\n",
+                "WARC-Protocol: h2\r\nWARC-Protocol: tls/1.3\r\nWARC-Cipher-Suite: TLS_AES_128_GCM_SHA256\r\n",
+                1,
+            );
+        let record = parse_warc_record(tls.as_bytes(), &ParseOptions::default()).unwrap();
+        assert_eq!(record.warc_protocol, Some("h2, tls/1.3".to_string()));
+        assert_eq!(record.warc_cipher_suite, Some("TLS_AES_128_GCM_SHA256".to_string()));
+        assert!(record.warc_headers.as_deref().unwrap().contains("\"WARC-Protocol\": \"h2, tls/1.3\""));
+
+        let record = parse_warc_record(&warc_file::tests::warc_record("request", b""), &ParseOptions::default());
+        assert_eq!(record.unwrap().warc_protocol, None);
+    }
+
+    #[test]
+    fn test_warc_concurrent_to() {
+        let data = String::from_utf8(warc_file::tests::warc_record("metadata", b"fetchTimeMs: 12\r\n"))
+            .unwrap()
+            .replacen(
+                "Content-Length:",
+                "WARC-Concurrent-To: <urn:uuid:11111111-1111-1111-1111-111111111111>\r\n\
+                 warc-concurrent-to: <urn:uuid:22222222-2222-2222-2222-222222222222>\r\nContent-Length:",
+                1,
+            );
+        let record = parse_warc_record(data.as_bytes(), &ParseOptions::default()).unwrap();
+        assert_eq!(
+            record.warc_concurrent_to,
+            [
+                "<urn:uuid:11111111-1111-1111-1111-111111111111>",
+                "<urn:uuid:22222222-2222-2222-2222-222222222222>"
+            ]
+        );
+
+        let record = parse_warc_record(&load_example_warc(), &ParseOptions::default()).unwrap();
+        assert_eq!(record.warc_concurrent_to, ["<urn:uuid:e36bbbed-904f-4ff3-80ce-b4f5bd5ea59f>"]);
+
+        let record = parse_warc_record(&warc_file::tests::warc_record("metadata", b""), &ParseOptions::default());
+        assert!(record.unwrap().warc_concurrent_to.is_empty());
+    }
+
+    #[test]
+    fn test_warc_source_uri_retained() {
+        let data = String::from_utf8(warc_file::tests::warc_record("resource", b"%PDF-1.7"))
+            .unwrap()
+            .replacen(
+                "Content-Length:",
+                "WARC-Source-URI: file:///archive/2019/report.pdf\r\n\
+                 software: warcit 0.4\r\nX-Harvested-By: migration-job-7\r\nContent-Length:",
+                1,
+            );
+        let record = parse_warc_record(data.as_bytes(), &ParseOptions::default()).unwrap();
+        assert_eq!(record.warc_source_uri.as_deref(), Some("file:///archive/2019/report.pdf"));
+
+        let headers: serde_json::Value = serde_json::from_str(record.warc_headers.as_deref().unwrap()).unwrap();
+        assert_eq!(headers["WARC-Source-URI"], "file:///archive/2019/report.pdf");
+        assert_eq!(headers["software"], "warcit 0.4");
+        assert_eq!(headers["X-Harvested-By"], "migration-job-7");
+
+        let record = parse_warc_record(&load_example_warc(), &ParseOptions::default()).unwrap();
+        assert_eq!(record.warc_source_uri, None);
+        assert!(!record.warc_headers.unwrap().contains("WARC-Source-URI"));
+    }
+
+    #[test]
+    fn test_parse_http_headers_lowercase() {
+        let data = load_example_warc();
+        let result = parse_warc_record(&data, &ParseOptions::default()).unwrap();
+        let http_headers = result.http_headers.unwrap();
+
+        // HTTP header keys should be lowercase
+        assert!(http_headers.contains("\"content-type\": \"text/html\""));
+        assert!(http_headers.contains("\"content-length\": \"513\""));
+    }
+
+    #[test]
+    fn test_parse_http_response_basic() {
+        let http_data = b"HTTP/1.1 404 Not Found\r\nContent-Type: text/plain\r\n\r\nNot found";
+        let response = parse_http_response(http_data);
+
+        assert_eq!(response.version, Some("HTTP/1.1".to_string()));
+        assert_eq!(response.status, Some(404));
+        assert!(response.headers.unwrap().contains("\"content-type\": \"text/plain\""));
+        assert_eq!(response.body, Some(&b"Not found"[..]));
+    }
+
+    #[test]
+    fn test_parse_http_response_binary() {
+        // Binary content (PNG header) should be preserved in BLOB
+        let http_data = b"HTTP/1.1 200 OK\r\nContent-Type: image/png\r\n\r\n\x89PNG\r\n\x1a\n";
+        let response = parse_http_response(http_data);
+
+        assert_eq!(response.version, Some("HTTP/1.1".to_string()));
+        assert_eq!(response.status, Some(200));
+        assert!(response.headers.is_some());
+        // Binary body is now preserved (not skipped)
+        assert_eq!(response.body, Some(&b"\x89PNG\r\n\x1a\n"[..]));
+    }
+
+    #[test]
+    fn test_parse_http_response_pdf() {
+        // PDF content should be preserved in BLOB
+        let http_data = b"HTTP/1.1 200 OK\r\nContent-Type: application/pdf\r\n\r\n%PDF-1.4\n%\xe2\xe3\xcf\xd3";
+        let response = parse_http_response(http_data);
+
+        assert_eq!(response.version, Some("HTTP/1.1".to_string()));
+        assert_eq!(response.status, Some(200));
+        assert!(response.headers.unwrap().contains("\"content-type\": \"application/pdf\""));
+        // PDF body preserved with binary data
+        assert!(response.body.is_some());
+        assert!(response.body.unwrap().starts_with(b"%PDF-1.4"));
+    }
+
+    #[test]
+    fn test_parse_http_response_without_separator() {
+        let http_data = b"HTTP/1.1 301 Moved Permanently\r\nLocation: https://example.com/\r\nContent-Length: 0\r\n";
+        let response = parse_http_response(http_data);
+
+        assert_eq!(response.status, Some(301));
+        let headers = response.headers.unwrap();
+        assert!(headers.contains("\"location\": \"https://example.com/\""));
+        assert!(headers.contains("\"content-length\": \"0\""));
+        assert_eq!(response.body, Some(&b""[..]));
+
+        // No trailing newline at all
+        let response = parse_http_response(b"HTTP/1.1 204 No Content\r\nServer: test");
+        assert_eq!(response.status, Some(204));
+        assert!(response.headers.unwrap().contains("\"server\": \"test\""));
+        assert_eq!(response.body, Some(&b""[..]));
+    }
+
+    #[test]
+    fn test_parse_http_response_crlf_in_header_value() {
+        // A quoted value carrying a raw CRLF pair: the blank line follows the
+        // tail of the value, not a header, so the head continues past it
+        let http_data = b"HTTP/1.1 200 OK\r\nX-Note: \"one\r\ntwo\"\r\n\r\nContent-Type: text/html\r\n\r\n<html></html>";
+        let response = parse_http_response(http_data);
+
+        assert_eq!(response.status, Some(200));
+        assert_eq!(response.content_type.as_deref(), Some("text/html"));
+        assert_eq!(response.body, Some(&b"<html></html>"[..]));
+
+        // Without any header-like line before a blank line, the first one still splits
+        let (head, body) = split_http_head(b"HTTP/1.1 200 OK\r\nbroken\r\n\r\nbody");
+        assert_eq!(head, b"HTTP/1.1 200 OK\r\nbroken");
+        assert_eq!(body, b"body");
+    }
+
+    #[test]
+    fn test_parse_http_response_without_reason_phrase() {
+        for message in [
+            &b"HTTP/1.1 200\r\nContent-Type: text/plain\r\n\r\nok"[..],
+            b"HTTP/1.1 200\nContent-Type: text/plain\n\nok",
+            // Trailing whitespace or a stray CR after the code
+            b"HTTP/1.1 200 \r\nContent-Type: text/plain\r\n\r\nok",
+            b"HTTP/1.1 200\t\r\nContent-Type: text/plain\r\n\r\nok",
+            b"HTTP/1.1 200\r\r\nContent-Type: text/plain\r\n\r\nok",
+        ] {
+            let response = parse_http_response(message);
+            assert_eq!(response.status, Some(200), "{:?}", String::from_utf8_lossy(message));
+            assert_eq!(response.version.as_deref(), Some("HTTP/1.1"));
+            assert_eq!(response.content_type.as_deref(), Some("text/plain"));
+            assert_eq!(response.body, Some(&b"ok"[..]));
+        }
+        assert_eq!(parse_http_response(b"HTTP/1.1 200\r\n\r\n").status_line.as_deref(), Some("HTTP/1.1 200"));
+    }
+
+    #[test]
+    fn test_parse_http_response_skips_informational() {
+        let http_data = b"HTTP/1.1 103 Early Hints\r\nLink: </style.css>; rel=preload; as=style\r\n\r\n\
+                          HTTP/1.1 200 OK\r\nContent-Type: text/html\r\n\r\n<html></html>";
+        let response = parse_http_response(http_data);
+
+        assert_eq!(response.status, Some(200));
+        assert_eq!(response.status_line.as_deref(), Some("HTTP/1.1 200 OK"));
+        assert!(!response.headers.unwrap().contains("link"));
+        assert_eq!(response.body, Some(&b"<html></html>"[..]));
+
+        // 100 Continue before the final status, and a lone 1xx is kept as-is
+        let response = parse_http_response(b"HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 201 Created\r\n\r\n");
+        assert_eq!(response.status, Some(201));
+        let response = parse_http_response(b"HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\n\r\n\x81\x05hello");
+        assert_eq!(response.status, Some(101));
+    }
+
+    #[test]
+    fn test_parse_http_response_repeated_headers_joined() {
+        let http_data = b"HTTP/1.1 200 OK\r\nAccept: text/html\r\nSet-Cookie: a=1; Expires=Wed, 21 Oct 2015 07:28:00 GMT\r\n\
+                          accept: application/json\r\nSet-Cookie: b=2\r\n\r\n";
+        let response = parse_http_response(http_data);
+        let headers: serde_json::Value = serde_json::from_str(&response.headers.unwrap()).unwrap();
+        let headers = headers.as_object().unwrap();
+
+        // One key per header name
+        assert_eq!(headers.len(), 2);
+        assert_eq!(headers["accept"], "text/html, application/json");
+        assert_eq!(headers["set-cookie"], "a=1; Expires=Wed, 21 Oct 2015 07:28:00 GMT\nb=2");
+    }
+
+    #[test]
+    fn test_parse_http_response_not_http() {
         let data = b"Not HTTP data";
-        let (version, status, headers, body) = parse_http_response(data);
+        let response = parse_http_response(data);
+
+        assert!(response.version.is_none());
+        assert!(response.status.is_none());
+        assert!(response.headers.is_none());
+        assert!(response.body.is_none());
+    }
+
+    #[test]
+    fn test_parse_http_status_normalization() {
+        assert_eq!(parse_http_status("200"), Some(200));
+        assert_eq!(parse_http_status("+200"), Some(200));
+        assert_eq!(parse_http_status("0200"), Some(200));
+        assert_eq!(parse_http_status("-200"), None);
+        assert_eq!(parse_http_status("++200"), None);
+        assert_eq!(parse_http_status("99"), None);
+        assert_eq!(parse_http_status("600"), None);
+        assert_eq!(parse_http_status("999999999999"), None);
+        assert_eq!(parse_http_status("+"), None);
+        assert_eq!(parse_http_status("2OO"), None);
+    }
+
+    #[test]
+    fn test_parse_http_response_invalid_status_keeps_line() {
+        let http_data = b"HTTP/1.1 999999999999 Weird\r\nContent-Type: text/plain\r\n\r\nbody";
+        let response = parse_http_response(http_data);
+
+        assert_eq!(response.status, None);
+        assert_eq!(response.status_line, Some("HTTP/1.1 999999999999 Weird".to_string()));
+        assert_eq!(response.body, Some(&b"body"[..]));
+
+        let response = parse_http_response(b"HTTP/1.1 +204 No Content\r\n\r\n");
+        assert_eq!(response.status, Some(204));
+        assert_eq!(response.status_line, Some("HTTP/1.1 +204 No Content".to_string()));
+    }
+
+    #[test]
+    fn test_parse_http_response_malformed_version() {
+        let response = parse_http_response(b"HTTP 200 OK\r\nContent-Type: text/plain\r\n\r\nbody");
+        assert_eq!(response.version.as_deref(), Some("HTTP"));
+        assert_eq!(response.status, Some(200));
+        assert_eq!(response.protocol, None);
+        assert_eq!(response.content_type.as_deref(), Some("text/plain"));
+        assert_eq!(response.body, Some(&b"body"[..]));
+
+        let response = parse_http_response(b"HTTP/1.1  404  Not Found\r\n\r\n");
+        assert_eq!(response.version.as_deref(), Some("HTTP/1.1"));
+        assert_eq!(response.status, Some(404));
+
+        // Parsed as a record rather than reported as a bad HTTP message
+        let data = warc_file::tests::warc_record("response", b"HTTP 200 OK\r\n\r\nhello");
+        let record = parse_warc_record(&data, &ParseOptions::default()).unwrap();
+        assert_eq!(record.parse_error, None);
+        assert_eq!(record.http_version.as_deref(), Some("HTTP"));
+        assert_eq!(record.http_status, Some(200));
+        assert_eq!(record.http_body.as_deref(), Some(&b"hello"[..]));
+    }
+
+    #[test]
+    fn test_parse_http_response_leading_blank_line() {
+        let response = parse_http_response(b"\r\nHTTP/1.1 200 OK\r\nContent-Type: text/html\r\n\r\n<p>hi</p>");
+        assert_eq!(response.status_line.as_deref(), Some("HTTP/1.1 200 OK"));
+        assert_eq!(response.status, Some(200));
+        assert_eq!(response.content_type.as_deref(), Some("text/html"));
+        assert_eq!(response.body, Some(&b"<p>hi</p>"[..]));
+        // The payload offset still counts from the start of the block
+        assert_eq!(response.payload_offset, Some(46));
+
+        let data = warc_file::tests::warc_record("response", b"\n\r\nHTTP/1.0 404 Not Found\r\n\r\n");
+        let record = parse_warc_record(&data, &ParseOptions::default()).unwrap();
+        assert_eq!(record.parse_error, None);
+        assert_eq!(record.http_status, Some(404));
+    }
+
+    #[test]
+    fn test_parse_http_response_without_status_line() {
+        let block = b"Content-Type: text/html\r\nServer: Apache\r\n\r\n<p>hi</p>";
+        let response = parse_http_response(block);
+        assert_eq!((response.version, response.status, response.status_line), (None, None, None));
+        assert_eq!(response.content_type.as_deref(), Some("text/html"));
+        assert_eq!(response.body, Some(&b"<p>hi</p>"[..]));
+        assert_eq!(response.payload_offset, Some(block.len() - 9));
+
+        // Headers are parsed, the missing status line is still reported
+        let data = warc_file::tests::warc_record("response", block);
+        let record = parse_warc_record(&data, &ParseOptions::default()).unwrap();
+        assert_eq!(record.parse_error.as_deref(), Some("invalid HTTP message: no HTTP status line"));
+        assert_eq!((record.http_version, record.http_status), (None, None));
+        assert_eq!(record.http_headers.as_deref(), Some("{\"content-type\": \"text/html\", \"server\": \"Apache\"}"));
+        assert_eq!(record.http_body.as_deref(), Some(&b"<p>hi</p>"[..]));
+
+        // A header dump with no blank line, and text that isn't a header
+        let response = parse_http_response(b"Content-Length: 0\r\nConnection: close\r\n");
+        assert_eq!(response.content_length, Some(0));
+        assert_eq!(response.body, Some(&b""[..]));
+        assert!(parse_http_response(b"220 ftp.example.com ready\r\nName: x\r\n").body.is_none());
+        assert!(parse_http_response(b"<html>: no\r\n").body.is_none());
+    }
+
+    #[test]
+    fn test_normalize_http_protocol() {
+        assert_eq!(normalize_http_protocol("HTTP/1.0"), Some("HTTP/1.0"));
+        assert_eq!(normalize_http_protocol("HTTP/1.1"), Some("HTTP/1.1"));
+        assert_eq!(normalize_http_protocol("http/1.1"), Some("HTTP/1.1"));
+        assert_eq!(normalize_http_protocol("HTTP/2"), Some("HTTP/2"));
+        assert_eq!(normalize_http_protocol("HTTP/2.0"), Some("HTTP/2"));
+        assert_eq!(normalize_http_protocol("h2"), Some("HTTP/2"));
+        assert_eq!(normalize_http_protocol("HTTP/3.0"), Some("HTTP/3"));
+        assert_eq!(normalize_http_protocol("HTTP/9.9"), None);
+    }
+
+    #[test]
+    fn test_parse_http_response_protocol() {
+        let response = parse_http_response(b"HTTP/2.0 200 OK\r\ncontent-type: text/html\r\n\r\n");
+        assert_eq!(response.version, Some("HTTP/2.0".to_string()));
+        assert_eq!(response.protocol, Some("HTTP/2"));
+
+        // Unknown version token, but h2 pseudo-headers give the protocol away
+        let response = parse_http_response(b"HTTP/x 200\r\n:status: 200\r\ncontent-type: text/html\r\n\r\n");
+        assert_eq!(response.protocol, Some("HTTP/2"));
+        assert!(!response.headers.unwrap().contains("status"));
+
+        let data = load_example_warc();
+        let record = parse_warc_record(&data, &ParseOptions::default()).unwrap();
+        assert_eq!(record.http_protocol, Some("HTTP/1.1"));
+    }
+
+    #[test]
+    fn test_parse_http_response_last_modified_and_etag() {
+        let response = parse_http_response(
+            b"HTTP/1.1 200 OK\r\nLast-Modified: Sun, 06 Nov 1994 08:49:37 GMT\r\nETag: W/\"0815\"\r\n\r\n",
+        );
+        assert_eq!(response.last_modified, Some(784_111_777_000_000));
+        assert_eq!(response.etag, Some("W/\"0815\"".to_string()));
+
+        // Not an HTTP-date: NULL rather than a guess
+        let response = parse_http_response(b"HTTP/1.1 200 OK\r\nLast-Modified: 1994-11-06T08:49:37Z\r\n\r\n");
+        assert_eq!(response.last_modified, None);
+        assert_eq!(response.etag, None);
+    }
+
+    #[test]
+    fn test_http_retry_after_and_age() {
+        let parse = |head: &str| {
+            let block = format!("HTTP/1.1 503 Service Unavailable\r\n{}\r\n", head);
+            let data = warc_file::tests::warc_record("response", block.as_bytes());
+            let record = parse_warc_record(&data, &ParseOptions::default()).unwrap();
+            (record.http_retry_after, record.http_age)
+        };
+
+        // delay-seconds
+        assert_eq!(parse("Retry-After: 120\r\nAge: 30\r\n"), (Some(120), Some(30)));
+        // An HTTP-date counts from the response's Date
+        assert_eq!(
+            parse("Date: Sun, 06 Nov 1994 08:49:37 GMT\r\nRetry-After: Sun, 06 Nov 1994 09:49:37 GMT\r\n"),
+            (Some(3600), None)
+        );
+        assert_eq!(
+            parse("Date: Sun, 06 Nov 1994 08:49:37 GMT\r\nRetry-After: Sun, 06 Nov 1994 08:00:00 GMT\r\n"),
+            (Some(0), None)
+        );
+        // Else from the capture's WARC-Date
+        let data = String::from_utf8(warc_file::tests::warc_record(
+            "response",
+            b"HTTP/1.1 429 Too Many Requests\r\nRetry-After: Sun, 06 Nov 1994 08:50:37 GMT\r\n\r\n",
+        ))
+        .unwrap();
+        let date_line = data.lines().find(|line| line.starts_with("WARC-Date:")).unwrap();
+        let data = data.replacen(date_line, "WARC-Date: 1994-11-06T08:49:37Z", 1);
+        let record = parse_warc_record(data.as_bytes(), &ParseOptions::default()).unwrap();
+        assert_eq!(record.http_retry_after, Some(60));
+
+        // Malformed values are NULL; huge ones are capped
+        assert_eq!(parse("Retry-After: soon\r\nAge: -5\r\n"), (None, None));
+        assert_eq!(parse("Age: 99999999999\r\n"), (None, Some(i32::MAX)));
+        assert_eq!(parse(""), (None, None));
+    }
+
+    #[test]
+    fn test_http_vary() {
+        let vary = |head: &str| {
+            let data = warc_file::tests::warc_record("response", format!("HTTP/1.1 200 OK\r\n{}\r\n", head).as_bytes());
+            parse_warc_record(&data, &ParseOptions::default()).unwrap().http_vary
+        };
+        assert_eq!(vary("Vary: Accept-Encoding, User-Agent\r\n"), ["Accept-Encoding", "User-Agent"]);
+        // Repeated headers add up, empty elements are dropped
+        assert_eq!(vary("Vary: Origin\r\nvary: ,Accept,\r\n"), ["Origin", "Accept"]);
+        assert!(vary("").is_empty());
+        let record = parse_warc_record(&load_example_warc(), &ParseOptions::default()).unwrap();
+        assert_eq!(record.http_vary, ["Accept-Encoding"]);
+    }
+
+    #[test]
+    fn test_keep_alive_defaults() {
+        let parse = |warc_type: &str, head: &str| {
+            let data = warc_file::tests::warc_record(warc_type, format!("{}\r\n", head).as_bytes());
+            let record = parse_warc_record(&data, &ParseOptions::default()).unwrap();
+            (record.http_connection, record.keep_alive)
+        };
+
+        // HTTP/1.1 persists unless told to close
+        assert_eq!(parse("response", "HTTP/1.1 200 OK\r\n"), (None, Some(true)));
+        assert_eq!(
+            parse("response", "HTTP/1.1 200 OK\r\nConnection: Close\r\n"),
+            (Some("Close".to_string()), Some(false))
+        );
+        // HTTP/1.0 closes unless asked to keep alive
+        assert_eq!(parse("response", "HTTP/1.0 200 OK\r\n"), (None, Some(false)));
+        assert_eq!(
+            parse("response", "HTTP/1.0 200 OK\r\nConnection: Keep-Alive\r\n"),
+            (Some("Keep-Alive".to_string()), Some(true))
+        );
+        assert_eq!(
+            parse("request", "GET / HTTP/1.0\r\nConnection: keep-alive, Upgrade\r\n"),
+            (Some("keep-alive, Upgrade".to_string()), Some(true))
+        );
+        assert_eq!(parse("request", "GET / HTTP/1.1\r\nConnection: close\r\n").1, Some(false));
+
+        assert_eq!(keep_alive(Some("HTTP/2"), None), Some(true));
+        assert_eq!(keep_alive(None, Some("keep-alive")), None);
+        assert_eq!(parse("warcinfo", "software: test\r\n"), (None, None));
+    }
+
+    #[test]
+    fn test_parse_warc_repairs_header_line_endings() {
+        let data = load_example_warc();
+        let header_end = data.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+        let (header_block, rest) = data.split_at(header_end);
+
+        // Windows text-mode double CR, with one bare LF line mixed in
+        let mut mangled = String::from_utf8(header_block.to_vec())
+            .unwrap()
+            .replace("\r\n", "\r\r\n")
+            .into_bytes();
+        let first_line_end = mangled.iter().position(|&b| b == b'\n').unwrap();
+        mangled.drain(first_line_end - 2..first_line_end);
+        mangled.extend_from_slice(rest);
+        assert!(read_first_record(&mangled).is_err());
+
+        let record = parse_warc_record(&mangled, &ParseOptions::default()).unwrap();
+        assert_eq!(record.warc_version.as_deref(), Some("1.0"));
+        assert_eq!(record.http_status, Some(200));
+        assert!(record.warc_headers.as_deref().unwrap().contains("\"WARC-Target-URI\": \"http://www.example.com/\""));
+        // Body bytes are untouched by the repair
+        assert!(String::from_utf8_lossy(record.http_body.as_ref().unwrap()).contains("Example Domain"));
+    }
+
+    #[test]
+    fn test_parse_warc_lf_only_header_block() {
+        // Bare LF lines in the body must survive the repair as they are
+        let block = b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\n\r\nline one\nline two\n";
+        let data = warc_file::tests::warc_record("response", block);
+        let header_end = data.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+        let header_block = String::from_utf8(data[..header_end].to_vec()).unwrap().replace("\r\n", "\n");
+        let lf_only = [header_block.as_bytes(), &data[header_end..]].concat();
+        assert!(read_first_record(&lf_only).is_err());
+
+        let expected = parse_warc_record(&data, &ParseOptions::default()).unwrap();
+        let record = parse_warc_record(&lf_only, &ParseOptions::default()).unwrap();
+        assert_eq!(record.http_body.as_deref(), Some(&b"line one\nline two\n"[..]));
+        assert_eq!(record.warc_headers, expected.warc_headers);
+        assert_eq!(record.parse_error, None);
+
+        // Streamed from gzip, and read from a file of such records
+        let gzipped = parse_raw_warc_record(&gzip::gzip_compress(&lf_only).unwrap(), &ParseOptions::default());
+        assert_eq!(gzipped.unwrap().http_body, record.http_body);
+        let file = warc_file::WarcFile::from_reader(io::Cursor::new([lf_only.clone(), lf_only].concat())).unwrap();
+        let records: Vec<Vec<u8>> = warc_file::Records::new(file).map(|r| r.unwrap()).collect();
+        assert_eq!(records.len(), 2);
+        for data in records {
+            assert_eq!(parse_warc_record(&data, &ParseOptions::default()).unwrap().http_body, record.http_body);
+        }
+    }
+
+    #[test]
+    fn test_repair_warc_header_block_noop_when_valid() {
+        let data = load_example_warc();
+        assert!(repair_warc_header_block(&data).is_none());
+        assert!(repair_warc_header_block(b"not a warc").is_none());
+    }
+
+    #[test]
+    fn test_record_age_days() {
+        // The example record is dated 2025-11-06T20:10:40Z
+        let data = load_example_warc();
+        let warc_date = http_date::parse_warc_date("2025-11-06T20:10:40Z").unwrap();
+        assert_eq!(record_age_days(&data, warc_date), Some(0.0));
+        let later = http_date::parse_warc_date("2025-11-16T08:10:40Z").unwrap();
+        assert_eq!(record_age_days(&data, later), Some(9.5));
+        assert_eq!(record_age_days(&data, warc_date - 86_400_000_000), Some(-1.0));
+
+        // Against the real clock, within a tolerance
+        let age = record_age_days(&data, now_micros()).unwrap();
+        let expected = (now_micros() - warc_date) as f64 / MICROS_PER_DAY;
+        assert!((age - expected).abs() < 1.0 / 24.0, "{} vs {}", age, expected);
+
+        let undated = String::from_utf8(data).unwrap().replace("WARC-Date: 2025-11-06T20:10:40Z\r\n", "");
+        assert_eq!(record_age_days(undated.as_bytes(), later), None);
+        assert_eq!(record_age_days(b"not a record", later), None);
+    }
+
+    #[test]
+    fn test_record_id_uuid() {
+        let data = load_example_warc();
+        let record = read_first_record_lenient(&data).unwrap();
+        let uuid = record_id_uuid(&record.header(WarcHeader::RecordID).unwrap());
+        assert_eq!(uuid, Some(0xec056bcb_85c6_411e_991b_0f5246b9d411));
+
+        assert_eq!(
+            record_id_uuid("<URN:UUID:EC056BCB-85C6-411E-991B-0F5246B9D411>"),
+            Some(0xec056bcb_85c6_411e_991b_0f5246b9d411)
+        );
+        assert_eq!(record_id_uuid("<urn:sha1:JUWMXAQNHPTRTHYQWT3EJILYCL7YC3PQ>"), None);
+        assert_eq!(record_id_uuid("<urn:uuid:not-a-uuid>"), None);
+    }
+
+    #[test]
+    fn test_http_raw_round_trips_payload() {
+        // Mixed-case headers, odd spacing and a binary body must all survive
+        let payload = b"HTTP/1.1 200 OK\r\nContent-TYPE:  text/html \r\nX-Odd: a\\b\r\n\r\n<p>\x00\xff</p>";
+        let record = warc_file::tests::warc_record("response", payload);
+        assert_eq!(http_raw(&record).unwrap(), payload);
+
+        let request = warc_file::tests::warc_record("request", b"GET / HTTP/1.1\r\n\r\n");
+        assert_eq!(http_raw(&request), None);
+    }
+
+    #[test]
+    fn test_parse_warc_post_request_body() {
+        let payload = b"{\"query\": \"warc\", \"page\": 2}";
+        let block = [
+            format!(
+                "POST /api/search HTTP/1.1\r\nHost: example.com\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n",
+                payload.len()
+            )
+            .as_bytes(),
+            payload,
+        ]
+        .concat();
+        let data = warc_file::tests::warc_record("request", &block);
+
+        let record = parse_warc_record(&data, &ParseOptions::default()).unwrap();
+        assert_eq!(record.http_method, Some("POST".to_string()));
+        assert_eq!(record.http_version, Some("HTTP/1.1".to_string()));
+        assert_eq!(record.http_protocol, Some("HTTP/1.1"));
+        assert_eq!(record.http_body.as_deref(), Some(&payload[..]));
+        assert_eq!(record.http_status, None);
+        assert!(record.http_headers.unwrap().contains("\"content-type\": \"application/json\""));
+
+        // A GET has an empty body
+        let data = warc_file::tests::warc_record("request", b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n");
+        let record = parse_warc_record(&data, &ParseOptions::default()).unwrap();
+        assert_eq!(record.http_method, Some("GET".to_string()));
+        assert_eq!(record.http_body, Some(Vec::new()));
+    }
+
+    #[test]
+    fn test_parse_warc_resource_record() {
+        // DNS lookup as recorded by Heritrix
+        let dns = b"20251106201040\r\nexample.com.\t300\tIN\tA\t93.184.215.14\r\n";
+        let mut data = format!(
+            "WARC/1.0\r\nWARC-Type: resource\r\nWARC-Date: 2025-11-06T20:10:40Z\r\n\
+             WARC-Record-ID: <urn:uuid:00000000-0000-0000-0000-000000000001>\r\n\
+             WARC-Target-URI: dns:example.com\r\nContent-Type: text/dns\r\nContent-Length: {}\r\n\r\n",
+            dns.len()
+        )
+        .into_bytes();
+        data.extend_from_slice(dns);
+        data.extend_from_slice(b"\r\n\r\n");
+
+        let record = parse_warc_record(&data, &ParseOptions::default()).unwrap();
+        assert!(record.warc_headers.as_deref().unwrap().contains("\"WARC-Type\": \"resource\""));
+        assert_eq!(record.http_body.as_deref(), Some(&dns[..]));
+        assert_eq!(record.http_status, None);
+        assert_eq!(record.http_headers, None);
+        assert!(!record.skipped);
+
+        // The content-type filter applies to the WARC Content-Type
+        let options = ParseOptions {
+            content_types: Some(ContentTypeFilter::new(&["text/html"])),
+            ..Default::default()
+        };
+        let record = parse_warc_record(&data, &options).unwrap();
+        assert_eq!(record.http_body, None);
+        assert!(record.skipped);
+    }
+
+    #[test]
+    fn test_raw_record_text() {
+        let data = load_example_warc();
+        let expected = std::fs::read_to_string("test-data/example.warc").unwrap();
+        assert_eq!(raw_record_text(&data), expected);
+        assert_eq!(raw_record_text(&gzip::gzip_compress(&data).unwrap()), expected);
+
+        // Binary bytes are replaced rather than failing
+        assert_eq!(raw_record_text(b"WARC/1.0\r\n\xff\x00x"), "WARC/1.0\r\n\u{fffd}x");
+    }
+
+    #[test]
+    fn test_strip_body_round_trip() {
+        let data = load_example_warc();
+        let stripped = strip_body(&data).unwrap();
+        let record = read_first_record(&stripped).unwrap();
+        assert_eq!(record.content_length(), 0);
+        assert!(record.body().is_empty());
+
+        // Every other header line is kept as written
+        let original = read_first_record(&data).unwrap();
+        let header_lines = |record: &[u8]| -> Vec<String> {
+            let text = String::from_utf8_lossy(record);
+            let head = text.split("\r\n\r\n").next().unwrap().to_string();
+            head.lines().filter(|line| !line.starts_with("Content-Length")).map(String::from).collect()
+        };
+        assert_eq!(header_lines(&stripped), header_lines(&data));
+        assert_eq!(record.header(WarcHeader::RecordID), original.header(WarcHeader::RecordID));
+        assert!(String::from_utf8_lossy(&stripped).contains("\r\nContent-Length: 0\r\n"));
+        assert!(stripped.ends_with(b"\r\n\r\n\r\n\r\n"));
+
+        // gzip in, gzip out; not a record, NULL
+        let gzipped = strip_body_raw(&gzip::gzip_compress(&data).unwrap()).unwrap();
+        assert_eq!(gzip::gzip_decompress(&gzipped).unwrap(), stripped);
+        assert_eq!(strip_body_raw(b"HTTP/1.1 200 OK\r\n\r\n"), None);
+    }
+
+    #[test]
+    fn test_warc_header_casing_setting() {
+        let data = load_example_warc();
+
+        // Spec casing by default
+        let record = parse_warc_record(&data, &ParseOptions::default()).unwrap();
+        assert!(record.warc_headers.as_deref().unwrap().contains("\"WARC-Target-URI\": \"http://www.example.com/\""));
+        assert!(record.warc_headers.as_deref().unwrap().contains("\"Content-Length\": "));
+
+        let lowercase = ParseOptions {
+            lowercase_header_names: true,
+            ..Default::default()
+        };
+        let record = parse_warc_record(&data, &lowercase).unwrap();
+        assert!(record.warc_headers.as_deref().unwrap().contains("\"warc-target-uri\": \"http://www.example.com/\""));
+        assert!(record.warc_headers.as_deref().unwrap().contains("\"content-length\": "));
+        assert!(!record.warc_headers.as_deref().unwrap().contains("WARC-"));
+    }
+
+    #[test]
+    fn test_source_compression() {
+        let data = load_example_warc();
+        let gzipped = gzip::gzip_compress(&data).unwrap();
+
+        let record = parse_raw_warc_record(&gzipped, &ParseOptions::default()).unwrap();
+        assert_eq!(record.source_compression, Some("gzip"));
+        assert_eq!(record.http_status, Some(200));
+
+        let record = parse_raw_warc_record(&data, &ParseOptions::default()).unwrap();
+        assert_eq!(record.source_compression, Some("none"));
+    }
+
+    #[test]
+    fn test_body_preview() {
+        let data = load_example_warc();
+
+        let record = parse_warc_record(&data, &ParseOptions::default()).unwrap();
+        let preview = record.body_preview.unwrap();
+        assert!(preview.starts_with("<!doctype html><html lang=\"en\"><head><title>Example Domain</title>"));
+        assert_eq!(preview.len(), DEFAULT_PREVIEW_LENGTH);
+
+        // Kept for skipped bodies, with a custom length
+        let options = ParseOptions {
+            content_types: Some(ContentTypeFilter::new(&["image/*"])),
+            preview_length: 15,
+            ..Default::default()
+        };
+        let record = parse_warc_record(&data, &options).unwrap();
+        assert!(record.skipped);
+        assert_eq!(record.body_preview.as_deref(), Some("<!doctype html>"));
+
+        // A cut multi-byte character is replaced rather than failing
+        assert_eq!(body_preview("héllo".as_bytes(), 2), "h\u{fffd}");
+        assert_eq!(body_preview(b"short", 256), "short");
+
+        // Previewed after the HTTP codings come off
+        let gzipped = gzip::gzip_compress(b"<html><title>Compressed</title></html>").unwrap();
+        let head = b"HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Encoding: gzip\r\n\r\n";
+        let data = warc_file::tests::warc_record("response", &[&head[..], &gzipped].concat());
+        let record = parse_warc_record(&data, &ParseOptions::default()).unwrap();
+        assert_eq!(record.body_preview.as_deref(), Some("<html><title>Compressed</title></html>"));
+
+        // A body left coded has none
+        let options = ParseOptions {
+            decode_body: false,
+            ..Default::default()
+        };
+        assert_eq!(parse_warc_record(&data, &options).unwrap().body_preview, None);
+    }
+
+    #[test]
+    fn test_parse_raw_warc_record_empty_input() {
+        let error = parse_raw_warc_record(b"", &ParseOptions::default()).err().unwrap();
+        assert_eq!(error, WarcParseError::EmptyInput);
+
+        // As reported by parse_warc
+        let record = ParsedRecord::from_error(error);
+        assert_eq!(record.parse_error, Some("empty input".to_string()));
+        assert_eq!(record.warc_version, None);
+        assert_eq!(record.warc_headers, None);
+        assert_eq!(record.http_body, None);
+
+        // Unparseable bytes are a different failure than an empty input
+        assert_eq!(
+            parse_raw_warc_record(b"garbage", &ParseOptions::default()).err(),
+            Some(WarcParseError::NoRecords)
+        );
+    }
+
+    #[test]
+    fn test_to_hex() {
+        let data = warc_file::tests::warc_record("response", b"HTTP/1.1 200 OK\r\n\r\nHi!\x00\xff\n");
+        let record = parse_warc_record(&data, &ParseOptions::default()).unwrap();
+        assert_eq!(to_hex(&record.http_body.unwrap()), "48692100ff0a");
+        assert_eq!(to_hex(b""), "");
+    }
+
+    #[test]
+    fn test_body_sha256() {
+        let data = load_example_warc();
+
+        // Hashing is opt-in
+        let record = parse_warc_record(&data, &ParseOptions::default()).unwrap();
+        assert_eq!(record.body_sha256, None);
+
+        let options = ParseOptions {
+            hash_body: true,
+            ..Default::default()
+        };
+        let record = parse_warc_record(&data, &options).unwrap();
+        assert_eq!(
+            record.body_sha256.as_deref(),
+            Some("6f5635035f36ad500b4fc4bb7816bb72ef5594e1bcae44fa074c5e988fc4c0fe")
+        );
+
+        // Stable across compression and content-type filtering
+        let options = ParseOptions {
+            hash_body: true,
+            content_types: Some(ContentTypeFilter::new(&["image/*"])),
+            ..Default::default()
+        };
+        let gzipped = gzip::gzip_compress(&data).unwrap();
+        let skipped = parse_raw_warc_record(&gzipped, &options).unwrap();
+        assert!(skipped.skipped);
+        assert_eq!(skipped.body_sha256, record.body_sha256);
+    }
+
+    #[test]
+    fn test_likely_truncated() {
+        let record = parse_warc_record(&load_example_warc(), &ParseOptions::default()).unwrap();
+        assert!(!record.likely_truncated);
+
+        // WARC-Truncated alone
+        let data = warc_file::tests::warc_record("response", b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello");
+        let truncated = String::from_utf8(data.clone())
+            .unwrap()
+            .replacen("\r\n\r\n", "\r\nWARC-Truncated: length\r\n\r\n", 1);
+        assert!(!parse_warc_record(&data, &ParseOptions::default()).unwrap().likely_truncated);
+        assert!(parse_warc_record(truncated.as_bytes(), &ParseOptions::default()).unwrap().likely_truncated);
+
+        // Body shorter than the HTTP Content-Length, in a response and a request
+        let data = warc_file::tests::warc_record("response", b"HTTP/1.1 200 OK\r\nContent-Length: 500\r\n\r\nhello");
+        assert!(parse_warc_record(&data, &ParseOptions::default()).unwrap().likely_truncated);
+        let data = warc_file::tests::warc_record("request", b"POST / HTTP/1.1\r\nContent-Length: 10\r\n\r\na=1");
+        assert!(parse_warc_record(&data, &ParseOptions::default()).unwrap().likely_truncated);
+
+        // A longer body (e.g. chunked framing) is not a truncation
+        assert!(!shorter_than_declared(Some(b"hello world"), Some(5)));
+        assert!(!shorter_than_declared(Some(b"hello"), None));
+    }
+
+    #[test]
+    fn test_warnings() {
+        // Clean records have none, their block digest checked or not
+        let hashed = ParseOptions {
+            hash_body: true,
+            ..Default::default()
+        };
+        assert!(parse_warc_record(&load_example_warc(), &hashed).unwrap().warnings.is_empty());
+        assert!(parse_warc_record(&load_example_warc(), &ParseOptions::default()).unwrap().warnings.is_empty());
+
+        let block = b"HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=klingon\r\nContent-Length: 500\r\n\r\nhello";
+        let data = String::from_utf8(warc_file::tests::warc_record("response", block)).unwrap().replacen(
+            "\r\n\r\n",
+            "\r\nWARC-Truncated: length\r\nWARC-Block-Digest: sha1:AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA\r\n\r\n",
+            1,
+        );
+        assert_eq!(
+            parse_warc_record(data.as_bytes(), &hashed).unwrap().warnings,
+            [
+                "truncated by the crawler (WARC-Truncated: length)",
+                "WARC-Block-Digest mismatch (sha1:AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA)",
+                "HTTP body shorter than its Content-Length (5 of 500 bytes)",
+                "unknown charset \"klingon\"",
+            ]
+        );
+        // Without hashing, the digest isn't checked
+        assert_eq!(parse_warc_record(data.as_bytes(), &ParseOptions::default()).unwrap().warnings.len(), 3);
+
+        // A longer body only counts when it isn't chunked
+        let data = warc_file::tests::warc_record("request", b"POST / HTTP/1.1\r\nContent-Length: 1\r\n\r\na=1");
+        assert_eq!(
+            parse_warc_record(&data, &ParseOptions::default()).unwrap().warnings,
+            ["HTTP body longer than its Content-Length (3 of 1 bytes)"]
+        );
+        assert!(http_warnings(b"5\r\nhello\r\n0\r\n\r\n", Some(5), Some("chunked"), Some("text/plain")).is_empty());
+    }
+
+    #[test]
+    fn test_decode_body() {
+        let html = b"<html><head><title>Page not found</title>\
+                     <meta http-equiv=refresh content=\"0;url=/\"></head></html>";
+        let gzipped = gzip::gzip_compress(html).unwrap();
+        let block = [
+            &b"HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Encoding: gzip\r\n\r\n"[..],
+            &gzipped,
+        ]
+        .concat();
+        let record = warc_file::tests::warc_record("response", &block);
+        let line_end = record.windows(2).position(|w| w == b"\r\n").unwrap() + 2;
+        let target_uri = b"WARC-Target-URI: https://example.com/missing\r\n";
+        let data = [&record[..line_end], target_uri, &record[line_end..]].concat();
+
+        let decoded = parse_warc_record(&data, &ParseOptions::default()).unwrap();
+        assert!(decoded.likely_soft_404);
+        assert_eq!(decoded.meta_refresh_url.as_deref(), Some("https://example.com/"));
+        assert!(decoded.body_encoded);
+        assert_eq!(decoded.http_body.as_deref(), Some(gzipped.as_slice()));
+
+        // Headers only: the body stays gzipped and nothing is read from it
+        let options = ParseOptions {
+            decode_body: false,
+            ..Default::default()
+        };
+        let encoded = parse_warc_record(&data, &options).unwrap();
+        assert_eq!(encoded.http_status, Some(200));
+        assert_eq!(encoded.http_content_type.as_deref(), Some("text/html"));
+        assert_eq!(encoded.http_body.as_deref(), Some(gzipped.as_slice()));
+        assert!(encoded.body_encoded);
+        assert!(!encoded.likely_soft_404);
+        assert_eq!(encoded.meta_refresh_url, None);
+
+        // Transcoding removes the codings; identity is no coding
+        let options = ParseOptions {
+            body_charset: Some(encoding_rs::UTF_8),
+            ..Default::default()
+        };
+        let transcoded = parse_warc_record(&data, &options).unwrap();
+        assert_eq!(transcoded.http_body.as_deref(), Some(&html[..]));
+        assert!(!transcoded.body_encoded);
+        assert!(!content_coding::has_codings(Some("identity"), Some(" identity ")));
+        assert!(content_coding::has_codings(Some("chunked"), None));
+        assert!(!parse_warc_record(&load_example_warc(), &ParseOptions::default()).unwrap().body_encoded);
+    }
+
+    #[test]
+    fn test_meta_refresh_url() {
+        let block = b"HTTP/1.1 200 OK\r\nContent-Type: text/html\r\n\r\n\
+                      <html><head><meta http-equiv=\"refresh\" content=\"0;url=../new/\"></head></html>";
+        let data = String::from_utf8(warc_file::tests::warc_record("response", block))
+            .unwrap()
+            .replacen("\r\n", "\r\nWARC-Target-URI: https://example.com/old/page.html\r\n", 1);
+        let record = parse_warc_record(data.as_bytes(), &ParseOptions::default()).unwrap();
+        assert_eq!(record.meta_refresh_url.as_deref(), Some("https://example.com/new/"));
+        assert_eq!(record.http_status, Some(200));
+
+        // Only HTML responses are looked at
+        let text = String::from_utf8_lossy(block).replace("text/html", "text/plain");
+        let data = warc_file::tests::warc_record("response", text.as_bytes());
+        assert_eq!(parse_warc_record(&data, &ParseOptions::default()).unwrap().meta_refresh_url, None);
+        assert_eq!(parse_warc_record(&load_example_warc(), &ParseOptions::default()).unwrap().meta_refresh_url, None);
+    }
+
+    #[test]
+    fn test_likely_soft_404() {
+        let parse = |block: &[u8]| {
+            let data = warc_file::tests::warc_record("response", block);
+            parse_warc_record(&data, &ParseOptions::default()).unwrap().likely_soft_404
+        };
+        let long_page = format!("<p>{}</p>", "Lorem ipsum dolor sit amet. ".repeat(200));
+
+        assert!(parse(
+            b"HTTP/1.1 200 OK\r\nContent-Type: text/html\r\n\r\n<title>Page Not Found</title><p>Sorry</p>"
+        ));
+        // A long page is only flagged by its title
+        assert!(parse(
+            format!("HTTP/1.1 200 OK\r\nContent-Type: text/html\r\n\r\n<title>Error 404</title>{}", long_page).as_bytes()
+        ));
+        assert!(!parse(
+            format!("HTTP/1.1 200 OK\r\nContent-Type: text/html\r\n\r\n<title>News</title>{}not found", long_page)
+                .as_bytes()
+        ));
+        assert!(parse(b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\n\r\nThe requested URL was not found"));
+
+        // Real 404s, binary bodies and normal pages aren't soft 404s
+        assert!(!parse(b"HTTP/1.1 404 Not Found\r\nContent-Type: text/html\r\n\r\n<title>Not Found</title>"));
+        assert!(!parse(b"HTTP/1.1 200 OK\r\nContent-Type: image/png\r\n\r\n\x89PNG 404 not found"));
+        assert!(!parse_warc_record(&load_example_warc(), &ParseOptions::default()).unwrap().likely_soft_404);
+
+        // A skipped body isn't decoded, so isn't looked at
+        let data = warc_file::tests::warc_record(
+            "response",
+            b"HTTP/1.1 200 OK\r\nContent-Type: text/html\r\n\r\n<title>Page Not Found</title>",
+        );
+        let options = ParseOptions {
+            content_types: Some(ContentTypeFilter::new(&["image/*"])),
+            ..Default::default()
+        };
+        let record = parse_warc_record(&data, &options).unwrap();
+        assert!(record.skipped);
+        assert!(!record.likely_soft_404);
+    }
+
+    #[test]
+    fn test_content_type_mismatch() {
+        let parse = |block: &[u8], identified: Option<&str>| {
+            let mut data = warc_file::tests::warc_record("response", block);
+            if let Some(t) = identified {
+                let at = data.windows(15).position(|w| w == b"Content-Length:").unwrap();
+                data.splice(at..at, format!("WARC-Identified-Payload-Type: {}\r\n", t).into_bytes());
+            }
+            parse_warc_record(&data, &ParseOptions::default()).unwrap().content_type_mismatch
+        };
+
+        // An "HTML page" that is really a JPEG
+        assert!(parse(b"HTTP/1.1 200 OK\r\nContent-Type: text/html\r\n\r\n\xff\xd8\xff\xe0JFIF", None));
+        // The crawler's identification disagreeing with the server
+        assert!(parse(b"HTTP/1.1 200 OK\r\nContent-Type: image/png\r\n\r\n...", Some("application/pdf")));
+        // Sniffed after the content coding comes off
+        let gzipped = gzip::gzip_compress(b"%PDF-1.7").unwrap();
+        let head = b"HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Encoding: gzip\r\n\r\n";
+        let block = [&head[..], &gzipped].concat();
+        assert!(parse(&block, None));
+
+        assert!(!parse(b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\n\r\n<!doctype html>", Some("text/html")));
+        assert!(!parse(b"HTTP/1.1 200 OK\r\nContent-Type: application/octet-stream\r\n\r\n%PDF-1.7", None));
+        assert!(!parse_warc_record(&load_example_warc(), &ParseOptions::default()).unwrap().content_type_mismatch);
+    }
+
+    #[test]
+    fn test_text_body() {
+        // The options `parse_warc_text` parses with
+        let options = ParseOptions {
+            body_charset: Some(encoding_rs::UTF_8),
+            ..Default::default()
+        };
+        let record = parse_warc_record(&load_example_warc(), &options).unwrap();
+        let text = body_text(record.http_body.as_deref().unwrap());
+        assert!(text.starts_with("<!doctype html>"), "{}", text);
+        assert!(text.contains("<title>Example Domain</title>"));
+
+        // Text in another charset is decoded, binary bodies come out lossy
+        let latin1 = b"HTTP/1.1 200 OK\r\nContent-Type: text/plain; charset=iso-8859-1\r\n\r\nCaf\xe9";
+        let record = parse_warc_record(&warc_file::tests::warc_record("response", latin1), &options).unwrap();
+        assert_eq!(body_text(record.http_body.as_deref().unwrap()), "Café");
+        let png = b"HTTP/1.1 200 OK\r\nContent-Type: image/png\r\n\r\n\x89PNG\r\n\x1a\n\x00";
+        let record = parse_warc_record(&warc_file::tests::warc_record("response", png), &options).unwrap();
+        assert_eq!(body_text(record.http_body.as_deref().unwrap()), "\u{fffd}PNG\r\n\x1a\n");
+    }
+
+    #[test]
+    fn test_identified_payload_type() {
+        let record = parse_warc_record(&load_example_warc(), &ParseOptions::default()).unwrap();
+        assert_eq!(record.identified_payload_type.as_deref(), Some("text/html"));
+        assert_eq!(record.http_content_type.as_deref(), Some("text/html"));
+
+        let record = parse_warc_record(&warc_file::tests::warc_record("resource", b"x"), &ParseOptions::default());
+        assert_eq!(record.unwrap().identified_payload_type, None);
+    }
+
+    #[test]
+    fn test_warc_filename() {
+        let data = warc_file::tests::warc_record("warcinfo", b"software: Heritrix/3.4.0\r\nformat: WARC File Format 1.0\r\n");
+        let data = String::from_utf8(data).unwrap().replacen(
+            "WARC-Type: warcinfo\r\n",
+            "WARC-Type: warcinfo\r\nWARC-Filename: CC-MAIN-20251106200718-00970.warc.gz\r\n",
+            1,
+        );
+
+        let record = parse_warc_record(data.as_bytes(), &ParseOptions::default()).unwrap();
+        assert_eq!(record.warc_filename.as_deref(), Some("CC-MAIN-20251106200718-00970.warc.gz"));
+        assert!(record
+            .warc_headers
+            .unwrap()
+            .contains("\"WARC-Filename\": \"CC-MAIN-20251106200718-00970.warc.gz\""));
+
+        let record = parse_warc_record(&load_example_warc(), &ParseOptions::default()).unwrap();
+        assert_eq!(record.warc_filename, None);
+    }
+
+    #[test]
+    fn test_http_content_type() {
+        let record = parse_warc_record(&load_example_warc(), &ParseOptions::default()).unwrap();
+        assert_eq!(record.http_content_type.as_deref(), Some("text/html"));
+        // The WARC-level Content-Type stays in warc_headers
+        assert!(record.warc_headers.unwrap().contains("application/http"));
+
+        let data = warc_file::tests::warc_record(
+            "request",
+            b"POST /api HTTP/1.1\r\nContent-Type: application/json\r\n\r\n{}",
+        );
+        let record = parse_warc_record(&data, &ParseOptions::default()).unwrap();
+        assert_eq!(record.http_content_type.as_deref(), Some("application/json"));
+
+        let data = warc_file::tests::warc_record("metadata", b"fetchTimeMs: 12\r\n");
+        assert_eq!(parse_warc_record(&data, &ParseOptions::default()).unwrap().http_content_type, None);
+    }
+
+    #[test]
+    fn test_warc_date() {
+        let data = warc_file::tests::warc_record("resource", b"x");
+        let record = parse_warc_record(&data, &ParseOptions::default()).unwrap();
+        // 2025-11-06T20:10:40Z
+        assert_eq!(record.warc_date, Some(1_762_459_840_000_000));
+
+        let variant = String::from_utf8(data).unwrap().replace("2025-11-06T20:10:40Z", "20251106201040");
+        let record = parse_warc_record(variant.as_bytes(), &ParseOptions::default()).unwrap();
+        assert_eq!(record.warc_date, Some(1_762_459_840_000_000));
+
+        // The header is normalized before the `warc` crate, which only reads RFC 3339, sees it
+        assert!(record.warc_headers.unwrap().contains("\"WARC-Date\": \"2025-11-06T20:10:40Z\""));
+
+        // The `warc` crate requires a WARC-Date, so one no format matches fails the record
+        let invalid = variant.replace("20251106201040", "6 Nov 2025");
+        assert!(parse_warc_record(invalid.as_bytes(), &ParseOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_sanitized_bytes_removed() {
+        let record = parse_warc_record(&load_example_warc(), &ParseOptions::default()).unwrap();
+        assert_eq!(record.sanitized_bytes_removed, Some(0));
+
+        // Two NULs in an HTTP header value and one in a WARC header; the body's don't count
+        let data = warc_file::tests::warc_record(
+            "response",
+            b"HTTP/1.1 200 OK\r\nX-Tracking: a\x00b\x00c\r\n\r\nbody\x00",
+        );
+        let data = String::from_utf8(data)
+            .unwrap()
+            .replacen("Content-Length:", "WARC-Target-URI: http://example.com/\x00\r\nContent-Length:", 1);
+        let record = parse_warc_record(data.as_bytes(), &ParseOptions::default()).unwrap();
+        assert_eq!(record.sanitized_bytes_removed, Some(3));
+        assert!(record.http_headers.unwrap().contains("\"x-tracking\": \"abc\""));
+    }
+
+    #[test]
+    fn test_http_payload_offset() {
+        let data = load_example_warc();
+        let record = parse_warc_record(&data, &ParseOptions::default()).unwrap();
+        let offset = record.http_payload_offset.unwrap() as usize;
+        let block = read_first_record(&data).unwrap().body().to_vec();
+        assert_eq!(offset, 372);
+        assert!(block[..offset].ends_with(b"\r\n\r\n"));
+        assert_eq!(&block[offset..], record.http_body.unwrap().as_slice());
+
+        // Counted from the start of the block, past interim responses
+        let data = warc_file::tests::warc_record(
+            "response",
+            b"HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 200 OK\r\n\r\nhi",
+        );
+        let record = parse_warc_record(&data, &ParseOptions::default()).unwrap();
+        assert_eq!(record.http_payload_offset, Some(44));
+
+        let data = warc_file::tests::warc_record("request", b"GET / HTTP/1.1\r\n\r\n");
+        assert_eq!(parse_warc_record(&data, &ParseOptions::default()).unwrap().http_payload_offset, None);
+    }
+
+    #[test]
+    fn test_header_bytes() {
+        let data = load_example_warc();
+        let record = parse_warc_record(&data, &ParseOptions::default()).unwrap();
+        // The WARC header block runs to its blank line, the block holds the HTTP headers then the body
+        let warc_header_bytes = data.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+        assert_eq!(record.warc_header_bytes, Some(warc_header_bytes as i32));
+        assert_eq!(record.http_header_bytes, Some(372));
+        assert_eq!(data[warc_header_bytes + 372..].len(), record.http_body.unwrap().len() + 4);
+
+        let data = warc_file::tests::warc_record("request", b"POST / HTTP/1.1\r\nHost: a\r\n\r\nq=1");
+        let record = parse_warc_record(&data, &ParseOptions::default()).unwrap();
+        assert_eq!(record.http_header_bytes, Some(28));
+
+        // Other record types have no HTTP headers
+        let record = parse_warc_record(&warc_file::tests::warc_record("resource", b"x"), &ParseOptions::default());
+        let record = record.unwrap();
+        assert!(record.warc_header_bytes.unwrap() > 0);
+        assert_eq!(record.http_header_bytes, None);
+    }
+
+    #[test]
+    fn test_check_struct_output() {
+        let fields = 44;
+        // A full chunk, as DuckDB sends for large tables, fits exactly
+        assert!(check_struct_output(2048, 2048, fields, fields).is_ok());
+        assert!(check_struct_output(1, 2048, fields, fields).is_ok());
+
+        let error = check_struct_output(2049, 2048, fields, fields).unwrap_err();
+        assert_eq!(error, "chunk of 2049 rows exceeds the output vector capacity of 2048");
+        assert!(check_struct_output(2048, 2048, fields - 1, fields).is_err());
+    }
+
+    #[test]
+    fn test_warcinfo_fields() {
+        let data = warc_file::tests::warc_record(
+            "warcinfo",
+            "software: Heritrix/3.4.0\r\noperator: Zoë Müller <archiv@example.org>\r\n".as_bytes(),
+        );
+        let data = String::from_utf8(data)
+            .unwrap()
+            .replacen("Content-Length:", "Content-Type: application/warc-fields\r\nContent-Length:", 1);
+
+        let record = parse_warc_record(data.as_bytes(), &ParseOptions::default()).unwrap();
+        assert_eq!(
+            record.warc_fields.as_deref(),
+            Some("{\"software\": \"Heritrix/3.4.0\", \"operator\": \"Zoë Müller <archiv@example.org>\"}")
+        );
+        assert_eq!(parse_warc_record(&load_example_warc(), &ParseOptions::default()).unwrap().warc_fields, None);
+    }
+
+    #[test]
+    fn test_metadata_outlinks() {
+        let block = b"via: https://example.com/\r\nhopsFromSeed: L\r\n\
+                      outlink: https://example.com/about L a/@href\r\n\
+                      outlink: https://example.com/logo.png E img/@src\r\n";
+        let data = String::from_utf8(warc_file::tests::warc_record("metadata", block))
+            .unwrap()
+            .replacen("Content-Length:", "Content-Type: application/warc-fields\r\nContent-Length:", 1);
+
+        let record = parse_warc_record(data.as_bytes(), &ParseOptions::default()).unwrap();
+        assert_eq!(
+            record.metadata_outlinks,
+            Some(vec!["https://example.com/about".to_string(), "https://example.com/logo.png".to_string()])
+        );
+        assert_eq!(record.metadata_via.as_deref(), Some("https://example.com/"));
+
+        // warcinfo blocks and responses have no crawler links
+        let warcinfo = data.replacen("WARC-Type: metadata", "WARC-Type: warcinfo", 1);
+        let record = parse_warc_record(warcinfo.as_bytes(), &ParseOptions::default()).unwrap();
+        assert_eq!((record.metadata_outlinks, record.metadata_via), (None, None));
+        assert_eq!(parse_warc_record(&load_example_warc(), &ParseOptions::default()).unwrap().metadata_outlinks, None);
+    }
+
+    #[test]
+    fn test_parse_error_variants() {
+        let options = ParseOptions::default();
+        let error = |data: &[u8]| parse_raw_warc_record(data, &options).err();
+
+        // gzip magic bytes followed by garbage
+        assert_eq!(error(b"\x1f\x8bnot gzip"), Some(WarcParseError::DecompressFailed));
+        assert_eq!(error(b"HTTP/1.1 200 OK\r\n\r\n"), Some(WarcParseError::NoRecords));
+        assert!(matches!(error(b"WARC/1.0\r\nno colon\r\n\r\n"), Some(WarcParseError::WarcError(_))));
+
+        let missing_type = String::from_utf8(warc_file::tests::warc_record("response", b"HTTP/1.1 200 OK\r\n\r\n"))
+            .unwrap()
+            .replace("WARC-Type: response\r\n", "");
+        assert!(matches!(error(missing_type.as_bytes()), Some(WarcParseError::WarcError(_))));
+
+        // Partial parses keep the WARC fields and report the error
+        let not_http = warc_file::tests::warc_record("response", b"220 ftp.example.com FTP server ready\r\n");
+        let record = parse_warc_record(&not_http, &options).unwrap();
+        assert_eq!(record.parse_error.as_deref(), Some("invalid HTTP message: no HTTP status line"));
+        assert_eq!(record.http_status, None);
+        assert!(record.warc_headers.is_some());
+
+        let not_http = warc_file::tests::warc_record("request", b"\x00\x01binary");
+        let record = parse_warc_record(&not_http, &options).unwrap();
+        assert_eq!(record.parse_error.as_deref(), Some("invalid HTTP message: no HTTP request line"));
+
+        assert_eq!(parse_warc_record(&load_example_warc(), &options).unwrap().parse_error, None);
+    }
 
-        assert!(version.is_none());
-        assert!(status.is_none());
-        assert!(headers.is_none());
-        assert!(body.is_none());
+    #[test]
+    fn test_warc_version_numbers() {
+        let data = load_example_warc();
+        let record = parse_warc_record(&data, &ParseOptions::default()).unwrap();
+        assert_eq!((record.warc_version_major, record.warc_version_minor), (Some(1), Some(0)));
+        let v1_1 = [&b"WARC/1.1"[..], &data[b"WARC/1.0".len()..]].concat();
+        let record = parse_warc_record(&v1_1, &ParseOptions::default()).unwrap();
+        assert_eq!(record.warc_version.as_deref(), Some("1.1"));
+        assert_eq!((record.warc_version_major, record.warc_version_minor), (Some(1), Some(1)));
+
+        assert_eq!(warc_version_numbers("0.18"), (Some(0), Some(18)));
+        assert_eq!(warc_version_numbers("1"), (Some(1), None));
+        assert_eq!(warc_version_numbers("x.y"), (None, None));
+    }
+
+    #[test]
+    fn test_parse_warc_unsupported_version() {
+        let data = load_example_warc();
+        let future = [&b"WARC/2.0"[..], &data[b"WARC/1.0".len()..]].concat();
+
+        let record = parse_warc_record(&future, &ParseOptions::default()).unwrap();
+        assert_eq!(record.warc_version.as_deref(), Some("2.0"));
+        assert_eq!((record.warc_version_major, record.warc_version_minor), (Some(2), Some(0)));
+        assert_eq!(record.parse_error, Some("unsupported WARC version 2.0".to_string()));
+        assert_eq!(record.http_status, None);
+        assert_eq!(record.http_body, None);
+
+        let record = parse_warc_record(&data, &ParseOptions::default()).unwrap();
+        assert_eq!(record.parse_error, None);
+        assert_eq!(record.http_status, Some(200));
+    }
+
+    #[test]
+    fn test_content_type_allow_list() {
+        let data = load_example_warc();
+
+        let html_only = ParseOptions {
+            content_types: Some(ContentTypeFilter::new(&["text/html"])),
+            ..Default::default()
+        };
+        let record = parse_warc_record(&data, &html_only).unwrap();
+        assert!(!record.skipped);
+        assert!(record.http_body.is_some());
+
+        let images_only = ParseOptions {
+            content_types: Some(ContentTypeFilter::new(&["image/*"])),
+            ..Default::default()
+        };
+        let record = parse_warc_record(&data, &images_only).unwrap();
+        assert!(record.skipped);
+        assert!(record.http_body.is_none());
+        // Headers are still returned for skipped records
+        assert_eq!(record.http_status, Some(200));
+        assert!(record.http_headers.is_some());
+    }
+
+    #[test]
+    fn test_content_type_filter_matching() {
+        let filter = ContentTypeFilter::new(&["text/*", "!text/css"]);
+        assert!(filter.allows(Some("text/html; charset=UTF-8")));
+        assert!(filter.allows(Some("TEXT/PLAIN")));
+        assert!(!filter.allows(Some("text/css")));
+        assert!(!filter.allows(Some("image/jpeg")));
+        assert!(!filter.allows(None));
+
+        let deny_only = ContentTypeFilter::new(&["!video/*"]);
+        assert!(deny_only.allows(Some("text/html")));
+        assert!(deny_only.allows(None));
+        assert!(!deny_only.allows(Some("video/mp4")));
+    }
+
+    #[test]
+    fn test_sanitize_header_escapes_json() {
+        assert_eq!(sanitize_header(r"C:\path"), r"C:\\path");
+        assert_eq!(sanitize_header("line1\nline2"), r"line1\nline2");
+        assert_eq!(sanitize_header("say \"hi\"\t\x01\0"), r#"say \"hi\"\t\u0001"#);
+
+        let json = format!("{{\"k\": \"{}\"}}", sanitize_header("a\\b\"c\nd\r\x7f"));
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed["k"], "a\\b\"c\nd\r\x7f");
+    }
+
+    #[test]
+    fn test_http_headers_json_valid_with_backslash() {
+        let http_data = b"HTTP/1.1 200 OK\r\nX-Path: C:\\temp\\file\r\nX-Quote: \"quoted\"\r\n\r\n";
+        let response = parse_http_response(http_data);
+        let headers: serde_json::Value = serde_json::from_str(&response.headers.unwrap()).unwrap();
+        assert_eq!(headers["x-path"], "C:\\temp\\file");
+        assert_eq!(headers["x-quote"], "\"quoted\"");
+
+        let data = load_example_warc();
+        let record = parse_warc_record(&data, &ParseOptions::default()).unwrap();
+        assert!(serde_json::from_str::<serde_json::Value>(record.warc_headers.as_deref().unwrap()).is_ok());
+        assert!(serde_json::from_str::<serde_json::Value>(&record.http_headers.unwrap()).is_ok());
     }
 
     #[test]
@@ -425,11 +3821,21 @@ mod tests {
         assert_eq!(result, "helloworld");
     }
 
+    #[test]
+    fn test_sanitize_for_ffi_keeps_unicode() {
+        assert_eq!(sanitize_for_ffi("東京\0🦆 café"), "東京🦆 café");
+
+        // Non-ASCII header values survive parsing and JSON encoding
+        let response = parse_http_response("HTTP/1.1 200 OK\r\nX-Title: 東京の天気 🌤\r\n\r\n".as_bytes());
+        let headers: serde_json::Value = serde_json::from_str(&response.headers.unwrap()).unwrap();
+        assert_eq!(headers["x-title"], "東京の天気 🌤");
+    }
+
     #[test]
     fn test_parse_warc_invalid_data() {
         let invalid = b"This is not a WARC file";
-        let result = parse_warc_record(invalid);
-        assert!(result.is_none());
+        let result = parse_warc_record(invalid, &ParseOptions::default());
+        assert_eq!(result.err(), Some(WarcParseError::NoRecords));
     }
 
     #[test]
@@ -450,8 +3856,116 @@ mod tests {
         let mut decompressed = Vec::new();
         decoder.read_to_end(&mut decompressed).unwrap();
 
-        let result = parse_warc_record(&decompressed);
-        assert!(result.is_some());
+        let result = parse_warc_record(&decompressed, &ParseOptions::default());
+        assert!(result.is_ok());
         assert_eq!(result.unwrap().http_status, Some(200));
     }
+
+    #[test]
+    fn test_with_decompressed_passes_uncompressed_input() {
+        let data = load_example_warc();
+        assert!(with_decompressed(&data, |passed| std::ptr::eq(passed, &data[..])));
+
+        // Magic bytes without a valid stream fall back to the raw bytes
+        let corrupt = [0x1f, 0x8b, 0x08, 0x00, 0xde, 0xad];
+        assert!(with_decompressed(&corrupt, |passed| std::ptr::eq(passed, &corrupt[..])));
+
+        let compressed = gzip::gzip_compress(&data).unwrap();
+        assert_eq!(with_decompressed(&compressed, <[u8]>::to_vec), data);
+    }
+
+    #[test]
+    fn test_with_decompressed_reuses_buffer() {
+        let small = load_example_warc();
+        let large = warc_file::tests::warc_record("response", &[b'x'; 100_000]);
+        let corrupt = vec![0x1f, 0x8b, 0x08, 0x00, 0xde, 0xad];
+        let rows = [
+            (gzip::gzip_compress(&large).unwrap(), &large),
+            (gzip::gzip_compress(&small).unwrap(), &small),
+            (small.clone(), &small),
+            (corrupt.clone(), &corrupt),
+            (gzip::gzip_compress(&large).unwrap(), &large),
+        ];
+        // A smaller row after a larger one doesn't see the larger one's tail
+        for (raw_data, expected) in &rows {
+            assert_eq!(&with_decompressed(raw_data, <[u8]>::to_vec), *expected);
+        }
+
+        // Nested calls don't share the buffer
+        let nested = with_decompressed(&rows[0].0, |outer| {
+            let inner = with_decompressed(&rows[1].0, <[u8]>::to_vec);
+            (outer.to_vec(), inner)
+        });
+        assert_eq!(nested, (large, small));
+    }
+
+    #[test]
+    fn test_streamed_gzip_matches_buffered() {
+        let body = "<p>Streamed through the decoder</p>\n".repeat(50_000);
+        let large = warc_file::tests::warc_record(
+            "response",
+            format!("HTTP/1.1 200 OK\r\nContent-Type: text/html\r\n\r\n{}", body).as_bytes(),
+        );
+        let options = ParseOptions {
+            hash_body: true,
+            ..Default::default()
+        };
+        for data in [load_example_warc(), large] {
+            let gzipped = gzip::gzip_compress(&data).unwrap();
+            let streamed = parse_raw_warc_record(&gzipped, &options).unwrap();
+            // Parsing the decompressed record
+            let buffered = parse_warc_record(&data, &options).unwrap();
+            assert_eq!(
+                streamed,
+                ParsedRecord {
+                    source_compression: Some("gzip"),
+                    ..buffered
+                }
+            );
+        }
+
+        // A member with a bad checksum or cut short still fails to decompress
+        let mut gzipped = gzip::gzip_compress(&load_example_warc()).unwrap();
+        let crc = gzipped.len() - 8;
+        gzipped[crc] ^= 0xff;
+        assert_eq!(parse_raw_warc_record(&gzipped, &options), Err(WarcParseError::DecompressFailed));
+        gzipped.truncate(crc / 2);
+        assert_eq!(parse_raw_warc_record(&gzipped, &options), Err(WarcParseError::DecompressFailed));
+    }
+
+    #[test]
+    fn test_parse_base64_gzip_record() {
+        use base64::engine::{general_purpose::STANDARD, Engine};
+
+        let gzipped = gzip::gzip_compress(&load_example_warc()).unwrap();
+        let encoded = STANDARD.encode(&gzipped);
+        let record = parse_raw_warc_record(&decode_base64(encoded.as_bytes()).unwrap(), &ParseOptions::default());
+        assert_eq!(record, parse_raw_warc_record(&gzipped, &ParseOptions::default()));
+        let record = record.unwrap();
+        assert_eq!(record.source_compression, Some("gzip"));
+        assert_eq!(record.http_status, Some(200));
+
+        // MIME-style line breaks and missing padding are accepted
+        let unpadded = encoded.trim_end_matches('=');
+        let lines: Vec<&str> = unpadded.as_bytes().chunks(76).map(|line| std::str::from_utf8(line).unwrap()).collect();
+        assert_eq!(decode_base64(lines.join("\r\n").as_bytes()).unwrap(), gzipped);
+
+        assert_eq!(decode_base64(b"not base64!"), Err(WarcParseError::InvalidBase64));
+    }
+
+    #[test]
+    fn test_require_compressed() {
+        let options = ParseOptions {
+            require_compressed: true,
+            ..Default::default()
+        };
+        // Plain bytes are an error instead of a record parsed as-is
+        let plain = load_example_warc();
+        assert_eq!(parse_raw_warc_record(&plain, &options), Err(WarcParseError::NotCompressed));
+        assert_eq!(parse_raw_warc_record(&plain, &ParseOptions::default()).unwrap().http_status, Some(200));
+
+        let record = parse_raw_warc_record(&gzip::gzip_compress(&plain).unwrap(), &options).unwrap();
+        assert_eq!(record.source_compression, Some("gzip"));
+        assert_eq!(record.http_status, Some(200));
+    }
 }
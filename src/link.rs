@@ -0,0 +1,219 @@
+use crate::{for_each_blob, sanitize_for_ffi};
+use duckdb::{
+    core::{DataChunkHandle, Inserter, LogicalTypeHandle, LogicalTypeId},
+    vscalar::{ScalarFunctionSignature, VScalar},
+    vtab::arrow::WritableVector,
+};
+use std::error::Error;
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+/// One link of a `Link` header (RFC 8288)
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct Link {
+    pub uri: String,
+    pub rel: Option<String>, // Lowercased; may hold several space-separated relations
+    pub link_type: Option<String>,
+}
+
+/// Parse a `Link` header value into its links, in order
+///
+/// Commas and semicolons inside `<...>` and quoted parameter values don't
+/// split links. Malformed links are skipped.
+pub(crate) fn parse_link_header(value: &str) -> Vec<Link> {
+    let mut links = Vec::new();
+    let mut chars = value.char_indices().peekable();
+    loop {
+        skip_while(&mut chars, |c| c.is_whitespace() || c == ',');
+        let Some((_, c)) = chars.next() else {
+            break;
+        };
+        if c != '<' {
+            skip_while(&mut chars, |c| c != ',');
+            continue;
+        }
+
+        let uri: String = take_while(&mut chars, |c| c != '>');
+        if chars.next().is_none() {
+            break; // Unterminated URI reference
+        }
+        let mut link = Link {
+            uri: uri.trim().to_string(),
+            ..Default::default()
+        };
+
+        // Parameters: `; name=value` or `; name="quoted value"`
+        loop {
+            skip_while(&mut chars, char::is_whitespace);
+            match chars.peek() {
+                Some((_, ';')) => {
+                    chars.next();
+                }
+                _ => break,
+            }
+            let name = take_while(&mut chars, |c| c != '=' && c != ';' && c != ',');
+            let value = match chars.peek() {
+                Some((_, '=')) => {
+                    chars.next();
+                    skip_while(&mut chars, char::is_whitespace);
+                    Some(parameter_value(&mut chars))
+                }
+                _ => None,
+            };
+            let name = name.trim();
+            // Only the first occurrence of a parameter counts (RFC 8288 section 3)
+            if name.eq_ignore_ascii_case("rel") && link.rel.is_none() {
+                link.rel = value.map(|v| v.to_lowercase());
+            } else if name.eq_ignore_ascii_case("type") && link.link_type.is_none() {
+                link.link_type = value;
+            }
+        }
+
+        links.push(link);
+    }
+    links
+}
+
+/// A token or quoted-string parameter value, with backslash escapes resolved
+fn parameter_value(chars: &mut Peekable<CharIndices>) -> String {
+    if !matches!(chars.peek(), Some((_, '"'))) {
+        return take_while(chars, |c| c != ';' && c != ',').trim().to_string();
+    }
+    chars.next();
+    let mut value = String::new();
+    while let Some((_, c)) = chars.next() {
+        match c {
+            '"' => break,
+            '\\' => value.extend(chars.next().map(|(_, c)| c)),
+            c => value.push(c),
+        }
+    }
+    value
+}
+
+fn take_while(chars: &mut Peekable<CharIndices>, f: impl Fn(char) -> bool) -> String {
+    let mut taken = String::new();
+    while let Some(&(_, c)) = chars.peek() {
+        if !f(c) {
+            break;
+        }
+        taken.push(c);
+        chars.next();
+    }
+    taken
+}
+
+fn skip_while(chars: &mut Peekable<CharIndices>, f: impl Fn(char) -> bool) {
+    while chars.next_if(|&(_, c)| f(c)).is_some() {}
+}
+
+/// DuckDB scalar function parsing a `Link` header value
+///
+/// Returns a LIST of STRUCT(uri, rel, type), or NULL for a NULL value.
+pub(crate) struct WarcParseLinkHeader;
+
+impl VScalar for WarcParseLinkHeader {
+    type State = ();
+
+    unsafe fn invoke(
+        _state: &Self::State,
+        input: &mut DataChunkHandle,
+        output: &mut dyn WritableVector,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut rows = Vec::with_capacity(input.len());
+        for_each_blob(input, 0, |_, value| {
+            rows.push(value.map(|value| parse_link_header(&sanitize_for_ffi(&String::from_utf8_lossy(value)))));
+        });
+
+        let mut list_vector = output.list_vector();
+        let total: usize = rows.iter().flatten().map(Vec::len).sum();
+        let link_struct = list_vector.struct_child(total);
+        let uri_vec = link_struct.child(0, total);
+        let mut rel_vec = link_struct.child(1, total);
+        let mut type_vec = link_struct.child(2, total);
+
+        let mut offset = 0;
+        for (i, links) in rows.iter().enumerate() {
+            let Some(links) = links else {
+                list_vector.set_null(i);
+                continue;
+            };
+            list_vector.set_entry(i, offset, links.len());
+            for link in links {
+                uri_vec.insert(offset, link.uri.as_str());
+                match &link.rel {
+                    Some(v) => rel_vec.insert(offset, v.as_str()),
+                    None => rel_vec.set_null(offset),
+                }
+                match &link.link_type {
+                    Some(v) => type_vec.insert(offset, v.as_str()),
+                    None => type_vec.set_null(offset),
+                }
+                offset += 1;
+            }
+        }
+        list_vector.set_len(total);
+
+        Ok(())
+    }
+
+    fn signatures() -> Vec<ScalarFunctionSignature> {
+        vec![ScalarFunctionSignature::exact(
+            vec![LogicalTypeHandle::from(LogicalTypeId::Varchar)],
+            LogicalTypeHandle::list(&LogicalTypeHandle::struct_type(&[
+                ("uri", LogicalTypeHandle::from(LogicalTypeId::Varchar)),
+                ("rel", LogicalTypeHandle::from(LogicalTypeId::Varchar)),
+                ("type", LogicalTypeHandle::from(LogicalTypeId::Varchar)),
+            ])),
+        )]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_link_header_multiple_links() {
+        let links = parse_link_header(
+            "<https://example.com/a,b>; rel=\"canonical\", \
+             </page/2>; rel=next; type=\"text/html; charset=utf-8\", \
+             <https://example.com/fr>; REL=\"Alternate\"; hreflang=fr",
+        );
+        assert_eq!(
+            links,
+            vec![
+                Link {
+                    uri: "https://example.com/a,b".to_string(),
+                    rel: Some("canonical".to_string()),
+                    link_type: None,
+                },
+                Link {
+                    uri: "/page/2".to_string(),
+                    rel: Some("next".to_string()),
+                    link_type: Some("text/html; charset=utf-8".to_string()),
+                },
+                Link {
+                    uri: "https://example.com/fr".to_string(),
+                    rel: Some("alternate".to_string()),
+                    link_type: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_link_header_malformed() {
+        assert_eq!(parse_link_header(""), vec![]);
+        // A link without `<...>` is skipped, the rest still parse
+        let links = parse_link_header("garbage; rel=x, <a>; rel=\"first\"; rel=second, <unterminated");
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].uri, "a");
+        assert_eq!(links[0].rel.as_deref(), Some("first"));
+        // Escaped quote in a quoted value
+        assert_eq!(
+            parse_link_header(r#"<b>; type="a\"b""#)[0].link_type.as_deref(),
+            Some("a\"b")
+        );
+    }
+}
@@ -0,0 +1,106 @@
+use crate::{for_each_blob, sanitize_for_ffi};
+use duckdb::{
+    core::{DataChunkHandle, Inserter, LogicalTypeHandle, LogicalTypeId},
+    vscalar::{ScalarFunctionSignature, VScalar},
+    vtab::arrow::WritableVector,
+};
+use std::error::Error;
+
+/// Elements of a comma-separated HTTP list header (`Vary`, `Accept-*`,
+/// `Cache-Control`, ...), trimmed and as written
+///
+/// Commas inside quoted strings don't split, and empty elements are left out
+/// (RFC 9110 section 5.6.1), so `gzip;q=1.0, , "a,b"` gives `gzip;q=1.0` and
+/// `"a,b"`.
+pub(crate) fn split_list_header(value: &str) -> Vec<String> {
+    let mut elements = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    let mut escaped = false;
+    for (i, c) in value.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                elements.push(&value[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    elements.push(&value[start..]);
+    elements
+        .into_iter()
+        .map(str::trim)
+        .filter(|element| !element.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// DuckDB scalar function splitting a comma-separated HTTP list header value
+///
+/// Returns a LIST(VARCHAR) of its elements, or NULL for a NULL value.
+pub(crate) struct WarcSplitListHeader;
+
+impl VScalar for WarcSplitListHeader {
+    type State = ();
+
+    unsafe fn invoke(
+        _state: &Self::State,
+        input: &mut DataChunkHandle,
+        output: &mut dyn WritableVector,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut rows = Vec::with_capacity(input.len());
+        for_each_blob(input, 0, |_, value| {
+            rows.push(value.map(|value| split_list_header(&sanitize_for_ffi(&String::from_utf8_lossy(value)))));
+        });
+
+        let mut list_vector = output.list_vector();
+        let total: usize = rows.iter().flatten().map(Vec::len).sum();
+        let element_vec = list_vector.child(total);
+
+        let mut offset = 0;
+        for (i, elements) in rows.iter().enumerate() {
+            let Some(elements) = elements else {
+                list_vector.set_null(i);
+                continue;
+            };
+            list_vector.set_entry(i, offset, elements.len());
+            for element in elements {
+                element_vec.insert(offset, element.as_str());
+                offset += 1;
+            }
+        }
+        list_vector.set_len(total);
+
+        Ok(())
+    }
+
+    fn signatures() -> Vec<ScalarFunctionSignature> {
+        vec![ScalarFunctionSignature::exact(
+            vec![LogicalTypeHandle::from(LogicalTypeId::Varchar)],
+            LogicalTypeHandle::list(&LogicalTypeHandle::from(LogicalTypeId::Varchar)),
+        )]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_list_header() {
+        assert_eq!(split_list_header("Accept-Encoding, User-Agent"), ["Accept-Encoding", "User-Agent"]);
+        assert_eq!(
+            split_list_header("text/html, application/xhtml+xml;q=0.9,, */*;q=0.8 "),
+            ["text/html", "application/xhtml+xml;q=0.9", "*/*;q=0.8"]
+        );
+        // Quoted commas and escaped quotes stay inside their element
+        assert_eq!(
+            split_list_header(r#"private="set-cookie, x-id", max-age=60, note="a\"b,c""#),
+            ["private=\"set-cookie, x-id\"", "max-age=60", r#"note="a\"b,c""#]
+        );
+        assert!(split_list_header(" , ").is_empty());
+    }
+}
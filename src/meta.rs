@@ -0,0 +1,315 @@
+use crate::{
+    check_struct_output, for_each_blob, repair_warc_header_block, skip_informational_responses, split_http_message,
+    status_line_code, with_decompressed,
+};
+use duckdb::{
+    core::{DataChunkHandle, Inserter, LogicalTypeHandle, LogicalTypeId},
+    ffi,
+    types::DuckString,
+    vscalar::{ScalarFunctionSignature, VScalar},
+    vtab::arrow::WritableVector,
+};
+use flate2::read::GzDecoder;
+use libduckdb_sys::duckdb_string_t;
+use std::borrow::Cow;
+use std::error::Error;
+use std::io::{BufRead, BufReader};
+
+/// The few fields most queries filter on, read without copying the body
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct RecordMeta {
+    pub warc_type: Option<String>,
+    pub target_uri: Option<String>,
+    pub http_status: Option<i32>,
+    pub content_type: Option<String>, // HTTP Content-Type, or the WARC one for non-responses
+    pub content_length: Option<i64>,  // WARC Content-Length of the record block
+}
+
+/// Parse the metadata of the first record in decompressed WARC bytes
+///
+/// Reads the WARC header block with the `warc` crate's parser and the HTTP
+/// status line and Content-Type in place, so the body is never copied and no
+/// JSON is built.
+pub(crate) fn parse_record_meta(data: &[u8]) -> Option<RecordMeta> {
+    let data = match repair_warc_header_block(data) {
+        Some(repaired) => Cow::Owned(repaired),
+        None => Cow::Borrowed(data),
+    };
+    let (rest, (_version, headers, content_length)) = warc_rs::parser::headers(&data).ok()?;
+
+    let mut meta = RecordMeta {
+        content_length: Some(content_length as i64),
+        ..Default::default()
+    };
+    for (name, value) in headers {
+        let value = || String::from_utf8_lossy(value).trim().to_string();
+        if name.eq_ignore_ascii_case("WARC-Type") {
+            meta.warc_type = Some(value());
+        } else if name.eq_ignore_ascii_case("WARC-Target-URI") {
+            meta.target_uri = Some(value());
+        } else if name.eq_ignore_ascii_case("Content-Type") {
+            meta.content_type = Some(value());
+        }
+    }
+
+    if meta.warc_type.as_deref() == Some("response") {
+        let block = rest.strip_prefix(b"\r\n").unwrap_or(rest);
+        let block = &block[..content_length.min(block.len())];
+        let (status, content_type) = http_status_and_content_type(block);
+        meta.http_status = status;
+        meta.content_type = content_type;
+    }
+
+    Some(meta)
+}
+
+/// HTTP status code and Content-Type of an HTTP message
+fn http_status_and_content_type(message: &[u8]) -> (Option<i32>, Option<String>) {
+    let Some((header_bytes, _)) = split_http_message(skip_informational_responses(message)) else {
+        return (None, None);
+    };
+
+    let header_text = String::from_utf8_lossy(header_bytes);
+    let mut lines = header_text.lines();
+    let status = lines.next().and_then(status_line_code);
+    let content_type = lines.find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        key.trim()
+            .eq_ignore_ascii_case("content-type")
+            .then(|| value.trim().to_string())
+    });
+
+    (status, content_type)
+}
+
+/// DuckDB scalar function returning a narrow metadata struct for a record
+///
+/// Returns a struct with:
+/// - warc_type: VARCHAR
+/// - target_uri: VARCHAR
+/// - http_status: INTEGER
+/// - content_type: VARCHAR
+/// - content_length: BIGINT
+pub(crate) struct ParseWarcMeta;
+
+impl VScalar for ParseWarcMeta {
+    type State = ();
+
+    unsafe fn invoke(
+        _state: &Self::State,
+        input: &mut DataChunkHandle,
+        output: &mut dyn WritableVector,
+    ) -> Result<(), Box<dyn Error>> {
+        let size = input.len();
+        let mut metas = Vec::with_capacity(size);
+        for_each_blob(input, 0, |_, raw_data| {
+            metas.push(raw_data.and_then(|raw_data| with_decompressed(raw_data, parse_record_meta)));
+        });
+
+        let output_struct = output.struct_vector();
+        let capacity = ffi::duckdb_vector_size() as usize;
+        check_struct_output(size, capacity, output_struct.num_children(), 5)?;
+        let mut warc_type_vec = output_struct.child(0, capacity);
+        let mut target_uri_vec = output_struct.child(1, capacity);
+        let mut http_status_vec = output_struct.child(2, capacity);
+        let mut content_type_vec = output_struct.child(3, capacity);
+        let mut content_length_vec = output_struct.child(4, capacity);
+
+        for (i, meta) in metas.into_iter().enumerate() {
+            let meta = meta.unwrap_or_default();
+
+            match &meta.warc_type {
+                Some(v) => warc_type_vec.insert(i, v.as_str()),
+                None => warc_type_vec.set_null(i),
+            }
+            match &meta.target_uri {
+                Some(v) => target_uri_vec.insert(i, v.as_str()),
+                None => target_uri_vec.set_null(i),
+            }
+            match meta.http_status {
+                Some(v) => http_status_vec.as_mut_slice::<i32>()[i] = v,
+                None => http_status_vec.set_null(i),
+            }
+            match &meta.content_type {
+                Some(v) => content_type_vec.insert(i, v.as_str()),
+                None => content_type_vec.set_null(i),
+            }
+            match meta.content_length {
+                Some(v) => content_length_vec.as_mut_slice::<i64>()[i] = v,
+                None => content_length_vec.set_null(i),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn signatures() -> Vec<ScalarFunctionSignature> {
+        let make_return_type = || {
+            LogicalTypeHandle::struct_type(&[
+                ("warc_type", LogicalTypeHandle::from(LogicalTypeId::Varchar)),
+                ("target_uri", LogicalTypeHandle::from(LogicalTypeId::Varchar)),
+                ("http_status", LogicalTypeHandle::from(LogicalTypeId::Integer)),
+                ("content_type", LogicalTypeHandle::from(LogicalTypeId::Varchar)),
+                ("content_length", LogicalTypeHandle::from(LogicalTypeId::Bigint)),
+            ])
+        };
+
+        vec![
+            ScalarFunctionSignature::exact(vec![LogicalTypeHandle::from(LogicalTypeId::Blob)], make_return_type()),
+            ScalarFunctionSignature::exact(
+                vec![LogicalTypeHandle::from(LogicalTypeId::Varchar)],
+                make_return_type(),
+            ),
+        ]
+    }
+}
+
+/// Whether the first record of a blob, plain or gzip, has a WARC header named `name`
+///
+/// Only the header block is read, and for gzip only decompressed that far.
+/// Names compare case-insensitively. None when the blob doesn't start with a
+/// WARC record.
+pub(crate) fn warc_header_present(data: &[u8], name: &str) -> Option<bool> {
+    if data.starts_with(&[0x1f, 0x8b]) {
+        header_block_has(BufReader::new(GzDecoder::new(data)), name)
+    } else {
+        header_block_has(data, name)
+    }
+}
+
+fn header_block_has(mut reader: impl BufRead, name: &str) -> Option<bool> {
+    let mut line = Vec::new();
+    reader.read_until(b'\n', &mut line).ok()?;
+    if !line.starts_with(b"WARC/") {
+        return None;
+    }
+    loop {
+        line.clear();
+        // A header block cut short has no more headers
+        if reader.read_until(b'\n', &mut line).ok()? == 0 || line.trim_ascii().is_empty() {
+            return Some(false);
+        }
+        // Folded continuation lines carry no name
+        if line.starts_with(b" ") || line.starts_with(b"\t") {
+            continue;
+        }
+        if let Some(colon) = line.iter().position(|&b| b == b':') {
+            if line[..colon].trim_ascii().eq_ignore_ascii_case(name.trim().as_bytes()) {
+                return Some(true);
+            }
+        }
+    }
+}
+
+/// DuckDB scalar function checking a record for a WARC header without parsing it
+///
+/// Returns a BOOLEAN, NULL when the blob isn't a WARC record.
+pub(crate) struct WarcHeaderPresent;
+
+impl VScalar for WarcHeaderPresent {
+    type State = ();
+
+    unsafe fn invoke(
+        _state: &Self::State,
+        input: &mut DataChunkHandle,
+        output: &mut dyn WritableVector,
+    ) -> Result<(), Box<dyn Error>> {
+        let size = input.len();
+        let name_vector = input.flat_vector(1);
+        let names = name_vector.as_slice_with_len::<duckdb_string_t>(size);
+        let mut output_vector = output.flat_vector();
+
+        for_each_blob(input, 0, |i, raw_data| {
+            let present = raw_data.filter(|_| !name_vector.row_is_null(i as u64)).and_then(|raw_data| {
+                let mut name = names[i];
+                warc_header_present(raw_data, DuckString::new(&mut name).as_str().as_ref())
+            });
+            match present {
+                Some(present) => output_vector.as_mut_slice::<bool>()[i] = present,
+                None => output_vector.set_null(i),
+            }
+        });
+
+        Ok(())
+    }
+
+    fn signatures() -> Vec<ScalarFunctionSignature> {
+        let make_signature = |blob: LogicalTypeId| {
+            ScalarFunctionSignature::exact(
+                vec![LogicalTypeHandle::from(blob), LogicalTypeHandle::from(LogicalTypeId::Varchar)],
+                LogicalTypeHandle::from(LogicalTypeId::Boolean),
+            )
+        };
+        vec![make_signature(LogicalTypeId::Blob), make_signature(LogicalTypeId::Varchar)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_warc_record, ParseOptions};
+
+    #[test]
+    fn test_parse_record_meta_response() {
+        let data = std::fs::read("test-data/example.warc").unwrap();
+        let meta = parse_record_meta(&data).unwrap();
+        assert_eq!(
+            meta,
+            RecordMeta {
+                warc_type: Some("response".to_string()),
+                target_uri: Some("http://www.example.com/".to_string()),
+                http_status: Some(200),
+                content_type: Some("text/html".to_string()),
+                content_length: Some(885),
+            }
+        );
+
+        // Agrees with the full parser
+        let record = parse_warc_record(&data, &ParseOptions::default()).unwrap();
+        assert_eq!(meta.http_status, record.http_status);
+    }
+
+    #[test]
+    fn test_parse_record_meta_non_response() {
+        let data = crate::warc_file::tests::warc_record("request", b"GET / HTTP/1.1\r\n\r\n");
+        let meta = parse_record_meta(&data).unwrap();
+        assert_eq!(meta.warc_type, Some("request".to_string()));
+        assert_eq!(meta.http_status, None);
+        assert_eq!(meta.content_length, Some(18));
+
+        assert_eq!(parse_record_meta(b"not a warc record"), None);
+    }
+
+    #[test]
+    fn test_parse_record_meta_without_reason_phrase() {
+        let data = crate::warc_file::tests::warc_record("response", b"HTTP/1.1 204\t\r\n\r\n");
+        assert_eq!(parse_record_meta(&data).unwrap().http_status, Some(204));
+    }
+
+    #[test]
+    fn test_parse_record_meta_skips_informational() {
+        let data = crate::warc_file::tests::warc_record(
+            "response",
+            b"HTTP/1.1 103 Early Hints\r\nLink: </a.css>\r\n\r\nHTTP/1.1 200 OK\r\nContent-Type: text/html\r\n\r\nhi",
+        );
+        let meta = parse_record_meta(&data).unwrap();
+        assert_eq!(meta.http_status, Some(200));
+        assert_eq!(meta.content_type, Some("text/html".to_string()));
+    }
+
+    #[test]
+    fn test_warc_header_present() {
+        let data = std::fs::read("test-data/example.warc").unwrap();
+        assert_eq!(warc_header_present(&data, "WARC-Target-URI"), Some(true));
+        assert_eq!(warc_header_present(&data, "warc-payload-digest"), Some(true));
+        assert_eq!(warc_header_present(&data, "WARC-Refers-To"), Some(false));
+        // HTTP headers aren't WARC headers
+        assert_eq!(warc_header_present(&data, "ETag"), Some(false));
+
+        let gzipped = crate::gzip::gzip_compress(&data).unwrap();
+        assert_eq!(warc_header_present(&gzipped, "WARC-Target-URI"), Some(true));
+        assert_eq!(warc_header_present(&gzipped, "WARC-Refers-To"), Some(false));
+
+        assert_eq!(warc_header_present(b"not a warc record", "WARC-Type"), None);
+    }
+}
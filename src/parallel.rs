@@ -0,0 +1,74 @@
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use std::sync::OnceLock;
+
+/// Most threads the pool gets, however many DuckDB runs
+const MAX_THREADS: usize = 8;
+
+/// The pool used to parse a single chunk, built at registration
+static POOL: OnceLock<ThreadPool> = OnceLock::new();
+
+/// Pool size for DuckDB's `threads` setting: as many threads, up to
+/// `MAX_THREADS`
+fn pool_size(duckdb_threads: i64) -> usize {
+    duckdb_threads.clamp(1, MAX_THREADS as i64) as usize
+}
+
+fn build_pool(threads: usize) -> Result<ThreadPool, String> {
+    ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .thread_name(|i| format!("warc-{}", i))
+        .build()
+        .map_err(|e| format!("failed to build WARC thread pool: {}", e))
+}
+
+/// Build the pool for DuckDB's `threads` setting, as read at registration
+///
+/// A DuckDB thread waits while the pool parses its chunk, so no more
+/// threads parse at once than DuckDB is allowed. With a single thread there
+/// is no pool and rows are parsed inline. The pool is shared by every
+/// database in the process; a later load keeps the first one.
+pub(crate) fn init(duckdb_threads: i64) -> Result<(), String> {
+    let threads = pool_size(duckdb_threads);
+    if threads > 1 && POOL.get().is_none() {
+        let _ = POOL.set(build_pool(threads)?);
+    }
+    Ok(())
+}
+
+/// Map `f` over `items` on the pool, preserving order
+///
+/// Runs inline when there is no pool.
+pub(crate) fn par_map<T: Sync, U: Send>(items: &[T], f: impl Fn(&T) -> U + Sync + Send) -> Vec<U> {
+    match POOL.get() {
+        Some(pool) => pool.install(|| items.par_iter().map(f).collect()),
+        None => items.iter().map(f).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pool_size_follows_duckdb_threads() {
+        assert_eq!(pool_size(3), 3);
+        assert_eq!(pool_size(1), 1);
+        assert_eq!(pool_size(64), MAX_THREADS);
+        assert_eq!(pool_size(0), 1);
+    }
+
+    #[test]
+    fn test_pool_size_honors_setting() {
+        assert_eq!(build_pool(pool_size(2)).unwrap().current_num_threads(), 2);
+        assert_eq!(build_pool(pool_size(32)).unwrap().current_num_threads(), MAX_THREADS);
+    }
+
+    #[test]
+    fn test_par_map_preserves_order() {
+        init(4).unwrap();
+        assert_eq!(POOL.get().map(ThreadPool::current_num_threads), Some(4));
+        let items: Vec<usize> = (0..1000).collect();
+        assert_eq!(par_map(&items, |i| i * 2), items.iter().map(|i| i * 2).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,66 @@
+use duckdb::{
+    ffi,
+    vtab::{BindInfo, Value},
+};
+use std::ffi::CStr;
+use std::slice;
+
+// `Value` is a lone `duckdb_value` with no accessor for it
+const _: () = assert!(std::mem::size_of::<Value>() == std::mem::size_of::<ffi::duckdb_value>());
+
+/// The `duckdb_value` a parameter wraps, owned by `value`
+fn raw_value(value: &Value) -> ffi::duckdb_value {
+    unsafe { *(value as *const Value).cast::<ffi::duckdb_value>() }
+}
+
+/// A BOOLEAN named parameter, `default` when it is absent or NULL
+pub(crate) fn bool_parameter(bind: &BindInfo, name: &str, default: bool) -> bool {
+    bind.get_named_parameter(name).map_or(default, |value| {
+        let value = raw_value(&value);
+        unsafe {
+            if ffi::duckdb_is_null_value(value) {
+                default
+            } else {
+                ffi::duckdb_get_bool(value)
+            }
+        }
+    })
+}
+
+/// Bytes of a BLOB parameter; None when it is NULL
+pub(crate) fn blob_parameter(value: &Value) -> Option<Vec<u8>> {
+    let value = raw_value(value);
+    unsafe {
+        if ffi::duckdb_is_null_value(value) {
+            return None;
+        }
+        let blob = ffi::duckdb_get_blob(value);
+        let bytes = if blob.data.is_null() {
+            Vec::new()
+        } else {
+            slice::from_raw_parts(blob.data.cast::<u8>(), blob.size as usize).to_vec()
+        };
+        ffi::duckdb_free(blob.data);
+        Some(bytes)
+    }
+}
+
+/// Elements of a VARCHAR[] parameter, NULL elements left out
+pub(crate) fn list_parameter(value: &Value) -> Vec<String> {
+    let value = raw_value(value);
+    unsafe {
+        (0..ffi::duckdb_get_list_size(value))
+            .filter_map(|i| {
+                let mut child = ffi::duckdb_get_list_child(value, i);
+                let element = (!ffi::duckdb_is_null_value(child)).then(|| {
+                    let text = ffi::duckdb_get_varchar(child);
+                    let element = CStr::from_ptr(text).to_string_lossy().into_owned();
+                    ffi::duckdb_free(text.cast());
+                    element
+                });
+                ffi::duckdb_destroy_value(&mut child);
+                element
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,83 @@
+use crate::parameters::{blob_parameter, bool_parameter};
+use crate::{parse_raw_warc_record, record_fields, ParseOptions, ParsedRecord, RecordVectors};
+use duckdb::{
+    core::{DataChunkHandle, LogicalTypeHandle, LogicalTypeId},
+    vtab::{BindInfo, InitInfo, TableFunctionInfo, VTab},
+};
+use std::error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// DuckDB table function returning a record as one row of flat columns
+///
+/// `parse_warc_rows(blob)` parses the record in a BLOB, plain or gzip, into
+/// the `parse_warc` fields as top-level columns, the columns of `read_warc`
+/// without `filename` and `record_index`. A NULL blob returns no rows.
+/// `lowercase_headers := true` lowercases the keys of `warc_headers`.
+///
+/// Like any table function argument, the blob must be a constant: a literal
+/// or e.g. `getvariable('record')`. For a column of records, unnest
+/// `parse_warc` instead.
+pub(crate) struct ParseWarcRows;
+
+pub(crate) struct ParseWarcRowsBindData {
+    data: Option<Vec<u8>>,
+    lowercase_headers: bool,
+}
+
+pub(crate) struct ParseWarcRowsInitData {
+    done: AtomicBool,
+}
+
+impl VTab for ParseWarcRows {
+    type InitData = ParseWarcRowsInitData;
+    type BindData = ParseWarcRowsBindData;
+
+    fn bind(bind: &BindInfo) -> Result<Self::BindData, Box<dyn Error>> {
+        for (name, logical_type) in record_fields() {
+            bind.add_result_column(name, logical_type);
+        }
+        Ok(ParseWarcRowsBindData {
+            data: blob_parameter(&bind.get_parameter(0)),
+            lowercase_headers: bool_parameter(bind, "lowercase_headers", false),
+        })
+    }
+
+    fn init(_: &InitInfo) -> Result<Self::InitData, Box<dyn Error>> {
+        Ok(ParseWarcRowsInitData {
+            done: AtomicBool::new(false),
+        })
+    }
+
+    fn func(func: &TableFunctionInfo<Self>, output: &mut DataChunkHandle) -> Result<(), Box<dyn Error>> {
+        let bind_data = func.get_bind_data();
+        let data = match &bind_data.data {
+            Some(data) if !func.get_init_data().done.swap(true, Ordering::Relaxed) => data,
+            _ => {
+                output.set_len(0);
+                return Ok(());
+            }
+        };
+
+        let options = ParseOptions {
+            lowercase_header_names: bind_data.lowercase_headers,
+            ..Default::default()
+        };
+        let record = parse_raw_warc_record(data, &options).unwrap_or_else(ParsedRecord::from_error);
+        let mut vectors = RecordVectors::new(|field| output.flat_vector(field), |field| output.list_vector(field));
+        vectors.write(0, Some(&record));
+        output.set_len(1);
+
+        Ok(())
+    }
+
+    fn parameters() -> Option<Vec<LogicalTypeHandle>> {
+        Some(vec![LogicalTypeHandle::from(LogicalTypeId::Blob)])
+    }
+
+    fn named_parameters() -> Option<Vec<(String, LogicalTypeHandle)>> {
+        Some(vec![(
+            "lowercase_headers".to_string(),
+            LogicalTypeHandle::from(LogicalTypeId::Boolean),
+        )])
+    }
+}
@@ -0,0 +1,576 @@
+use crate::http_date::parse_warc_date;
+use crate::parameters::{bool_parameter, list_parameter};
+use crate::transcode::target_encoding;
+use crate::warc_file::{DateRange, Records, WarcFile};
+use crate::error::WarcParseError;
+use crate::{
+    parse_warc_record, record_fields, record_id_uuid, warc_header_values, HeaderAllowlist,
+    ParseOptions, ParsedRecord, RecordVectors,
+};
+use duckdb::{
+    core::{DataChunkHandle, Inserter, LogicalTypeHandle, LogicalTypeId},
+    ffi,
+    vtab::{BindInfo, InitInfo, TableFunctionInfo, VTab},
+};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use uuid::Uuid;
+
+/// DuckDB table function reading every record of one or more WARC files
+///
+/// `read_warc(pattern)` accepts a path or a glob (`data/*.warc.gz`), with
+/// plain or gzip files. Returns `filename` and `record_index` (0-based position
+/// of the record in its file) columns followed by the `parse_warc` fields as
+/// columns. Files are read in parallel, one file per
+/// DuckDB thread at a time.
+///
+/// With `extract_bodies_to := 'dir/'`, each body is written to
+/// `dir/<record-id>.bin` instead of `http_body`, and its path is returned in
+/// an extra `body_path` column.
+///
+/// A file that ends mid-record keeps its complete records; the partial last
+/// one sets `parse_error` and `likely_truncated`. A record that can't be read
+/// at all (e.g. no Content-Length) becomes a row with only `filename` and
+/// `parse_error` set, and reading goes on with the next record.
+///
+/// `date_from := '2025-01-01'` and `date_to := '2025-02-01'` keep only
+/// records with a WARC-Date in `[date_from, date_to)`. Other records are
+/// skipped on their header, before any parsing.
+///
+/// `body_charset := 'utf-8'` re-encodes text response bodies from their
+/// declared or sniffed charset into the given one.
+///
+/// `headers := ['WARC-Type', 'WARC-Target-URI']` keeps only the named
+/// headers in `warc_headers` and `http_headers`.
+///
+/// `lowercase_headers := true` lowercases the keys of `warc_headers`, which
+/// otherwise keep the spec casing (`WARC-Target-URI`).
+///
+/// `trust_content_length := false` reads each block up to the next record
+/// instead of for its declared Content-Length, for files from writers that
+/// get it wrong.
+///
+/// `decode_body := false` never decodes a body's HTTP codings: `http_body`
+/// is returned as captured (see `body_encoded`) and the fields read from the
+/// decoded content (`likely_soft_404`, `meta_refresh_url`, the sniffed type
+/// of `content_type_mismatch`) are left unset, for header-only scans of
+/// compressed bodies. It can't be combined with `body_charset`.
+pub(crate) struct ReadWarc;
+
+pub(crate) struct ReadWarcBindData {
+    paths: Vec<String>,
+    body_dir: Option<PathBuf>,
+    date_range: DateRange,
+    body_charset: Option<&'static encoding_rs::Encoding>,
+    header_names: Option<Vec<String>>,
+    lowercase_headers: bool,
+    trust_content_length: bool,
+    decode_body: bool,
+}
+
+impl ReadWarcBindData {
+    /// How the records are parsed, from the named parameters
+    fn parse_options(&self) -> ParseOptions {
+        ParseOptions {
+            lowercase_header_names: self.lowercase_headers,
+            body_charset: self.body_charset,
+            header_names: self.header_names.as_deref().map(HeaderAllowlist::new),
+            decode_body: self.decode_body,
+            ..Default::default()
+        }
+    }
+}
+
+/// Record bytes buffered per output chunk
+///
+/// A chunk is returned early once its records pass this size, so a `LIMIT`
+/// over large records stops reading after a few MiB instead of a full
+/// vector's worth, and memory stays bounded.
+pub(crate) const CHUNK_BYTES: usize = 8 * 1024 * 1024;
+
+/// Parse a `date_from`/`date_to` parameter value
+fn date_bound(name: &str, value: Option<String>) -> Result<Option<i64>, Box<dyn Error>> {
+    value
+        .map(|value| {
+            parse_warc_date(&value).ok_or_else(|| {
+                format!("{}: invalid date \"{}\", expected YYYY-MM-DD or YYYY-MM-DDThh:mm:ssZ", name, value).into()
+            })
+        })
+        .transpose()
+}
+
+/// Mark the partial last record of a file that ends mid-record
+fn truncated_record(record: ParsedRecord) -> ParsedRecord {
+    let mut warnings = record.warnings.clone();
+    warnings.push("file ends inside the record".to_string());
+    ParsedRecord {
+        likely_truncated: true,
+        warnings,
+        ..record.with_error(WarcParseError::Truncated)
+    }
+}
+
+/// File name for a body: the record's UUID, or its WARC-Record-ID with any
+/// character unsafe in a path replaced
+fn body_file_name(record_id: &str) -> String {
+    let stem = match record_id_uuid(record_id) {
+        Some(uuid) => Uuid::from_u128(uuid).hyphenated().to_string(),
+        None => record_id
+            .trim()
+            .trim_start_matches('<')
+            .trim_end_matches('>')
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '.' { c } else { '_' })
+            .collect(),
+    };
+    format!("{}.bin", stem)
+}
+
+/// Write a body to `dir`, named after the record, returning its path
+fn write_body(dir: &Path, record_id: &str, body: &[u8]) -> std::io::Result<String> {
+    let path = dir.join(body_file_name(record_id));
+    std::fs::write(&path, body)?;
+    Ok(path.to_string_lossy().into_owned())
+}
+
+/// A record's bytes, or why it couldn't be read (reported in `parse_error`)
+type RecordData = Result<Vec<u8>, String>;
+
+/// A row read from a file: path index, record index, compression, record
+/// bytes and whether the file ended inside the record
+type RecordRow = (usize, u64, &'static str, RecordData, bool);
+
+/// A file being read, by whichever thread took it from the pool
+struct FileReader {
+    path_index: usize,
+    compression: &'static str,
+    records: Records,
+}
+
+pub(crate) struct ReadWarcInitData {
+    next_path: AtomicUsize,
+    // Partly read files, owned by no thread: DuckDB may resume a scan task
+    // on any worker, so each call takes a reader out and puts it back when
+    // it stops mid-file
+    readers: Mutex<Vec<FileReader>>,
+}
+
+/// Read the records of the next output chunk
+///
+/// Resumes a partly read file from the pool, else opens the next unread
+/// path, until the chunk holds `capacity` records or `CHUNK_BYTES` of them.
+/// Empty once every file has been read.
+fn read_rows(
+    bind_data: &ReadWarcBindData,
+    init_data: &ReadWarcInitData,
+    capacity: usize,
+) -> Result<Vec<RecordRow>, Box<dyn Error>> {
+    let mut reader: Option<FileReader> = None;
+    let mut rows = Vec::new();
+    let mut chunk_bytes = 0;
+    // Records are read only as DuckDB pulls chunks, so reading stops once
+    // a LIMIT is satisfied
+    while rows.len() < capacity && chunk_bytes < CHUNK_BYTES {
+        let current = match reader.as_mut() {
+            Some(current) => current,
+            None => {
+                // Partly read files first, so they don't wait on new ones
+                let parked = init_data.readers.lock().map_err(|e| e.to_string())?.pop();
+                if let Some(parked) = parked {
+                    reader.insert(parked)
+                } else {
+                    // Claim the next unread file
+                    let path_index = init_data.next_path.fetch_add(1, Ordering::Relaxed);
+                    let Some(path) = bind_data.paths.get(path_index) else {
+                        break;
+                    };
+                    let file = WarcFile::open(path).map_err(|e| format!("{}: {}", path, e))?;
+                    reader.insert(FileReader {
+                        path_index,
+                        compression: file.compression(),
+                        records: Records::new(file)
+                            .with_date_range(bind_data.date_range)
+                            .with_trust_content_length(bind_data.trust_content_length),
+                    })
+                }
+            }
+        };
+        match current.records.next() {
+            Some(record) => {
+                let record = record.map_err(|e| e.to_string());
+                chunk_bytes += record.as_ref().map_or(0, Vec::len);
+                let records = &current.records;
+                rows.push((current.path_index, records.index(), current.compression, record, records.truncated()));
+            }
+            None => reader = None,
+        }
+    }
+    if let Some(reader) = reader {
+        init_data.readers.lock().map_err(|e| e.to_string())?.push(reader);
+    }
+    Ok(rows)
+}
+
+/// Expand a glob pattern into the sorted list of matching paths
+///
+/// A pattern without wildcards is returned as-is so a missing file is
+/// reported when it is opened.
+fn expand_paths(pattern: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    if !pattern.contains(['*', '?', '[']) {
+        return Ok(vec![pattern.to_string()]);
+    }
+
+    let paths: Vec<String> = glob::glob(pattern)?
+        .filter_map(|entry| entry.ok())
+        .filter(|path| path.is_file())
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect();
+    if paths.is_empty() {
+        return Err(format!("No files found that match the pattern \"{}\"", pattern).into());
+    }
+    Ok(paths)
+}
+
+impl VTab for ReadWarc {
+    type InitData = ReadWarcInitData;
+    type BindData = ReadWarcBindData;
+
+    fn bind(bind: &BindInfo) -> Result<Self::BindData, Box<dyn Error>> {
+        bind.add_result_column("filename", LogicalTypeHandle::from(LogicalTypeId::Varchar));
+        bind.add_result_column("record_index", LogicalTypeHandle::from(LogicalTypeId::Bigint));
+        for (name, logical_type) in record_fields() {
+            bind.add_result_column(name, logical_type);
+        }
+
+        let body_dir = bind.get_named_parameter("extract_bodies_to").map(|dir| PathBuf::from(dir.to_string()));
+        if let Some(dir) = &body_dir {
+            bind.add_result_column("body_path", LogicalTypeHandle::from(LogicalTypeId::Varchar));
+            std::fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+        }
+
+        let date_range = DateRange {
+            from: date_bound("date_from", bind.get_named_parameter("date_from").map(|v| v.to_string()))?,
+            to: date_bound("date_to", bind.get_named_parameter("date_to").map(|v| v.to_string()))?,
+        };
+
+        let body_charset = bind
+            .get_named_parameter("body_charset")
+            .map(|label| target_encoding(&label.to_string()))
+            .transpose()?;
+
+        let header_names = bind.get_named_parameter("headers").map(|names| list_parameter(&names));
+        let lowercase_headers = bool_parameter(bind, "lowercase_headers", false);
+        let trust_content_length = bool_parameter(bind, "trust_content_length", true);
+
+        let decode_body = bool_parameter(bind, "decode_body", true);
+        if !decode_body && body_charset.is_some() {
+            return Err("body_charset needs the body decoded, so it can't be combined with decode_body := false".into());
+        }
+
+        let pattern = bind.get_parameter(0).to_string();
+        Ok(ReadWarcBindData {
+            paths: expand_paths(&pattern)?,
+            body_dir,
+            date_range,
+            body_charset,
+            header_names,
+            lowercase_headers,
+            trust_content_length,
+            decode_body,
+        })
+    }
+
+    fn init(init: &InitInfo) -> Result<Self::InitData, Box<dyn Error>> {
+        let bind_data = unsafe { &*init.get_bind_data::<ReadWarcBindData>() };
+        init.set_max_threads(bind_data.paths.len() as ffi::idx_t);
+        Ok(ReadWarcInitData {
+            next_path: AtomicUsize::new(0),
+            readers: Mutex::new(Vec::new()),
+        })
+    }
+
+    fn func(func: &TableFunctionInfo<Self>, output: &mut DataChunkHandle) -> Result<(), Box<dyn Error>> {
+        let bind_data = func.get_bind_data();
+        let init_data = func.get_init_data();
+        let capacity = unsafe { ffi::duckdb_vector_size() } as usize;
+
+        let rows = read_rows(bind_data, init_data, capacity)?;
+
+        let filename_vec = output.flat_vector(0);
+        let mut index_vec = output.flat_vector(1);
+        let mut vectors = RecordVectors::new(|field| output.flat_vector(field + 2), |field| output.list_vector(field + 2));
+        let options = bind_data.parse_options();
+        let mut body_path_vec = bind_data.body_dir.as_ref().map(|_| output.flat_vector(record_fields().len() + 2));
+        for (i, (path_index, index, compression, data, truncated)) in rows.iter().enumerate() {
+            filename_vec.insert(i, bind_data.paths[*path_index].as_str());
+            index_vec.as_mut_slice::<i64>()[i] = *index as i64;
+            let record = match data {
+                Ok(data) => parse_warc_record(data, &options).unwrap_or_else(ParsedRecord::from_error),
+                Err(e) => ParsedRecord::from_error(WarcParseError::WarcError(e.clone())),
+            };
+            let mut record = ParsedRecord {
+                source_compression: Some(compression),
+                ..record
+            };
+            if *truncated {
+                record = truncated_record(record);
+            }
+
+            if let (Some(dir), Some(body_path_vec)) = (&bind_data.body_dir, &mut body_path_vec) {
+                let body = record.http_body.take();
+                let record_id = data
+                    .as_ref()
+                    .ok()
+                    .and_then(|data| warc_header_values(data, "WARC-Record-ID").into_iter().next());
+                match (body, record_id) {
+                    (Some(body), Some(record_id)) => {
+                        let path = write_body(dir, &record_id, &body).map_err(|e| format!("{}: {}", dir.display(), e))?;
+                        body_path_vec.insert(i, path.as_str());
+                    }
+                    _ => body_path_vec.set_null(i),
+                }
+            }
+            vectors.write(i, Some(&record));
+        }
+        output.set_len(rows.len());
+
+        Ok(())
+    }
+
+    fn parameters() -> Option<Vec<LogicalTypeHandle>> {
+        Some(vec![LogicalTypeHandle::from(LogicalTypeId::Varchar)])
+    }
+
+    fn named_parameters() -> Option<Vec<(String, LogicalTypeHandle)>> {
+        Some(vec![
+            (
+                "extract_bodies_to".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Varchar),
+            ),
+            ("date_from".to_string(), LogicalTypeHandle::from(LogicalTypeId::Varchar)),
+            ("date_to".to_string(), LogicalTypeHandle::from(LogicalTypeId::Varchar)),
+            ("body_charset".to_string(), LogicalTypeHandle::from(LogicalTypeId::Varchar)),
+            (
+                "headers".to_string(),
+                LogicalTypeHandle::list(&LogicalTypeHandle::from(LogicalTypeId::Varchar)),
+            ),
+            (
+                "trust_content_length".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Boolean),
+            ),
+            ("decode_body".to_string(), LogicalTypeHandle::from(LogicalTypeId::Boolean)),
+            (
+                "lowercase_headers".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Boolean),
+            ),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::warc_file::tests::{temp_file, warc_record};
+    use std::io::Read;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_expand_paths_glob() {
+        let first = temp_file("glob_a.warc", &warc_record("response", b"HTTP/1.1 200 OK\r\n\r\na"));
+        let second = temp_file("glob_b.warc", &warc_record("response", b"HTTP/1.1 404 Not Found\r\n\r\n"));
+        let pattern = std::env::temp_dir().join(format!("duckdb_warc_{}_glob_*.warc", std::process::id()));
+        let paths = expand_paths(&pattern.to_string_lossy()).unwrap();
+
+        // Every record of both files parses, in file order
+        let statuses: Vec<Option<i32>> = paths
+            .iter()
+            .flat_map(|path| Records::new(WarcFile::open(path).unwrap()))
+            .map(|record| parse_warc_record(&record.unwrap(), &ParseOptions::default()).unwrap().http_status)
+            .collect();
+        std::fs::remove_file(&first).unwrap();
+        std::fs::remove_file(&second).unwrap();
+
+        assert_eq!(
+            paths,
+            vec![first.to_string_lossy().into_owned(), second.to_string_lossy().into_owned()]
+        );
+        assert_eq!(statuses, vec![Some(200), Some(404)]);
+    }
+
+    /// Counts the bytes pulled from the underlying data
+    struct CountingReader {
+        data: std::io::Cursor<Vec<u8>>,
+        read: Arc<AtomicUsize>,
+    }
+
+    impl Read for CountingReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let n = self.data.read(buf)?;
+            self.read.fetch_add(n, Ordering::Relaxed);
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn test_records_read_lazily() {
+        let record = warc_record("response", &[b'x'; 10_000]);
+        let read = Arc::new(AtomicUsize::new(0));
+        let file = WarcFile::from_reader(CountingReader {
+            data: std::io::Cursor::new(record.repeat(100)),
+            read: read.clone(),
+        })
+        .unwrap();
+
+        // Like `LIMIT 3`: only the records pulled (plus read-ahead) are read
+        let first: Vec<Vec<u8>> = Records::new(file).take(3).map(|r| r.unwrap()).collect();
+        assert_eq!(first.len(), 3);
+        assert!(read.load(Ordering::Relaxed) < 5 * record.len(), "read {} bytes", read.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn test_read_rows_from_many_threads() {
+        // Files of different lengths, read a few records per call so most
+        // calls stop mid-file and park their reader
+        let paths: Vec<String> = (1..=6)
+            .map(|n| {
+                let data = warc_record("response", b"HTTP/1.1 200 OK\r\n\r\nbody").repeat(n * 7);
+                temp_file(&format!("pool_{}.warc", n), &data).to_string_lossy().into_owned()
+            })
+            .collect();
+        let expected: usize = (1..=6).map(|n| n * 7).sum();
+        let bind_data = ReadWarcBindData {
+            paths: paths.clone(),
+            body_dir: None,
+            date_range: DateRange::default(),
+            body_charset: None,
+            header_names: None,
+            lowercase_headers: false,
+            trust_content_length: true,
+            decode_body: true,
+        };
+        let init_data = ReadWarcInitData {
+            next_path: AtomicUsize::new(0),
+            readers: Mutex::new(Vec::new()),
+        };
+
+        // Each thread stops after a call or two, as when DuckDB hands the
+        // task to another worker; the rest keep going until the scan is done
+        let rows: Vec<RecordRow> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..4)
+                .map(|t| {
+                    let (bind_data, init_data) = (&bind_data, &init_data);
+                    scope.spawn(move || {
+                        let mut rows = Vec::new();
+                        for call in 0.. {
+                            if t > 0 && call > t {
+                                break;
+                            }
+                            let chunk = read_rows(bind_data, init_data, 3).unwrap();
+                            if chunk.is_empty() {
+                                break;
+                            }
+                            rows.extend(chunk);
+                        }
+                        rows
+                    })
+                })
+                .collect();
+            handles.into_iter().flat_map(|handle| handle.join().unwrap()).collect()
+        });
+        // The threads that stopped early left their readers in the pool for thread 0
+        assert_eq!(rows.len(), expected);
+        for (n, _) in paths.iter().enumerate() {
+            let mut indices: Vec<u64> = rows.iter().filter(|row| row.0 == n).map(|row| row.1).collect();
+            indices.sort();
+            assert_eq!(indices, (0..(n as u64 + 1) * 7).collect::<Vec<_>>());
+        }
+        assert!(init_data.readers.lock().unwrap().is_empty());
+        for path in paths {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_lowercase_headers_parameter() {
+        let path = temp_file("casing.warc", &warc_record("response", b"HTTP/1.1 200 OK\r\n\r\n"));
+        let warc_headers = |lowercase_headers| {
+            let bind_data = ReadWarcBindData {
+                paths: vec![path.to_string_lossy().into_owned()],
+                body_dir: None,
+                date_range: DateRange::default(),
+                body_charset: None,
+                header_names: None,
+                lowercase_headers,
+                trust_content_length: true,
+                decode_body: true,
+            };
+            let init_data = ReadWarcInitData {
+                next_path: AtomicUsize::new(0),
+                readers: Mutex::new(Vec::new()),
+            };
+            let rows = read_rows(&bind_data, &init_data, 10).unwrap();
+            let data = rows[0].3.as_ref().unwrap();
+            parse_warc_record(data, &bind_data.parse_options()).unwrap().warc_headers.unwrap()
+        };
+        let (spec, lowercase) = (warc_headers(false), warc_headers(true));
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(spec.contains("\"WARC-Type\": \"response\""), "{}", spec);
+        assert!(lowercase.contains("\"warc-type\": \"response\""), "{}", lowercase);
+        assert!(!lowercase.contains("WARC-"), "{}", lowercase);
+    }
+
+    #[test]
+    fn test_write_body() {
+        let dir = std::env::temp_dir().join(format!("duckdb_warc_{}_bodies", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let path = write_body(&dir, "<urn:uuid:EC056BCB-85C6-411E-991B-0F5246B9D411>", b"%PDF-1.7").unwrap();
+        assert_eq!(
+            PathBuf::from(&path),
+            dir.join("ec056bcb-85c6-411e-991b-0f5246b9d411.bin")
+        );
+        assert_eq!(std::fs::read(&path).unwrap(), b"%PDF-1.7");
+
+        // Other record ID schemes can't escape the directory
+        let path = write_body(&dir, "<urn:other:../../etc/x>", b"x").unwrap();
+        assert_eq!(PathBuf::from(&path), dir.join("urn_other_.._.._etc_x.bin"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_date_bound() {
+        assert_eq!(date_bound("date_from", None).unwrap(), None);
+        assert_eq!(
+            date_bound("date_from", Some("2025-06-01".to_string())).unwrap(),
+            parse_warc_date("2025-06-01T00:00:00Z")
+        );
+        let error = date_bound("date_to", Some("June 2025".to_string())).unwrap_err();
+        assert!(error.to_string().starts_with("date_to: invalid date \"June 2025\""), "{}", error);
+    }
+
+    #[test]
+    fn test_truncated_record() {
+        // A partial record that still parses keeps its fields
+        let data = warc_record("response", b"HTTP/1.1 200 OK\r\n\r\n");
+        let record = truncated_record(parse_warc_record(&data, &ParseOptions::default()).unwrap());
+        assert_eq!(record.http_status, Some(200));
+        assert!(record.likely_truncated);
+        assert_eq!(record.parse_error, Some(WarcParseError::Truncated.to_string()));
+        assert_eq!(record.warnings, ["file ends inside the record"]);
+
+        // One cut inside its header block only reports the truncation
+        let error = parse_warc_record(b"WARC/1.0\r\nWARC-Ty", &ParseOptions::default()).err().unwrap();
+        let record = truncated_record(ParsedRecord::from_error(error));
+        assert_eq!(record.warc_version, None);
+        assert!(record.parse_error.unwrap().starts_with("record truncated"));
+    }
+
+    #[test]
+    fn test_expand_paths_without_matches() {
+        assert_eq!(expand_paths("data/record.warc").unwrap(), vec!["data/record.warc"]);
+        assert!(expand_paths("/nonexistent/*.warc.gz").is_err());
+    }
+}
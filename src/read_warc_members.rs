@@ -0,0 +1,234 @@
+use crate::gzip::{GzipMember, GzipMembers};
+use crate::parameters::bool_parameter;
+use crate::read_warc::CHUNK_BYTES;
+use crate::{parse_warc_record, record_fields, ParseOptions, ParsedRecord, RecordVectors};
+use duckdb::{
+    core::{DataChunkHandle, Inserter, LogicalTypeHandle, LogicalTypeId},
+    ffi,
+    vtab::{BindInfo, InitInfo, TableFunctionInfo, VTab},
+};
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::sync::Mutex;
+
+/// DuckDB table function yielding the gzip members of a per-record gzip WARC
+///
+/// `read_warc_members(path)` returns one row per member with:
+/// - filename: VARCHAR
+/// - member_offset: BIGINT (byte offset of the member in the file)
+/// - compressed_size: BIGINT
+/// - content: BLOB (decompressed member, e.g. for `parse_warc(content)`)
+/// - gzip_crc32: UINTEGER (CRC32 declared in the member footer)
+/// - gzip_isize: UINTEGER (uncompressed size mod 2^32 declared in the footer)
+/// - gzip_crc_ok: BOOLEAN (footer matches the decompressed data)
+///
+/// With `parse := true`, `content` is replaced by the `parse_warc` fields as
+/// columns. Each member is then parsed as soon as it is decompressed and
+/// freed before the next one is read, so memory is bounded by the largest
+/// record rather than by the chunk. `lowercase_headers := true` lowercases
+/// the keys of `warc_headers`, as for `read_warc`.
+///
+/// With `include_raw := true`, a last `raw_member` BLOB column holds the
+/// compressed member as found in the file, for copying records verbatim
+/// into another archive.
+pub(crate) struct ReadWarcMembers;
+
+pub(crate) struct ReadWarcMembersBindData {
+    path: String,
+    parse: bool,
+    lowercase_headers: bool,
+    include_raw: bool,
+}
+
+pub(crate) struct ReadWarcMembersInitData {
+    members: Mutex<GzipMembers<BufReader<File>>>,
+}
+
+/// Pass the next members to `write` one at a time, with their row index
+///
+/// Stops after `capacity` members or once `CHUNK_BYTES` of decompressed data
+/// has gone by; each member is dropped when `write` returns. Returns the
+/// number of members written.
+fn fill_chunk<R: BufRead>(
+    members: &mut GzipMembers<R>,
+    capacity: usize,
+    mut write: impl FnMut(usize, GzipMember),
+) -> io::Result<usize> {
+    let mut rows = 0;
+    let mut chunk_bytes = 0;
+    while rows < capacity && chunk_bytes < CHUNK_BYTES {
+        let Some(member) = members.next().transpose()? else {
+            break;
+        };
+        chunk_bytes += member.data.len();
+        write(rows, member);
+        rows += 1;
+    }
+    Ok(rows)
+}
+
+impl VTab for ReadWarcMembers {
+    type InitData = ReadWarcMembersInitData;
+    type BindData = ReadWarcMembersBindData;
+
+    fn bind(bind: &BindInfo) -> Result<Self::BindData, Box<dyn Error>> {
+        let parse = bool_parameter(bind, "parse", false);
+        let lowercase_headers = bool_parameter(bind, "lowercase_headers", false);
+        let include_raw = bool_parameter(bind, "include_raw", false);
+
+        bind.add_result_column("filename", LogicalTypeHandle::from(LogicalTypeId::Varchar));
+        bind.add_result_column("member_offset", LogicalTypeHandle::from(LogicalTypeId::Bigint));
+        bind.add_result_column("compressed_size", LogicalTypeHandle::from(LogicalTypeId::Bigint));
+        if !parse {
+            bind.add_result_column("content", LogicalTypeHandle::from(LogicalTypeId::Blob));
+        }
+        bind.add_result_column("gzip_crc32", LogicalTypeHandle::from(LogicalTypeId::UInteger));
+        bind.add_result_column("gzip_isize", LogicalTypeHandle::from(LogicalTypeId::UInteger));
+        bind.add_result_column("gzip_crc_ok", LogicalTypeHandle::from(LogicalTypeId::Boolean));
+        if parse {
+            for (name, logical_type) in record_fields() {
+                bind.add_result_column(name, logical_type);
+            }
+        }
+        if include_raw {
+            bind.add_result_column("raw_member", LogicalTypeHandle::from(LogicalTypeId::Blob));
+        }
+
+        let path = bind.get_parameter(0).to_string();
+        Ok(ReadWarcMembersBindData {
+            path,
+            parse,
+            lowercase_headers,
+            include_raw,
+        })
+    }
+
+    fn init(init: &InitInfo) -> Result<Self::InitData, Box<dyn Error>> {
+        let bind_data = unsafe { &*init.get_bind_data::<ReadWarcMembersBindData>() };
+        let file = File::open(&bind_data.path).map_err(|e| format!("{}: {}", bind_data.path, e))?;
+        let members = GzipMembers::new(BufReader::new(file));
+        Ok(ReadWarcMembersInitData {
+            members: Mutex::new(if bind_data.include_raw { members.with_raw() } else { members }),
+        })
+    }
+
+    fn func(func: &TableFunctionInfo<Self>, output: &mut DataChunkHandle) -> Result<(), Box<dyn Error>> {
+        let bind_data = func.get_bind_data();
+        let mut members = func.get_init_data().members.lock().map_err(|e| e.to_string())?;
+        let capacity = unsafe { ffi::duckdb_vector_size() } as usize;
+
+        let filename_vec = output.flat_vector(0);
+        let mut offset_vec = output.flat_vector(1);
+        let mut compressed_size_vec = output.flat_vector(2);
+        let content_vec = (!bind_data.parse).then(|| output.flat_vector(3));
+        // The gzip footer columns follow `content`, or take its place
+        let footer = if bind_data.parse { 3 } else { 4 };
+        let mut crc32_vec = output.flat_vector(footer);
+        let mut isize_vec = output.flat_vector(footer + 1);
+        let mut crc_ok_vec = output.flat_vector(footer + 2);
+        let mut vectors = bind_data
+            .parse
+            .then(|| RecordVectors::new(|field| output.flat_vector(field + 6), |field| output.list_vector(field + 6)));
+        let raw_vec = bind_data.include_raw.then(|| {
+            let fields = if bind_data.parse { record_fields().len() } else { 0 };
+            output.flat_vector(footer + 3 + fields)
+        });
+        let options = ParseOptions {
+            lowercase_header_names: bind_data.lowercase_headers,
+            ..Default::default()
+        };
+
+        let rows = fill_chunk(&mut members, capacity, |i, member| {
+            filename_vec.insert(i, bind_data.path.as_str());
+            offset_vec.as_mut_slice::<i64>()[i] = member.offset as i64;
+            compressed_size_vec.as_mut_slice::<i64>()[i] = member.compressed_size as i64;
+            if let Some(content_vec) = &content_vec {
+                Inserter::<&[u8]>::insert(content_vec, i, member.data.as_slice());
+            }
+            crc32_vec.as_mut_slice::<u32>()[i] = member.crc32;
+            isize_vec.as_mut_slice::<u32>()[i] = member.isize;
+            crc_ok_vec.as_mut_slice::<bool>()[i] = member.crc_ok;
+            if let Some(vectors) = &mut vectors {
+                let record = parse_warc_record(&member.data, &options).unwrap_or_else(ParsedRecord::from_error);
+                let record = ParsedRecord {
+                    source_compression: Some("gzip"),
+                    ..record
+                };
+                vectors.write(i, Some(&record));
+            }
+            if let (Some(raw_vec), Some(raw)) = (&raw_vec, &member.raw) {
+                Inserter::<&[u8]>::insert(raw_vec, i, raw.as_slice());
+            }
+        })
+        .map_err(|e| format!("{}: {}", bind_data.path, e))?;
+        output.set_len(rows);
+
+        Ok(())
+    }
+
+    fn parameters() -> Option<Vec<LogicalTypeHandle>> {
+        Some(vec![LogicalTypeHandle::from(LogicalTypeId::Varchar)])
+    }
+
+    fn named_parameters() -> Option<Vec<(String, LogicalTypeHandle)>> {
+        Some(vec![
+            ("parse".to_string(), LogicalTypeHandle::from(LogicalTypeId::Boolean)),
+            ("include_raw".to_string(), LogicalTypeHandle::from(LogicalTypeId::Boolean)),
+            (
+                "lowercase_headers".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Boolean),
+            ),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gzip::gzip_compress;
+    use crate::warc_file::tests::warc_record;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// Counts the bytes pulled from the underlying data
+    struct CountingReader {
+        data: io::Cursor<Vec<u8>>,
+        read: Arc<AtomicUsize>,
+    }
+
+    impl io::Read for CountingReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.data.read(buf)?;
+            self.read.fetch_add(n, Ordering::Relaxed);
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn test_fill_chunk_one_member_at_a_time() {
+        // Incompressible bodies, so compressed and decompressed sizes are alike
+        let body: Vec<u8> = (0..3 << 20).map(|i: u32| (i.wrapping_mul(2654435761) >> 13) as u8).collect();
+        let member = gzip_compress(&warc_record("resource", &body)).unwrap();
+        let read = Arc::new(AtomicUsize::new(0));
+        let reader = CountingReader {
+            data: io::Cursor::new(member.repeat(10)),
+            read: read.clone(),
+        };
+        let mut members = GzipMembers::new(BufReader::new(reader));
+
+        // Each member is read only when its row is written, and is gone by the next
+        let mut offsets = Vec::new();
+        let rows = fill_chunk(&mut members, 2048, |i, member| {
+            assert!(read.load(Ordering::Relaxed) <= (i + 1) * member.compressed_size as usize + 8192);
+            offsets.push(member.offset);
+        })
+        .unwrap();
+
+        // A chunk ends once it passes CHUNK_BYTES, well before its capacity
+        assert_eq!(rows, 3);
+        assert_eq!(offsets, [0, member.len() as u64, 2 * member.len() as u64]);
+        let rows = fill_chunk(&mut members, 2048, |_, _| {}).unwrap();
+        assert_eq!(rows, 3);
+    }
+}
@@ -0,0 +1,213 @@
+use crate::for_each_blob;
+use crate::warc_file::{read_header_block, RecordHead, RecordHeads, WarcFile};
+use duckdb::{
+    core::{DataChunkHandle, Inserter, LogicalTypeHandle, LogicalTypeId},
+    ffi,
+    vscalar::{ScalarFunctionSignature, VScalar},
+    vtab::{arrow::WritableVector, BindInfo, InitInfo, TableFunctionInfo, VTab},
+};
+use flate2::read::MultiGzDecoder;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::io::{self, BufRead, BufReader, Read};
+use std::sync::Mutex;
+
+/// DuckDB table function yielding the WARC-Type of every record in a file
+///
+/// Only record headers are read; bodies are skipped using their Content-Length
+/// (seeking in uncompressed files). `read_warc_types(path)` returns:
+/// - filename: VARCHAR
+/// - warc_type: VARCHAR
+/// - content_length: BIGINT
+pub(crate) struct ReadWarcTypes;
+
+pub(crate) struct ReadWarcTypesBindData {
+    path: String,
+}
+
+pub(crate) struct ReadWarcTypesInitData {
+    heads: Mutex<RecordHeads>,
+}
+
+impl VTab for ReadWarcTypes {
+    type InitData = ReadWarcTypesInitData;
+    type BindData = ReadWarcTypesBindData;
+
+    fn bind(bind: &BindInfo) -> Result<Self::BindData, Box<dyn Error>> {
+        bind.add_result_column("filename", LogicalTypeHandle::from(LogicalTypeId::Varchar));
+        bind.add_result_column("warc_type", LogicalTypeHandle::from(LogicalTypeId::Varchar));
+        bind.add_result_column("content_length", LogicalTypeHandle::from(LogicalTypeId::Bigint));
+
+        let path = bind.get_parameter(0).to_string();
+        Ok(ReadWarcTypesBindData { path })
+    }
+
+    fn init(init: &InitInfo) -> Result<Self::InitData, Box<dyn Error>> {
+        let bind_data = unsafe { &*init.get_bind_data::<ReadWarcTypesBindData>() };
+        let file = WarcFile::open(&bind_data.path).map_err(|e| format!("{}: {}", bind_data.path, e))?;
+        Ok(ReadWarcTypesInitData {
+            heads: Mutex::new(RecordHeads::new(file)),
+        })
+    }
+
+    fn func(func: &TableFunctionInfo<Self>, output: &mut DataChunkHandle) -> Result<(), Box<dyn Error>> {
+        let bind_data = func.get_bind_data();
+        let mut heads = func.get_init_data().heads.lock().map_err(|e| e.to_string())?;
+        let capacity = unsafe { ffi::duckdb_vector_size() } as usize;
+
+        let mut rows: Vec<RecordHead> = Vec::new();
+        while rows.len() < capacity {
+            match heads.next() {
+                Some(head) => rows.push(head.map_err(|e| format!("{}: {}", bind_data.path, e))?),
+                None => break,
+            }
+        }
+
+        let filename_vec = output.flat_vector(0);
+        let mut warc_type_vec = output.flat_vector(1);
+        let mut content_length_vec = output.flat_vector(2);
+
+        for (i, head) in rows.iter().enumerate() {
+            filename_vec.insert(i, bind_data.path.as_str());
+            match &head.warc_type {
+                Some(v) => warc_type_vec.insert(i, v.as_str()),
+                None => warc_type_vec.set_null(i),
+            }
+            content_length_vec.as_mut_slice::<i64>()[i] = head.content_length as i64;
+        }
+        output.set_len(rows.len());
+
+        Ok(())
+    }
+
+    fn parameters() -> Option<Vec<LogicalTypeHandle>> {
+        Some(vec![LogicalTypeHandle::from(LogicalTypeId::Varchar)])
+    }
+}
+
+/// Number of records of each WARC-Type in a stream of records
+///
+/// Only the header blocks are parsed; bodies are read past using their
+/// Content-Length. Records without a WARC-Type aren't counted.
+fn count_types<R: BufRead>(mut reader: R) -> io::Result<BTreeMap<String, i64>> {
+    let mut counts = BTreeMap::new();
+    while let Some((_, head)) = read_header_block(&mut reader)? {
+        io::copy(&mut (&mut reader).take(head.content_length), &mut io::sink())?;
+        if let Some(warc_type) = head.warc_type {
+            *counts.entry(warc_type).or_insert(0) += 1;
+        }
+    }
+    Ok(counts)
+}
+
+/// Record counts by WARC-Type of a blob holding one or more records, plain
+/// or gzip (one member per record or a single member); None when malformed
+pub(crate) fn blob_type_counts(data: &[u8]) -> Option<BTreeMap<String, i64>> {
+    let counts = if data.starts_with(&[0x1f, 0x8b]) {
+        count_types(BufReader::new(MultiGzDecoder::new(data)))
+    } else {
+        count_types(data)
+    };
+    counts.ok()
+}
+
+/// DuckDB scalar function counting the records of each type in a blob
+///
+/// Returns a MAP(VARCHAR, BIGINT) from WARC-Type to record count, or NULL
+/// when the blob isn't a sequence of WARC records.
+pub(crate) struct WarcTypeCounts;
+
+impl VScalar for WarcTypeCounts {
+    type State = ();
+
+    unsafe fn invoke(
+        _state: &Self::State,
+        input: &mut DataChunkHandle,
+        output: &mut dyn WritableVector,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut rows = Vec::with_capacity(input.len());
+        for_each_blob(input, 0, |_, raw_data| {
+            rows.push(raw_data.and_then(blob_type_counts));
+        });
+
+        // A MAP is a LIST of STRUCT(key, value)
+        let mut map_vector = output.list_vector();
+        let total: usize = rows.iter().flatten().map(BTreeMap::len).sum();
+        let entries = map_vector.struct_child(total);
+        let key_vec = entries.child(0, total);
+        let mut value_vec = entries.child(1, total);
+
+        let mut offset = 0;
+        for (i, counts) in rows.iter().enumerate() {
+            let Some(counts) = counts else {
+                map_vector.set_null(i);
+                continue;
+            };
+            map_vector.set_entry(i, offset, counts.len());
+            for (warc_type, count) in counts {
+                key_vec.insert(offset, warc_type.as_str());
+                value_vec.as_mut_slice::<i64>()[offset] = *count;
+                offset += 1;
+            }
+        }
+        map_vector.set_len(total);
+
+        Ok(())
+    }
+
+    fn signatures() -> Vec<ScalarFunctionSignature> {
+        let make_return_type = || {
+            LogicalTypeHandle::map(
+                &LogicalTypeHandle::from(LogicalTypeId::Varchar),
+                &LogicalTypeHandle::from(LogicalTypeId::Bigint),
+            )
+        };
+
+        vec![
+            ScalarFunctionSignature::exact(vec![LogicalTypeHandle::from(LogicalTypeId::Blob)], make_return_type()),
+            ScalarFunctionSignature::exact(
+                vec![LogicalTypeHandle::from(LogicalTypeId::Varchar)],
+                make_return_type(),
+            ),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::warc_file::tests::warc_record;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    #[test]
+    fn test_blob_type_counts() {
+        let records = [
+            warc_record("request", b"GET / HTTP/1.1\r\n\r\n"),
+            warc_record("response", b"HTTP/1.1 200 OK\r\n\r\nWARC/1.0 in the body"),
+            warc_record("metadata", b"fetchTimeMs: 12\r\n"),
+            warc_record("response", b"HTTP/1.1 301 Moved Permanently\r\n\r\n"),
+        ];
+        let expected = BTreeMap::from([
+            ("metadata".to_string(), 1),
+            ("request".to_string(), 1),
+            ("response".to_string(), 2),
+        ]);
+        assert_eq!(blob_type_counts(&records.concat()), Some(expected.clone()));
+
+        // One gzip member per record, as in Common Crawl files
+        let gzipped: Vec<u8> = records
+            .iter()
+            .flat_map(|record| {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(record).unwrap();
+                encoder.finish().unwrap()
+            })
+            .collect();
+        assert_eq!(blob_type_counts(&gzipped), Some(expected));
+
+        assert_eq!(blob_type_counts(b"not a warc record"), None);
+        assert_eq!(blob_type_counts(b""), Some(BTreeMap::new()));
+    }
+}
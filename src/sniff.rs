@@ -0,0 +1,104 @@
+/// MIME type of a body recognized by its leading bytes
+///
+/// Only formats with an unambiguous signature are recognized, plus HTML
+/// documents starting with a doctype or `<html>` tag (after whitespace and
+/// a UTF-8 BOM). Other bodies, plain text included, give None.
+pub(crate) fn sniff_content_type(body: &[u8]) -> Option<&'static str> {
+    const SIGNATURES: &[(&[u8], &str)] = &[
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"%PDF-", "application/pdf"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"\x1f\x8b", "application/gzip"),
+        (b"wOFF", "font/woff"),
+        (b"wOF2", "font/woff2"),
+        (b"ID3", "audio/mpeg"),
+        (b"OggS", "application/ogg"),
+        (b"\x1aE\xdf\xa3", "video/webm"),
+    ];
+    if let Some((_, mime)) = SIGNATURES.iter().find(|(magic, _)| body.starts_with(magic)) {
+        return Some(mime);
+    }
+    if body.len() >= 12 && body.starts_with(b"RIFF") && &body[8..12] == b"WEBP" {
+        return Some("image/webp");
+    }
+    if body.len() >= 12 && &body[4..8] == b"ftyp" {
+        return Some("video/mp4");
+    }
+
+    let text = body.strip_prefix(b"\xef\xbb\xbf").unwrap_or(body).trim_ascii_start();
+    let starts_with = |tag: &[u8]| text.len() >= tag.len() && text[..tag.len()].eq_ignore_ascii_case(tag);
+    if starts_with(b"<!doctype html") || starts_with(b"<html") {
+        return Some("text/html");
+    }
+    None
+}
+
+/// Broad kind of a MIME type, so that types only differing in dialect agree
+///
+/// Text formats (`text/*`, HTML, XML, JSON, JavaScript) are one kind, as are
+/// archives; images, audio, video and fonts go by their top-level type; PDF
+/// is its own kind. None for generic or missing types
+/// (`application/octet-stream`, `unknown`), which agree with anything.
+fn content_kind(content_type: &str) -> Option<String> {
+    let mime = content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+    let (top, sub) = mime.split_once('/').unwrap_or((&mime, ""));
+    let kind = match (top, sub) {
+        ("", _) | (_, "") | ("application", "octet-stream") | ("unknown", _) | ("*", _) => return None,
+        ("text", _) => "text",
+        (_, sub) if ["html", "xml", "json", "javascript", "ecmascript"].iter().any(|t| sub.contains(t)) => "text",
+        ("image" | "audio" | "video" | "font", _) => top,
+        ("application", "ogg") => "audio",
+        ("application", "pdf" | "x-pdf") => "pdf",
+        ("application", "zip" | "gzip" | "x-gzip" | "x-tar" | "x-7z-compressed" | "x-rar-compressed") => "archive",
+        ("application", sub) if sub.starts_with("font") || sub.contains("woff") => "font",
+        _ => return Some(mime),
+    };
+    Some(kind.to_string())
+}
+
+/// Whether the declared, identified and sniffed types of a body disagree
+///
+/// Each of the HTTP Content-Type, the `WARC-Identified-Payload-Type` and the
+/// sniffed type is reduced to its kind (see [`content_kind`]); missing and
+/// generic types are left out. The types disagree when two kinds remain that
+/// differ, e.g. `text/html` declared for a body that sniffs as `image/jpeg`.
+/// `image/jpeg` declared for a PNG, or `text/plain` for HTML, agree.
+pub(crate) fn content_type_mismatch(declared: Option<&str>, identified: Option<&str>, sniffed: Option<&str>) -> bool {
+    let mut kinds = [declared, identified, sniffed].into_iter().flatten().filter_map(content_kind);
+    let Some(first) = kinds.next() else {
+        return false;
+    };
+    kinds.any(|kind| kind != first)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sniff_content_type() {
+        assert_eq!(sniff_content_type(b"\xff\xd8\xff\xe0\x00\x10JFIF"), Some("image/jpeg"));
+        assert_eq!(sniff_content_type(b"RIFF\x24\x00\x00\x00WEBPVP8 "), Some("image/webp"));
+        assert_eq!(sniff_content_type(b"\xef\xbb\xbf\r\n  <!DOCTYPE HTML><html>"), Some("text/html"));
+        assert_eq!(sniff_content_type(b"\x00\x00\x00\x18ftypmp42"), Some("video/mp4"));
+        assert_eq!(sniff_content_type(b"Just some text"), None);
+        assert_eq!(sniff_content_type(b""), None);
+    }
+
+    #[test]
+    fn test_content_type_mismatch() {
+        assert!(content_type_mismatch(Some("text/html; charset=utf-8"), None, Some("image/jpeg")));
+        assert!(content_type_mismatch(Some("image/png"), Some("application/pdf"), None));
+        assert!(content_type_mismatch(Some("application/x-unknown"), None, Some("text/html")));
+
+        // Same kind, generic or missing types don't disagree
+        assert!(!content_type_mismatch(Some("text/plain"), Some("application/xhtml+xml"), Some("text/html")));
+        assert!(!content_type_mismatch(Some("image/jpeg"), Some("image/jpeg"), Some("image/png")));
+        assert!(!content_type_mismatch(Some("application/octet-stream"), None, Some("application/pdf")));
+        assert!(!content_type_mismatch(Some("IMAGE/JPEG"), None, None));
+        assert!(!content_type_mismatch(None, None, None));
+    }
+}
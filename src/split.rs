@@ -0,0 +1,126 @@
+use crate::for_each_blob;
+use crate::warc_file::read_header_block;
+use duckdb::{
+    core::{DataChunkHandle, Inserter, LogicalTypeHandle, LogicalTypeId},
+    vscalar::{ScalarFunctionSignature, VScalar},
+    vtab::arrow::WritableVector,
+};
+use flate2::read::MultiGzDecoder;
+use std::error::Error;
+use std::io::{self, BufRead, BufReader, Read};
+
+/// The bytes of each record in a stream of records
+///
+/// A record runs from its `WARC/` line through its block and the blank lines
+/// after it, byte for byte. Fails when a header block has no Content-Length
+/// or the stream ends inside a record.
+fn split_records<R: BufRead>(mut reader: R) -> io::Result<Vec<Vec<u8>>> {
+    let mut records = Vec::new();
+    while let Some((mut record, head)) = read_header_block(&mut reader)? {
+        let block_start = record.len();
+        (&mut reader).take(head.content_length).read_to_end(&mut record)?;
+        if ((record.len() - block_start) as u64) < head.content_length {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        // The CRLF CRLF ending the record, however many line breaks there are
+        loop {
+            let buffer = reader.fill_buf()?;
+            let line_breaks = buffer.iter().take_while(|&&b| b == b'\r' || b == b'\n').count();
+            if line_breaks == 0 {
+                break;
+            }
+            record.extend_from_slice(&buffer[..line_breaks]);
+            reader.consume(line_breaks);
+        }
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// Records of a blob holding one or more records, plain or gzip (one member
+/// per record or a single member), decompressed; None when malformed
+pub(crate) fn blob_records(data: &[u8]) -> Option<Vec<Vec<u8>>> {
+    let records = if data.starts_with(&[0x1f, 0x8b]) {
+        split_records(BufReader::new(MultiGzDecoder::new(data)))
+    } else {
+        split_records(data)
+    };
+    records.ok()
+}
+
+/// DuckDB scalar function splitting a multi-record blob into its records
+///
+/// Returns a LIST(BLOB) of the uncompressed bytes of each record, or NULL
+/// when the blob isn't a sequence of WARC records.
+pub(crate) struct WarcSplit;
+
+impl VScalar for WarcSplit {
+    type State = ();
+
+    unsafe fn invoke(
+        _state: &Self::State,
+        input: &mut DataChunkHandle,
+        output: &mut dyn WritableVector,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut rows = Vec::with_capacity(input.len());
+        for_each_blob(input, 0, |_, raw_data| {
+            rows.push(raw_data.and_then(blob_records));
+        });
+
+        let mut list_vector = output.list_vector();
+        let total: usize = rows.iter().flatten().map(Vec::len).sum();
+        let record_vec = list_vector.child(total);
+
+        let mut offset = 0;
+        for (i, records) in rows.iter().enumerate() {
+            let Some(records) = records else {
+                list_vector.set_null(i);
+                continue;
+            };
+            list_vector.set_entry(i, offset, records.len());
+            for record in records {
+                record_vec.insert(offset, record.as_slice());
+                offset += 1;
+            }
+        }
+        list_vector.set_len(total);
+
+        Ok(())
+    }
+
+    fn signatures() -> Vec<ScalarFunctionSignature> {
+        let make_return_type = || LogicalTypeHandle::list(&LogicalTypeHandle::from(LogicalTypeId::Blob));
+        vec![
+            ScalarFunctionSignature::exact(vec![LogicalTypeHandle::from(LogicalTypeId::Blob)], make_return_type()),
+            ScalarFunctionSignature::exact(
+                vec![LogicalTypeHandle::from(LogicalTypeId::Varchar)],
+                make_return_type(),
+            ),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gzip::gzip_compress;
+    use crate::warc_file::tests::warc_record;
+
+    #[test]
+    fn test_split_two_records() {
+        let request = warc_record("request", b"GET / HTTP/1.1\r\n\r\n");
+        let response = warc_record("response", b"HTTP/1.1 200 OK\r\n\r\nWARC/1.0 in the body\r\n\r\n");
+        let records = blob_records(&[request.as_slice(), &response].concat()).unwrap();
+        assert_eq!(records, [request.clone(), response.clone()]);
+
+        // One gzip member per record, split into the uncompressed records
+        let gzipped = [gzip_compress(&request).unwrap(), gzip_compress(&response).unwrap()].concat();
+        assert_eq!(blob_records(&gzipped).unwrap(), [request.clone(), response]);
+
+        // A record cut short, or with no Content-Length, isn't split
+        assert_eq!(blob_records(&request[..request.len() - 8]), None);
+        assert_eq!(blob_records(b"WARC/1.0\r\nWARC-Type: request\r\n\r\n"), None);
+        assert_eq!(blob_records(b""), Some(vec![]));
+    }
+}
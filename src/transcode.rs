@@ -0,0 +1,99 @@
+use crate::warc_fields::charset;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+
+/// How far into a body an HTML `<meta charset>` is looked for, as browsers do
+const META_CHARSET_PREFIX: usize = 1024;
+
+/// Encoding named by a `body_charset` label (`utf-8`, `latin1`, `shift_jis`, ...)
+///
+/// Labels are the WHATWG Encoding ones. UTF-16 has no encoder there, so it is
+/// rejected like an unknown label.
+pub(crate) fn target_encoding(label: &str) -> Result<&'static Encoding, String> {
+    match Encoding::for_label(label.trim().as_bytes()) {
+        Some(encoding) if encoding.output_encoding() == encoding => Ok(encoding),
+        Some(_) => Err(format!("body_charset: cannot encode to \"{}\"", label)),
+        None => Err(format!("body_charset: unknown encoding \"{}\"", label)),
+    }
+}
+
+/// Whether a Content-Type is text: `text/*`, or an HTML, XML or JSON type
+pub(crate) fn is_text_content_type(content_type: &str) -> bool {
+    let content_type = content_type.to_ascii_lowercase();
+    content_type.starts_with("text/") || ["html", "xml", "json"].iter().any(|t| content_type.contains(t))
+}
+
+/// Encoding a text body is written in
+///
+/// The Content-Type charset wins, then a byte order mark, then an HTML
+/// `<meta charset>` (or `http-equiv` Content-Type) near the start. UTF-8 when
+/// none is found or the label is unknown.
+pub(crate) fn source_encoding(content_type: Option<&str>, body: &[u8]) -> &'static Encoding {
+    content_type
+        .and_then(charset)
+        .and_then(|label| Encoding::for_label(label.as_bytes()))
+        .or_else(|| Encoding::for_bom(body).map(|(encoding, _)| encoding))
+        .or_else(|| meta_charset(&body[..body.len().min(META_CHARSET_PREFIX)]))
+        .unwrap_or(UTF_8)
+}
+
+/// Encoding of the first `charset=` in an HTML head
+fn meta_charset(head: &[u8]) -> Option<&'static Encoding> {
+    let start = head.windows(8).position(|w| w.eq_ignore_ascii_case(b"charset="))? + 8;
+    let value = &head[start..];
+    let value = value.strip_prefix(b"\"").or_else(|| value.strip_prefix(b"'")).unwrap_or(value);
+    let end = value
+        .iter()
+        .position(|&b| matches!(b, b'"' | b'\'' | b';' | b'/' | b'>') || b.is_ascii_whitespace())
+        .unwrap_or(value.len());
+    let encoding = Encoding::for_label(&value[..end])?;
+    // A document that could declare its charset in ASCII isn't UTF-16 (HTML spec)
+    Some(if encoding == UTF_16LE || encoding == UTF_16BE { UTF_8 } else { encoding })
+}
+
+/// Re-encode a text body from its source encoding into `target`
+///
+/// Invalid input bytes become U+FFFD; characters `target` can't represent
+/// become numeric character references (`&#8364;`).
+pub(crate) fn transcode(body: &[u8], content_type: Option<&str>, target: &'static Encoding) -> Vec<u8> {
+    let (text, _, _) = source_encoding(content_type, body).decode(body);
+    let (encoded, _, _) = target.encode(&text);
+    encoded.into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encoding_rs::WINDOWS_1252;
+
+    #[test]
+    fn test_transcode_latin1_to_utf8() {
+        let latin1 = b"<p>Caf\xe9 cr\xe8me \x80 10</p>";
+        let utf8 = transcode(latin1, Some("text/html; charset=ISO-8859-1"), UTF_8);
+        assert_eq!(String::from_utf8(utf8).unwrap(), "<p>Café crème € 10</p>");
+
+        // Declared in the document instead of the header
+        let html = b"<html><head><meta charset=\"latin1\"><title>Gr\xfc\xdfe</title>";
+        assert_eq!(source_encoding(Some("text/html"), html), WINDOWS_1252);
+        let utf8 = transcode(html, Some("text/html"), UTF_8);
+        assert_eq!(String::from_utf8(utf8).unwrap(), "<html><head><meta charset=\"latin1\"><title>Grüße</title>");
+
+        // And back, with characters latin1 lacks as character references
+        assert_eq!(transcode("Café ✓".as_bytes(), Some("text/plain"), WINDOWS_1252), b"Caf\xe9 &#10003;");
+    }
+
+    #[test]
+    fn test_source_encoding_and_targets() {
+        assert_eq!(source_encoding(None, b"\xef\xbb\xbfplain"), UTF_8);
+        assert_eq!(source_encoding(None, b"\xff\xfep\x00"), UTF_16LE);
+        assert_eq!(source_encoding(Some("text/html; charset=bogus"), b"x"), UTF_8);
+        assert_eq!(source_encoding(None, b"<meta http-equiv=Content-Type content='text/html; charset=utf-16'>"), UTF_8);
+
+        assert_eq!(target_encoding(" UTF8 "), Ok(UTF_8));
+        assert_eq!(target_encoding("latin1"), Ok(WINDOWS_1252));
+        assert!(target_encoding("utf-16").is_err());
+        assert!(target_encoding("klingon").is_err());
+
+        assert!(is_text_content_type("application/xhtml+xml"));
+        assert!(!is_text_content_type("image/png"));
+    }
+}
@@ -0,0 +1,170 @@
+use crate::{sanitize_for_ffi, sanitize_header};
+use encoding_rs::{Encoding, UTF_8};
+use std::borrow::Cow;
+
+/// Charset parameter of a Content-Type value, lowercased and unquoted
+pub(crate) fn charset(content_type: &str) -> Option<String> {
+    content_type.split(';').skip(1).find_map(|parameter| {
+        let (name, value) = parameter.split_once('=')?;
+        name.trim()
+            .eq_ignore_ascii_case("charset")
+            .then(|| value.trim().trim_matches('"').to_ascii_lowercase())
+    })
+}
+
+/// Decode text in a declared charset
+///
+/// Charsets are the WHATWG Encoding labels, so Latin-1 and ASCII decode as
+/// windows-1252, as browsers do. An unknown or no charset decodes as UTF-8;
+/// invalid sequences become U+FFFD.
+pub(crate) fn decode_text<'a>(bytes: &'a [u8], charset: Option<&str>) -> Cow<'a, str> {
+    let encoding = charset.and_then(|label| Encoding::for_label(label.as_bytes())).unwrap_or(UTF_8);
+    encoding.decode(bytes).0
+}
+
+/// Fields of an `application/warc-fields` block, in order, values of repeated
+/// names collected under the first occurrence
+///
+/// The block is decoded in the charset of `content_type`, UTF-8 by default,
+/// and lines starting with whitespace continue the previous value. None when
+/// the block isn't declared as `application/warc-fields`.
+fn parse_warc_fields(block: &[u8], content_type: Option<&str>) -> Option<Vec<(String, Vec<String>)>> {
+    let content_type = content_type?;
+    let essence = content_type.split(';').next().unwrap_or("").trim();
+    if !essence.eq_ignore_ascii_case("application/warc-fields") {
+        return None;
+    }
+
+    let text = decode_text(block, charset(content_type).as_deref());
+    let mut fields: Vec<(String, Vec<String>)> = Vec::new();
+    let mut last: Option<usize> = None;
+    for line in text.lines() {
+        if line.starts_with([' ', '\t']) {
+            if let Some(value) = last.and_then(|last| fields[last].1.last_mut()) {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+            continue;
+        }
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let (name, value) = (name.trim(), value.trim().to_string());
+        let index = match fields.iter().position(|(existing, _)| existing == name) {
+            Some(index) => {
+                fields[index].1.push(value);
+                index
+            }
+            None => {
+                fields.push((name.to_string(), vec![value]));
+                fields.len() - 1
+            }
+        };
+        last = Some(index);
+    }
+    Some(fields)
+}
+
+/// Parse an `application/warc-fields` block (warcinfo, metadata) into a JSON map
+///
+/// Repeated fields (e.g. metadata `outlink`s) are joined with "\n". None when
+/// the block isn't declared as `application/warc-fields`.
+pub(crate) fn warc_fields_to_json(block: &[u8], content_type: Option<&str>) -> Option<String> {
+    let pairs: Vec<String> = parse_warc_fields(block, content_type)?
+        .iter()
+        .map(|(name, values)| {
+            format!("\"{}\": \"{}\"", sanitize_header(name), sanitize_header(&values.join("\n")))
+        })
+        .collect();
+    Some(format!("{{{}}}", pairs.join(", ")))
+}
+
+/// Links a crawler recorded in a metadata record (Heritrix `outlink:` and `via:` fields)
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct MetadataLinks {
+    pub outlinks: Vec<String>, // Discovered URLs, without the hop type and context
+    pub via: Option<String>,   // URL the record's target was discovered from
+}
+
+/// Outlinks and via of a metadata record's `application/warc-fields` block
+///
+/// Heritrix writes each outlink as `<url> <hop type> <context>` (e.g.
+/// `http://example.com/ L a/@href`); only the URL is kept. None when the
+/// block isn't declared as `application/warc-fields`.
+pub(crate) fn metadata_links(block: &[u8], content_type: Option<&str>) -> Option<MetadataLinks> {
+    let fields = parse_warc_fields(block, content_type)?;
+    let values = |name: &str| {
+        fields
+            .iter()
+            .find(|(field, _)| field.eq_ignore_ascii_case(name))
+            .map(|(_, values)| values.as_slice())
+            .unwrap_or_default()
+    };
+    let url = |value: &String| value.split_whitespace().next().map(sanitize_for_ffi);
+    Some(MetadataLinks {
+        outlinks: values("outlink").iter().filter_map(url).collect(),
+        via: values("via").first().and_then(url),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_warc_fields_to_json_utf8() {
+        let block = "software: Heritrix/3.4.0\r\noperator: Zoë Łukasiewicz – Archiv Ü\r\n\
+                     description: Crawl of\r\n  regional news\r\n";
+        let json = warc_fields_to_json(block.as_bytes(), Some("application/warc-fields")).unwrap();
+        assert_eq!(
+            json,
+            "{\"software\": \"Heritrix/3.4.0\", \"operator\": \"Zoë Łukasiewicz – Archiv Ü\", \
+             \"description\": \"Crawl of regional news\"}"
+        );
+
+        // Repeated fields keep every value
+        let json = warc_fields_to_json(b"outlink: http://a/\r\noutlink: http://b/,c\r\n", Some("application/warc-fields"));
+        assert_eq!(json.as_deref(), Some("{\"outlink\": \"http://a/\\nhttp://b/,c\"}"));
+
+        assert_eq!(warc_fields_to_json(b"a: b\r\n", Some("text/plain")), None);
+        assert_eq!(warc_fields_to_json(b"a: b\r\n", None), None);
+    }
+
+    #[test]
+    fn test_warc_fields_to_json_declared_charset() {
+        let block = b"operator: Zo\xeb M\xfcller \x96 Archiv\r\n";
+        let json = warc_fields_to_json(block, Some("application/warc-fields; charset=\"ISO-8859-1\"")).unwrap();
+        assert_eq!(json, "{\"operator\": \"Zoë Müller – Archiv\"}");
+
+        // The same bytes read as UTF-8 only lose the invalid sequences
+        let json = warc_fields_to_json(block, Some("application/warc-fields")).unwrap();
+        assert_eq!(json, "{\"operator\": \"Zo\u{fffd} M\u{fffd}ller \u{fffd} Archiv\"}");
+
+        // Single-byte Cyrillic and multi-byte Japanese
+        let block = b"operator: \xc0\xf0\xf5\xe8\xe2\r\n";
+        let json = warc_fields_to_json(block, Some("application/warc-fields; charset=windows-1251"));
+        assert_eq!(json.as_deref(), Some("{\"operator\": \"Архив\"}"));
+        let block = b"operator: \x83\x41\x81\x5b\x83\x4a\x83\x43\x83\x75\r\n";
+        let json = warc_fields_to_json(block, Some("application/warc-fields; charset=Shift_JIS"));
+        assert_eq!(json.as_deref(), Some("{\"operator\": \"アーカイブ\"}"));
+    }
+
+    #[test]
+    fn test_metadata_links() {
+        let block = "via: http://example.com/\r\nhopsFromSeed: L\r\nfetchTimeMs: 120\r\n\
+                     outlink: http://example.com/about L a/@href\r\n\
+                     outlink: http://example.com/style.css E link/@href\r\n\
+                     outlink: http://cdn.example.com/app.js E script/@src\r\n";
+        let links = metadata_links(block.as_bytes(), Some("application/warc-fields")).unwrap();
+        assert_eq!(
+            links.outlinks,
+            ["http://example.com/about", "http://example.com/style.css", "http://cdn.example.com/app.js"]
+        );
+        assert_eq!(links.via.as_deref(), Some("http://example.com/"));
+
+        // A seed has no via; other blocks have no links at all
+        let links = metadata_links(b"fetchTimeMs: 5\r\n", Some("application/warc-fields")).unwrap();
+        assert_eq!(links, MetadataLinks::default());
+        assert_eq!(metadata_links(b"outlink: http://a/\r\n", Some("text/plain")), None);
+    }
+}
@@ -0,0 +1,980 @@
+use crate::http_date::parse_record_date;
+use flate2::read::MultiGzDecoder;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// The bytes behind a `WarcFile`: a regular file, or a stream such as
+/// `/dev/stdin` or a named pipe that can only be read in order
+pub(crate) enum Source {
+    File(File),
+    Stream(Box<dyn Read + Send>),
+}
+
+impl Read for Source {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Source::File(file) => file.read(buf),
+            Source::Stream(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Seek for Source {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match self {
+            Source::File(file) => file.seek(pos),
+            Source::Stream(_) => Err(io::Error::new(io::ErrorKind::Unsupported, "cannot seek in a stream")),
+        }
+    }
+}
+
+/// A WARC file opened for sequential reading, transparently decompressing gzip
+///
+/// gzip input is decoded as one multistream: members are concatenated before
+/// records are parsed, so a record split across members (non-conformant, but
+/// seen in the wild) still reads correctly. The cost is that a corrupt member
+/// ends the file, where `read_warc_members` would report it and move on.
+pub(crate) enum WarcFile {
+    Plain(BufReader<Source>),
+    Gzip(Box<BufReader<MultiGzDecoder<BufReader<Source>>>>),
+}
+
+impl WarcFile {
+    /// Open a path; anything but a regular file (a pipe, `/dev/stdin`) is
+    /// read as a stream
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = File::open(path)?;
+        if file.metadata()?.is_file() {
+            Self::new(Source::File(file))
+        } else {
+            Self::from_reader(file)
+        }
+    }
+
+    /// Read a stream incrementally, without a known length or seeking
+    pub fn from_reader<R: Read + Send + 'static>(reader: R) -> io::Result<Self> {
+        Self::new(Source::Stream(Box::new(reader)))
+    }
+
+    fn new(source: Source) -> io::Result<Self> {
+        let mut reader = BufReader::new(source);
+        if reader.fill_buf()?.starts_with(&[0x1f, 0x8b]) {
+            Ok(WarcFile::Gzip(Box::new(BufReader::new(MultiGzDecoder::new(reader)))))
+        } else {
+            Ok(WarcFile::Plain(reader))
+        }
+    }
+
+    /// How the file is decoded, as reported in `source_compression`
+    pub fn compression(&self) -> &'static str {
+        match self {
+            WarcFile::Plain(_) => "none",
+            WarcFile::Gzip(_) => "gzip",
+        }
+    }
+
+    /// Skip `n` bytes, seeking when the file is uncompressed and seekable
+    pub fn skip(&mut self, n: u64) -> io::Result<()> {
+        if let WarcFile::Plain(reader) = self {
+            if matches!(reader.get_ref(), Source::File(_)) {
+                return reader.seek_relative(n as i64);
+            }
+        }
+        let skipped = io::copy(&mut self.by_ref().take(n), &mut io::sink())?;
+        if skipped < n {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(())
+    }
+}
+
+impl Read for WarcFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            WarcFile::Plain(reader) => reader.read(buf),
+            WarcFile::Gzip(reader) => reader.read(buf),
+        }
+    }
+}
+
+impl BufRead for WarcFile {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        match self {
+            WarcFile::Plain(reader) => reader.fill_buf(),
+            WarcFile::Gzip(reader) => reader.fill_buf(),
+        }
+    }
+
+    fn consume(&mut self, amt: usize) {
+        match self {
+            WarcFile::Plain(reader) => reader.consume(amt),
+            WarcFile::Gzip(reader) => reader.consume(amt),
+        }
+    }
+}
+
+/// The header fields of a record needed without reading its body
+pub(crate) struct RecordHead {
+    pub warc_type: Option<String>,
+    pub warc_date: Option<String>,
+    pub content_length: u64,
+}
+
+/// Range of WARC-Date values to keep, in microseconds since the epoch
+///
+/// `from` is inclusive and `to` exclusive. Records with a missing or
+/// malformed WARC-Date fall outside any bounded range.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct DateRange {
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+}
+
+impl DateRange {
+    fn contains(&self, warc_date: Option<&str>) -> bool {
+        if self.from.is_none() && self.to.is_none() {
+            return true;
+        }
+        let Some(date) = warc_date.and_then(parse_record_date) else {
+            return false;
+        };
+        self.from.is_none_or(|from| date >= from) && self.to.is_none_or(|to| date < to)
+    }
+}
+
+/// Read the next record's header block, including its terminating blank line
+///
+/// Returns None at a clean end of file.
+pub(crate) fn read_header_block<R: BufRead>(file: &mut R) -> io::Result<Option<(Vec<u8>, RecordHead)>> {
+    let mut block = Vec::new();
+    Ok(read_header_block_into(file, &mut block)?.map(|head| (block, head)))
+}
+
+/// Like `read_header_block`, reading into `block` so the bytes read so far
+/// are kept when the file ends inside the header block
+fn read_header_block_into<R: BufRead>(file: &mut R, block: &mut Vec<u8>) -> io::Result<Option<RecordHead>> {
+    // Skip the blank lines terminating the previous record
+    loop {
+        block.clear();
+        if file.read_until(b'\n', block)? == 0 {
+            return Ok(None);
+        }
+        if !block.trim_ascii().is_empty() {
+            break;
+        }
+    }
+    if !block.starts_with(b"WARC/") {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "expected a WARC/ version line"));
+    }
+
+    let mut warc_type = None;
+    let mut warc_date = None;
+    let mut content_length = None;
+    loop {
+        let start = block.len();
+        if file.read_until(b'\n', block)? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let text = String::from_utf8_lossy(block[start..].trim_ascii());
+        if text.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = text.split_once(':') {
+            let name = name.trim();
+            if name.eq_ignore_ascii_case("WARC-Type") {
+                warc_type = Some(value.trim().to_string());
+            } else if name.eq_ignore_ascii_case("WARC-Date") {
+                warc_date = Some(value.trim().to_string());
+            } else if name.eq_ignore_ascii_case("Content-Length") {
+                content_length = value.trim().parse::<u64>().ok();
+            }
+        }
+    }
+
+    let content_length = content_length
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "record has no valid Content-Length"))?;
+
+    Ok(Some(RecordHead {
+        warc_type,
+        warc_date,
+        content_length,
+    }))
+}
+
+/// Iterator over record headers that skips each body using its Content-Length
+pub(crate) struct RecordHeads {
+    file: Option<WarcFile>,
+}
+
+impl RecordHeads {
+    pub fn new(file: WarcFile) -> Self {
+        Self { file: Some(file) }
+    }
+
+    fn read_head(file: &mut WarcFile) -> io::Result<Option<RecordHead>> {
+        let Some((_, head)) = read_header_block(file)? else {
+            return Ok(None);
+        };
+        file.skip(head.content_length)?;
+        Ok(Some(head))
+    }
+}
+
+impl Iterator for RecordHeads {
+    type Item = io::Result<RecordHead>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let file = self.file.as_mut()?;
+        match Self::read_head(file) {
+            Ok(Some(head)) => Some(Ok(head)),
+            Ok(None) => {
+                self.file = None;
+                None
+            }
+            Err(e) => {
+                self.file = None;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// A reader that can put bytes back in front of the stream
+struct Pushback<R> {
+    pending: Vec<u8>,
+    pos: usize,
+    inner: R,
+}
+
+impl<R: BufRead> Pushback<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            pending: Vec::new(),
+            pos: 0,
+            inner,
+        }
+    }
+
+    /// Make `bytes` the next bytes read, ahead of anything already pending
+    fn push_back(&mut self, bytes: &[u8]) {
+        let mut pending = bytes.to_vec();
+        pending.extend_from_slice(&self.pending[self.pos..]);
+        self.pending = pending;
+        self.pos = 0;
+    }
+}
+
+impl Pushback<WarcFile> {
+    /// Skip `n` bytes, the pending ones first, then seeking when the file can
+    fn skip(&mut self, n: u64) -> io::Result<()> {
+        let pending = (self.pending.len() - self.pos).min(n.try_into().unwrap_or(usize::MAX));
+        self.pos += pending;
+        self.inner.skip(n - pending as u64)
+    }
+}
+
+impl<R: BufRead> Read for Pushback<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.fill_buf()?.read(buf)?;
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl<R: BufRead> BufRead for Pushback<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.pos < self.pending.len() {
+            return Ok(&self.pending[self.pos..]);
+        }
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        if self.pos < self.pending.len() {
+            self.pos += amt;
+        } else {
+            self.inner.consume(amt);
+        }
+    }
+}
+
+/// Iterator over the complete records of a WARC file
+///
+/// Each item is a single record (header block, body and the trailing blank
+/// lines), ready for `parse_warc_record`. A record whose Content-Length
+/// doesn't match its block is cut or skipped to the next `WARC/` line, so one
+/// bad length doesn't corrupt the records after it.
+///
+/// A file that ends mid-record, like a partial download or a gzip member
+/// cut short, yields what was read of its last record and then ends; see
+/// `truncated`.
+///
+/// A record whose header block is invalid (e.g. no Content-Length) yields an
+/// error and reading resumes at the next `WARC/` line. Errors reading the
+/// file itself, like corrupt gzip data, end the iteration.
+pub(crate) struct Records {
+    file: Option<Pushback<WarcFile>>,
+    date_range: DateRange,
+    trust_content_length: bool,
+    truncated: bool,
+    records_read: u64, // Records read so far, including those outside the date range
+}
+
+impl Records {
+    pub fn new(file: WarcFile) -> Self {
+        Self {
+            file: Some(Pushback::new(file)),
+            date_range: DateRange::default(),
+            trust_content_length: true,
+            truncated: false,
+            records_read: 0,
+        }
+    }
+
+    /// 0-based position in the file of the last record returned
+    ///
+    /// Records outside the date range and records that can't be read count
+    /// too, so a record keeps its index whatever the range.
+    pub fn index(&self) -> u64 {
+        self.records_read.saturating_sub(1)
+    }
+
+    /// Whether the last record returned was cut off by the end of the file
+    pub fn truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Only yield records whose WARC-Date falls in `date_range`; the others
+    /// are passed over on their header alone, without being parsed
+    pub fn with_date_range(mut self, date_range: DateRange) -> Self {
+        self.date_range = date_range;
+        self
+    }
+
+    /// Whether a block runs for its declared Content-Length (the default), or
+    /// up to the next `WARC/` version line after a blank line
+    ///
+    /// Not trusting the length salvages files from writers that declare
+    /// `Content-Length: 0` for a non-empty block, or a length for a block
+    /// they never wrote. The Content-Length header of each record returned is
+    /// rewritten to the length found.
+    pub fn with_trust_content_length(mut self, trust_content_length: bool) -> Self {
+        self.trust_content_length = trust_content_length;
+        self
+    }
+
+    /// Read the next record in the date range, and whether the file ended
+    /// before it was complete
+    ///
+    /// `records_read` counts every record started, whether it is returned or not.
+    fn read_record(
+        file: &mut Pushback<WarcFile>,
+        date_range: &DateRange,
+        trust_content_length: bool,
+        records_read: &mut u64,
+    ) -> io::Result<Option<(Vec<u8>, bool)>> {
+        loop {
+            let mut record = Vec::new();
+            let head = read_header_block_into(file, &mut record);
+            if !record.trim_ascii().is_empty() {
+                *records_read += 1;
+            }
+            let head = match head {
+                Ok(Some(head)) => head,
+                Ok(None) => return Ok(None),
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                    // The WARC-Date may not have been read: keep the partial
+                    // header only when no range is set
+                    let keep = !record.trim_ascii().is_empty() && date_range.contains(None);
+                    return Ok(keep.then_some((record, true)));
+                }
+                Err(e) => return Err(e),
+            };
+            let header_len = record.len();
+            let in_range = date_range.contains(head.warc_date.as_deref());
+            if !trust_content_length {
+                let record = Self::read_block_to_next_record(file, record, header_len)?;
+                if !in_range {
+                    continue;
+                }
+                return Ok(Some((record, false)));
+            }
+            if !in_range {
+                // Skipped without reading the block into memory (seeking in
+                // plain files). A Content-Length that is too short leaves the
+                // end of the block, skipped to the next `WARC/` line; one that
+                // is too long loses the records it runs into.
+                match file.skip(head.content_length) {
+                    Ok(()) => {}
+                    Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+                    Err(e) => return Err(e),
+                }
+                if !Self::record_follows(file)? && !Self::skip_to_next_record(file)? {
+                    return Ok(None);
+                }
+                continue;
+            }
+            let cut_off = match file.take(head.content_length).read_to_end(&mut record) {
+                Ok(_) => false,
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => true,
+                Err(e) => return Err(e),
+            };
+            let complete = !cut_off && (record.len() - header_len) as u64 == head.content_length;
+            let truncated = if cut_off {
+                true
+            } else if !complete || !Self::record_follows(file)? {
+                !Self::resync(file, &mut record, header_len, complete)?
+            } else {
+                false
+            };
+            if !truncated {
+                record.extend_from_slice(b"\r\n\r\n");
+            }
+            return Ok(Some((record, truncated)));
+        }
+    }
+
+    /// Read a block up to the next `WARC/` version line following a blank
+    /// line, or the end of the file, whatever its Content-Length says
+    ///
+    /// The version line is put back, and the record returned with its
+    /// Content-Length set to the block read.
+    fn read_block_to_next_record(
+        file: &mut Pushback<WarcFile>,
+        mut record: Vec<u8>,
+        header_len: usize,
+    ) -> io::Result<Vec<u8>> {
+        loop {
+            let start = record.len();
+            if file.read_until(b'\n', &mut record)? == 0 {
+                break;
+            }
+            let after_blank_line = start == header_len || record[..start].ends_with(b"\n\r\n");
+            if after_blank_line && is_version_line(&record[start..]) {
+                file.push_back(&record[start..]);
+                record.truncate(start);
+                break;
+            }
+        }
+        // The CRLF CRLF ending the record isn't part of the block
+        let mut block_end = record.len();
+        for separator in [&b"\r\n\r\n"[..], b"\n\n"] {
+            if record[header_len..].ends_with(separator) {
+                block_end -= separator.len();
+                break;
+            }
+        }
+
+        let mut rewritten = set_content_length(&record[..header_len], block_end - header_len);
+        rewritten.extend_from_slice(&record[header_len..block_end]);
+        rewritten.extend_from_slice(b"\r\n\r\n");
+        Ok(rewritten)
+    }
+
+    /// Whether the next non-blank line is a `WARC/` version line or the file
+    /// ends there; reads nothing
+    fn record_follows(file: &mut Pushback<WarcFile>) -> io::Result<bool> {
+        let mut peeked = Vec::new();
+        let follows = loop {
+            let start = peeked.len();
+            if file.read_until(b'\n', &mut peeked)? == 0 {
+                break true;
+            }
+            let line = &peeked[start..];
+            if !line.trim_ascii().is_empty() {
+                break line.starts_with(b"WARC/");
+            }
+        };
+        file.push_back(&peeked);
+        Ok(follows)
+    }
+
+    /// Realign on the next record after a block whose length was wrong
+    ///
+    /// A Content-Length that is too long swallows the start of the next
+    /// record: the block is cut before the embedded `WARC/` line, which is put
+    /// back. One that is too short leaves the end of the block in the stream:
+    /// it is skipped up to the next `WARC/` line.
+    ///
+    /// Returns false when the block was short and no next record was found
+    /// in it: the file ended mid-record.
+    fn resync(
+        file: &mut Pushback<WarcFile>,
+        record: &mut Vec<u8>,
+        header_len: usize,
+        complete: bool,
+    ) -> io::Result<bool> {
+        let block = &record[header_len..];
+        let next_record = block
+            .windows(6)
+            .enumerate()
+            .find(|&(i, window)| {
+                window == b"\nWARC/" && (block[..i].ends_with(b"\n") || block[..i].ends_with(b"\n\r"))
+            })
+            .map(|(i, _)| i + 1);
+        if let Some(start) = next_record {
+            file.push_back(&block[start..]);
+            let end = header_len + block[..start].trim_ascii_end().len();
+            record.truncate(end);
+            return Ok(true);
+        }
+        if !complete {
+            return Ok(false);
+        }
+
+        Self::skip_to_next_record(file)?;
+        Ok(true)
+    }
+
+    /// Skip to the next `WARC/` line, which is put back; false when the file
+    /// ends first
+    fn skip_to_next_record(file: &mut Pushback<WarcFile>) -> io::Result<bool> {
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            if file.read_until(b'\n', &mut line)? == 0 {
+                return Ok(false);
+            }
+            if line.starts_with(b"WARC/") {
+                file.push_back(&line);
+                return Ok(true);
+            }
+        }
+    }
+}
+
+impl Iterator for Records {
+    type Item = io::Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let file = self.file.as_mut()?;
+        match Self::read_record(file, &self.date_range, self.trust_content_length, &mut self.records_read) {
+            Ok(Some((record, truncated))) => {
+                self.truncated = truncated;
+                if truncated {
+                    self.file = None;
+                }
+                Some(Ok(record))
+            }
+            Ok(None) => {
+                self.file = None;
+                None
+            }
+            Err(e) => {
+                // Only the bad record is lost: reading goes on from the next
+                // `WARC/` line, unless the stream itself can't be read further
+                self.truncated = false;
+                if !matches!(Self::skip_to_next_record(file), Ok(true)) {
+                    self.file = None;
+                }
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// Whether a line is a WARC version line alone, like `WARC/1.0`
+fn is_version_line(line: &[u8]) -> bool {
+    let Some(version) = line.trim_ascii_end().strip_prefix(b"WARC/") else {
+        return false;
+    };
+    let mut parts = version.split(|&b| b == b'.');
+    let is_number = |part: Option<&[u8]>| part.is_some_and(|p| !p.is_empty() && p.iter().all(u8::is_ascii_digit));
+    is_number(parts.next()) && is_number(parts.next()) && parts.next().is_none()
+}
+
+/// A header block with its Content-Length header set to `length`
+fn set_content_length(header_block: &[u8], length: usize) -> Vec<u8> {
+    let mut rewritten = Vec::with_capacity(header_block.len() + 8);
+    for line in header_block.split_inclusive(|&b| b == b'\n') {
+        let name = line.split(|&b| b == b':').next().unwrap_or_default();
+        if name.trim_ascii().eq_ignore_ascii_case(b"Content-Length") {
+            let line_end = if line.ends_with(b"\r\n") { "\r\n" } else { "\n" };
+            rewritten.extend_from_slice(format!("Content-Length: {}{}", length, line_end).as_bytes());
+        } else {
+            rewritten.extend_from_slice(line);
+        }
+    }
+    rewritten
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::http_date::parse_warc_date;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+    use std::path::PathBuf;
+
+    /// Build a WARC record with the given type and body
+    pub(crate) fn warc_record(warc_type: &str, body: &[u8]) -> Vec<u8> {
+        let mut record = format!(
+            "WARC/1.0\r\nWARC-Type: {}\r\nWARC-Date: 2025-11-06T20:10:40Z\r\nWARC-Record-ID: <urn:uuid:00000000-0000-0000-0000-000000000000>\r\nContent-Length: {}\r\n\r\n",
+            warc_type,
+            body.len()
+        )
+        .into_bytes();
+        record.extend_from_slice(body);
+        record.extend_from_slice(b"\r\n\r\n");
+        record
+    }
+
+    /// Write `data` to a unique temporary file
+    pub(crate) fn temp_file(name: &str, data: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("duckdb_warc_{}_{}", std::process::id(), name));
+        std::fs::write(&path, data).unwrap();
+        path
+    }
+
+    fn multi_record_warc() -> Vec<u8> {
+        [
+            warc_record("warcinfo", b"software: test\r\n"),
+            warc_record("request", b"GET / HTTP/1.1\r\n\r\n"),
+            warc_record("response", b"HTTP/1.1 200 OK\r\n\r\nWARC/1.0 looks like a header but is body"),
+            warc_record("metadata", b"fetchTimeMs: 12\r\n"),
+            warc_record("response", b"HTTP/1.1 404 Not Found\r\n\r\n"),
+        ]
+        .concat()
+    }
+
+    fn count_types(path: &Path) -> Vec<(String, usize)> {
+        let mut counts = std::collections::BTreeMap::new();
+        for head in RecordHeads::new(WarcFile::open(path).unwrap()) {
+            *counts.entry(head.unwrap().warc_type.unwrap()).or_insert(0) += 1;
+        }
+        counts.into_iter().collect()
+    }
+
+    #[test]
+    fn test_record_heads_counts_plain() {
+        let path = temp_file("types.warc", &multi_record_warc());
+        let counts = count_types(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            counts,
+            vec![
+                ("metadata".to_string(), 1),
+                ("request".to_string(), 1),
+                ("response".to_string(), 2),
+                ("warcinfo".to_string(), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_record_heads_counts_gzip_members() {
+        // One gzip member per record, as written by Common Crawl
+        let mut data = Vec::new();
+        for record in [warc_record("request", b"GET / HTTP/1.1\r\n\r\n"), warc_record("response", b"body")] {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(&record).unwrap();
+            data.extend(encoder.finish().unwrap());
+        }
+        let path = temp_file("types.warc.gz", &data);
+        let counts = count_types(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(counts, vec![("request".to_string(), 1), ("response".to_string(), 1)]);
+    }
+
+    #[test]
+    fn test_records_straddling_gzip_members() {
+        // Split the second record mid-body across two members
+        let data = multi_record_warc();
+        let first_len = warc_record("warcinfo", b"software: test\r\n").len();
+        let split = first_len + 60;
+        let mut gzipped = Vec::new();
+        for part in [&data[..split], &data[split..]] {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(part).unwrap();
+            gzipped.extend(encoder.finish().unwrap());
+        }
+        // Member by member, the second record is cut in two
+        let members: Vec<_> = crate::gzip::GzipMembers::new(gzipped.as_slice()).map(|m| m.unwrap()).collect();
+        assert_eq!(members.len(), 2);
+        assert!(!members[0].data.ends_with(b"\r\n\r\n"));
+
+        let path = temp_file("straddle.warc.gz", &gzipped);
+        let records: Vec<Vec<u8>> = Records::new(WarcFile::open(&path).unwrap()).map(|r| r.unwrap()).collect();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(records.len(), 5);
+        assert_eq!(records[1], warc_record("request", b"GET / HTTP/1.1\r\n\r\n"));
+    }
+
+    #[test]
+    fn test_records_reads_bodies() {
+        let path = temp_file("records.warc", &multi_record_warc());
+        let records: Vec<Vec<u8>> = Records::new(WarcFile::open(&path).unwrap()).map(|r| r.unwrap()).collect();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(records.len(), 5);
+        assert_eq!(
+            records[2],
+            warc_record("response", b"HTTP/1.1 200 OK\r\n\r\nWARC/1.0 looks like a header but is body")
+        );
+    }
+
+    /// A non-seekable reader handing out a few bytes per read, like a pipe
+    struct Trickle {
+        data: Vec<u8>,
+        pos: usize,
+    }
+
+    impl Read for Trickle {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = buf.len().min(7).min(self.data.len() - self.pos);
+            buf[..n].copy_from_slice(&self.data[self.pos..self.pos + n]);
+            self.pos += n;
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn test_records_from_stream() {
+        let data = multi_record_warc();
+        let mut gzipped = GzEncoder::new(Vec::new(), Compression::default());
+        gzipped.write_all(&data).unwrap();
+        let gzipped = gzipped.finish().unwrap();
+
+        for input in [data, gzipped] {
+            let stream = || WarcFile::from_reader(Trickle { data: input.clone(), pos: 0 }).unwrap();
+            let records: Vec<Vec<u8>> = Records::new(stream()).map(|r| r.unwrap()).collect();
+            assert_eq!(stream().compression(), if input.starts_with(&[0x1f, 0x8b]) { "gzip" } else { "none" });
+            assert_eq!(records.len(), 5);
+            assert_eq!(records[4], warc_record("response", b"HTTP/1.1 404 Not Found\r\n\r\n"));
+
+            // Bodies are skipped by reading through them
+            let types: Vec<String> = RecordHeads::new(stream()).map(|h| h.unwrap().warc_type.unwrap()).collect();
+            assert_eq!(types, vec!["warcinfo", "request", "response", "metadata", "response"]);
+        }
+    }
+
+    /// A record whose Content-Length header claims `declared` bytes
+    fn record_with_length(body: &[u8], declared: usize) -> Vec<u8> {
+        let record = warc_record("response", body);
+        let text = String::from_utf8(record).unwrap();
+        text.replace(&format!("Content-Length: {}\r\n", body.len()), &format!("Content-Length: {}\r\n", declared))
+            .into_bytes()
+    }
+
+    #[test]
+    fn test_records_resync_after_wrong_content_length() {
+        let good = warc_record("request", b"GET / HTTP/1.1\r\n\r\n");
+        let body = b"HTTP/1.1 200 OK\r\n\r\n0123456789";
+
+        // Too short: the rest of the block is skipped
+        let data = [record_with_length(body, 20), good.clone(), good.clone()].concat();
+        let records: Vec<Vec<u8>> = Records::new(WarcFile::from_reader(io::Cursor::new(data)).unwrap())
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[1], good);
+        assert_eq!(records[2], good);
+
+        // Too long: the next record's header is found inside the block
+        let data = [record_with_length(body, body.len() + 40), good.clone(), good.clone()].concat();
+        let records: Vec<Vec<u8>> = Records::new(WarcFile::from_reader(io::Cursor::new(data)).unwrap())
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(records.len(), 3);
+        assert!(records[0].ends_with(b"0123456789\r\n\r\n"));
+        assert_eq!(records[1], good);
+        assert_eq!(records[2], good);
+
+        // Too long on the last record, with nothing to realign on: the file
+        // looks cut off, so what there is of the record is returned
+        let data = [good.clone(), record_with_length(body, body.len() + 40)].concat();
+        let mut records = Records::new(WarcFile::from_reader(io::Cursor::new(data)).unwrap());
+        assert_eq!(records.next().unwrap().unwrap(), good);
+        assert!(!records.truncated());
+        assert!(records.next().unwrap().unwrap().ends_with(b"0123456789\r\n\r\n"));
+        assert!(records.truncated());
+        assert!(records.next().is_none());
+    }
+
+    #[test]
+    fn test_records_untrusted_content_length() {
+        let body = b"HTTP/1.1 200 OK\r\n\r\nWARC/1.0 in the body\r\n\r\n0123456789";
+        let good = warc_record("request", b"GET / HTTP/1.1\r\n\r\n");
+        let read = |data: Vec<u8>, trust: bool| {
+            Records::new(WarcFile::from_reader(io::Cursor::new(data)).unwrap())
+                .with_trust_content_length(trust)
+                .map(|r| r.unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        // Declared empty, the body is found up to the next record
+        let data = [record_with_length(body, 0), good.clone(), record_with_length(body, 0)].concat();
+        let found = warc_record("response", body);
+        assert_eq!(read(data.clone(), false), [found.clone(), good.clone(), found]);
+        // Trusting the length, the body is lost
+        let mut trusted = Records::new(WarcFile::from_reader(io::Cursor::new(data)).unwrap());
+        assert_eq!(trusted.next().unwrap().unwrap(), record_with_length(b"", 0));
+
+        // Declared but never written
+        let data = [record_with_length(b"", 500), good.clone()].concat();
+        assert_eq!(read(data, false), [warc_record("response", b""), good.clone()]);
+
+        let record = &read(record_with_length(body, 0), false)[0];
+        let parsed = crate::parse_warc_record(record, &Default::default()).unwrap();
+        assert_eq!(parsed.http_body.as_deref(), Some(&b"WARC/1.0 in the body\r\n\r\n0123456789"[..]));
+    }
+
+    #[test]
+    fn test_records_truncated_gzip_member() {
+        let records = [
+            warc_record("warcinfo", b"software: test\r\n"),
+            warc_record("request", b"GET / HTTP/1.1\r\n\r\n"),
+            warc_record("response", &[b"HTTP/1.1 200 OK\r\n\r\n".as_slice(), &[b'x'; 4096]].concat()),
+        ];
+        let members: Vec<Vec<u8>> = records
+            .iter()
+            .map(|record| {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::none());
+                encoder.write_all(record).unwrap();
+                encoder.finish().unwrap()
+            })
+            .collect();
+        // Cut the download halfway through the last member
+        let mut data = members.concat();
+        data.truncate(data.len() - members[2].len() / 2);
+
+        let mut read = Records::new(WarcFile::from_reader(io::Cursor::new(data)).unwrap());
+        assert_eq!(read.next().unwrap().unwrap(), records[0]);
+        assert_eq!(read.next().unwrap().unwrap(), records[1]);
+        assert!(!read.truncated());
+        let partial = read.next().unwrap().unwrap();
+        assert!(read.truncated());
+        assert!(partial.len() < records[2].len() && records[2].starts_with(&partial));
+        assert!(read.next().is_none());
+    }
+
+    #[test]
+    fn test_records_date_range() {
+        let dated = |date: &str| {
+            let record = String::from_utf8(warc_record("response", b"HTTP/1.1 200 OK\r\n\r\n")).unwrap();
+            record.replace("2025-11-06T20:10:40Z", date).into_bytes()
+        };
+        let data = [
+            dated("2024-01-01T00:00:00Z"),
+            dated("2025-06-01T12:00:00Z"),
+            dated("2025-06-30T23:59:59.5Z"),
+            dated("2025-07-01T00:00:00Z"),
+            dated("2025-07-01T01:30:00+02:00"),
+            dated("not a date"),
+        ]
+        .concat();
+        let read = |date_range: DateRange| {
+            Records::new(WarcFile::from_reader(io::Cursor::new(data.clone())).unwrap())
+                .with_date_range(date_range)
+                .map(|r| r.unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        let june = DateRange {
+            from: parse_warc_date("2025-06-01"),
+            to: parse_warc_date("2025-07-01"),
+        };
+        let records = read(june);
+        assert_eq!(
+            records,
+            vec![
+                dated("2025-06-01T12:00:00Z"),
+                dated("2025-06-30T23:59:59.5Z"),
+                dated("2025-07-01T01:30:00+02:00"),
+            ]
+        );
+
+        let since_june = DateRange {
+            from: parse_warc_date("2025-06-01"),
+            to: None,
+        };
+        assert_eq!(read(since_june).len(), 4);
+        assert_eq!(read(DateRange::default()).len(), 6);
+    }
+
+    #[test]
+    fn test_records_date_range_skips_blocks() {
+        let dated = |date: &str, body: &[u8]| {
+            let record = String::from_utf8_lossy(&warc_record("response", body)).into_owned();
+            record.replace("2025-11-06T20:10:40Z", date).into_bytes()
+        };
+        let wanted = dated("2025-06-01T00:00:00Z", b"HTTP/1.1 200 OK\r\n\r\nwanted");
+        let big = dated("2024-01-01T00:00:00Z", &[b'x'; 100_000]);
+        // Declared 10 bytes short, so the end of its block is skipped too
+        let short = String::from_utf8(dated("2024-01-01T00:00:00Z", b"HTTP/1.1 200 OK\r\n\r\n0123456789"))
+            .unwrap()
+            .replace("Content-Length: 29", "Content-Length: 19")
+            .into_bytes();
+        let cut = dated("2024-01-01T00:00:00Z", &[b'y'; 1000]);
+        let data = [big, wanted.clone(), short, wanted.clone(), cut[..500].to_vec()].concat();
+        let june = DateRange {
+            from: parse_warc_date("2025-06-01"),
+            to: parse_warc_date("2025-07-01"),
+        };
+
+        // Seeking over the plain file, or reading through the stream
+        let path = temp_file("skip_blocks.warc", &data);
+        let from_file: Vec<_> = Records::new(WarcFile::open(&path).unwrap()).with_date_range(june).collect();
+        std::fs::remove_file(&path).unwrap();
+        let from_stream: Vec<_> =
+            Records::new(WarcFile::from_reader(io::Cursor::new(data)).unwrap()).with_date_range(june).collect();
+        for records in [from_file, from_stream] {
+            let records: Vec<Vec<u8>> = records.into_iter().map(|r| r.unwrap()).collect();
+            assert_eq!(records, [wanted.clone(), wanted.clone()]);
+        }
+    }
+
+    #[test]
+    fn test_records_continue_after_bad_record() {
+        let good = warc_record("response", b"HTTP/1.1 200 OK\r\n\r\ngood");
+        let bad = b"WARC/1.0\r\nWARC-Type: response\r\nContent-Length: many\r\n\r\nHTTP/1.1 200 OK\r\n\r\nbad\r\n\r\n";
+        let data = [good.clone(), bad.to_vec(), good.clone()].concat();
+
+        let records: Vec<io::Result<Vec<u8>>> =
+            Records::new(WarcFile::from_reader(io::Cursor::new(data)).unwrap()).collect();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].as_ref().unwrap(), &good);
+        let error = records[1].as_ref().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(error.to_string(), "record has no valid Content-Length");
+        assert_eq!(records[2].as_ref().unwrap(), &good);
+    }
+
+    #[test]
+    fn test_records_index() {
+        let good = warc_record("response", b"HTTP/1.1 200 OK\r\n\r\n");
+        let old = String::from_utf8(good.clone()).unwrap().replace("2025-11-06", "2020-01-01").into_bytes();
+        let bad = b"WARC/1.0\r\nWARC-Type: response\r\nContent-Length: many\r\n\r\nbad\r\n\r\n";
+        let data = [good.clone(), good.clone(), bad.to_vec(), good.clone(), old, good.clone()].concat();
+        let indices = |date_range: DateRange| {
+            let mut records = Records::new(WarcFile::from_reader(io::Cursor::new(data.clone())).unwrap())
+                .with_date_range(date_range);
+            std::iter::from_fn(|| records.next().map(|_| records.index())).collect::<Vec<_>>()
+        };
+
+        // Sequential, bad records included
+        assert_eq!(indices(DateRange::default()), [0, 1, 2, 3, 4, 5]);
+
+        // Records outside the range keep their place
+        let recent = DateRange {
+            from: parse_warc_date("2025-01-01"),
+            to: None,
+        };
+        assert_eq!(indices(recent), [0, 1, 2, 3, 5]);
+    }
+
+    #[test]
+    fn test_record_heads_missing_content_length() {
+        let path = temp_file("no_length.warc", b"WARC/1.0\r\nWARC-Type: response\r\n\r\nbody");
+        let mut heads = RecordHeads::new(WarcFile::open(&path).unwrap());
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(heads.next().unwrap().is_err());
+        assert!(heads.next().is_none());
+    }
+}
@@ -22,3 +22,37 @@ FROM read_blob('/tmp/test_warc.gz');
 -- Test 3: Full struct output
 SELECT 'Test 3: Full struct' as test;
 SELECT parse_warc(content) FROM read_blob('test-data/example.warc');
+
+-- Test 4: Read gzip members with integrity checks
+SELECT 'Test 4: read_warc_members' as test;
+SELECT member_offset, compressed_size, gzip_crc_ok, (parse_warc(content)).http_status
+FROM read_warc_members('/tmp/test_warc.gz');
+
+-- Test 5: Count record types without parsing bodies
+SELECT 'Test 5: read_warc_types' as test;
+SELECT warc_type, count(*) FROM read_warc_types('test-data/example.warc') GROUP BY warc_type;
+
+-- Test 6: Bulk-load records from a glob
+SELECT 'Test 6: read_warc' as test;
+SELECT filename, warc_version, http_status FROM read_warc('test-data/*.warc');
+
+-- Test 7: Flat columns through unnest and the parse_warc_rows table function
+SELECT 'Test 7: flat columns' as test;
+SELECT unnest(parse_warc(content)) FROM read_blob('test-data/example.warc');
+SET VARIABLE record = (SELECT content FROM read_blob('test-data/example.warc'));
+SELECT http_status, http_protocol FROM parse_warc_rows(getvariable('record'));
+
+-- Test 8: parse_warc after a filter (rows reach the function through a selection vector)
+SELECT 'Test 8: filtered input' as test;
+CREATE TABLE mixed AS
+SELECT i, CASE WHEN i % 3 = 0 THEN (SELECT content FROM read_blob('test-data/example.warc')) ELSE ('junk' || i)::BLOB END AS content
+FROM range(5000) r(i);
+-- Expect 834 (multiples of 6) and 0
+SELECT count(*) FROM mixed WHERE i % 2 = 0 AND (parse_warc(content)).http_status = 200;
+SELECT count((parse_warc(content)).http_status) FROM mixed WHERE i % 3 = 1;
+
+-- Test 9: Full 2048-row chunks through the struct outputs
+SELECT 'Test 9: full chunks' as test;
+-- Expect 20000 and 20000
+SELECT count((parse_warc(content)).http_status), count((parse_warc_meta(content)).http_status)
+FROM read_blob('test-data/example.warc'), range(20000);